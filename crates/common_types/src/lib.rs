@@ -1,13 +1,16 @@
 use serde::{Serialize,Deserialize};
 use solana_sdk::pubkey::Pubkey;
 
+pub mod liquidity;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PoolEventCreated {
   pub program: Pubkey,
   pub pool: Pubkey,
   pub token_a_mint: Pubkey,
   pub token_b_mint: Pubkey,
-  pub fee_bps: Option<u16>,
+  /// Pool trade fee in parts per million (hundredths of a basis point).
+  pub fee_ppm: Option<u32>,
   pub tick_spacing: Option<u16>,
   pub ts_ms: u64,
 }
@@ -48,7 +51,8 @@ pub struct PoolTokenBundle {
   pub program: Pubkey,
   pub token_a: TokenSafetyReport,
   pub token_b: TokenSafetyReport,
-  pub fee_bps: Option<u16>,
+  /// Pool trade fee in parts per million (hundredths of a basis point).
+  pub fee_ppm: Option<u32>,
   pub tick_spacing: Option<u16>,
   pub ts_ms: u64,
 }
@@ -60,6 +64,10 @@ pub struct QuickLiq {
   pub reserves_b: u64,
   pub tvl_quote: Option<f64>,
   pub quote_liquidity: Option<f64>,
+  /// Both sides of a CLMM tradeable within `liq_metrics::DEPTH_PRICE_MOVE`
+  /// of the current price, in quote units.
+  #[serde(default)]
+  pub depth_quote: Option<f64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -71,9 +79,27 @@ pub struct HypeSnapshot {
   pub score: u8,
 }
 
+/// How a CLMM pool's liquidity is split between LP wallets.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LpConcentration {
+  pub positions: u32,
+  pub owners: u32,
+  /// Wallet holding the largest share, when its position owner is known.
+  pub top_owner: Option<Pubkey>,
+  pub top_share: f64,
+  /// A single wallet holds enough liquidity to pull the pool on its own.
+  pub single_lp: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EnrichedPoolAlert {
   pub bundle: PoolTokenBundle,
   pub liq: Option<QuickLiq>,
   pub hype: Option<HypeSnapshot>,
+  #[serde(default)]
+  pub lp: Option<LpConcentration>,
+  /// pump.fun bonding curve the token graduated from, for alerts raised at
+  /// graduation.
+  #[serde(default)]
+  pub graduated_from: Option<Pubkey>,
 }
@@ -0,0 +1,92 @@
+use std::collections::BTreeMap;
+
+/// Active liquidity between two ticks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LiquidityRange {
+  pub tick_lower: i32,
+  pub tick_upper: i32,
+  pub liquidity: u128,
+}
+
+/// Raw token amounts a pool can trade before the price moves by a given ratio.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Depth {
+  /// Token A bought out of the pool while the price rises.
+  pub base_up: f64,
+  /// Token B bought out of the pool while the price falls.
+  pub quote_down: f64,
+}
+
+/// Liquidity by price for a CLMM pool, limited to the ticks covered by the
+/// tick arrays it was built from.
+#[derive(Debug, Clone)]
+pub struct LiquidityDistribution {
+  pub tick_current: i32,
+  pub sqrt_price_x64: u128,
+  pub liquidity: u128,
+  /// Initialized ticks and their `liquidity_net`.
+  pub ticks: BTreeMap<i32, i128>,
+  /// Lowest and highest tick covered by the loaded arrays.
+  pub covered: (i32, i32),
+}
+
+impl LiquidityDistribution {
+  /// Ranges of constant liquidity across the covered ticks, in ascending order.
+  /// Every tick comes from a loaded array, so walking outwards from the
+  /// current tick never leaves the covered range.
+  pub fn ranges(&self) -> Vec<LiquidityRange> {
+    let (lo, hi) = self.covered;
+    let mut ranges = Vec::new();
+    let mut upper = self.tick_current.clamp(lo, hi);
+    let mut liquidity = self.liquidity as i128;
+    for (&tick, &net) in self.ticks.range(..=self.tick_current).rev() {
+      ranges.push(LiquidityRange { tick_lower: tick, tick_upper: upper, liquidity: liquidity.max(0) as u128 });
+      liquidity -= net;
+      upper = tick;
+    }
+    if upper > lo {
+      ranges.push(LiquidityRange { tick_lower: lo, tick_upper: upper, liquidity: liquidity.max(0) as u128 });
+    }
+    ranges.reverse();
+
+    let mut lower = self.tick_current.clamp(lo, hi);
+    let mut liquidity = self.liquidity as i128;
+    for (&tick, &net) in self.ticks.range(self.tick_current + 1..) {
+      ranges.push(LiquidityRange { tick_lower: lower, tick_upper: tick, liquidity: liquidity.max(0) as u128 });
+      liquidity += net;
+      lower = tick;
+    }
+    if lower < hi {
+      ranges.push(LiquidityRange { tick_lower: lower, tick_upper: hi, liquidity: liquidity.max(0) as u128 });
+    }
+    ranges.retain(|r| r.tick_lower < r.tick_upper && r.liquidity > 0);
+    ranges
+  }
+
+  /// Amounts tradeable before the price moves up or down by `price_move`
+  /// (0.02 for 2%). Ticks outside the covered range count as empty.
+  pub fn depth(&self, price_move: f64) -> Depth {
+    let sqrt_current = self.sqrt_price_x64 as f64 / 2f64.powi(64);
+    let step = (1.0 + price_move).sqrt();
+    let (sqrt_up, sqrt_down) = (sqrt_current * step, sqrt_current / step);
+    let mut depth = Depth::default();
+    for range in self.ranges() {
+      let liquidity = range.liquidity as f64;
+      let (sqrt_lower, sqrt_upper) = (sqrt_price_at(range.tick_lower), sqrt_price_at(range.tick_upper));
+
+      let (lo, hi) = (sqrt_lower.max(sqrt_current), sqrt_upper.min(sqrt_up));
+      if hi > lo {
+        depth.base_up += liquidity * (1.0 / lo - 1.0 / hi);
+      }
+      let (lo, hi) = (sqrt_lower.max(sqrt_down), sqrt_upper.min(sqrt_current));
+      if hi > lo {
+        depth.quote_down += liquidity * (hi - lo);
+      }
+    }
+    depth
+  }
+}
+
+fn sqrt_price_at(tick: i32) -> f64 {
+  1.0001f64.powf(tick as f64 / 2.0)
+}
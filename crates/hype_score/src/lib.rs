@@ -65,14 +65,11 @@ struct Bucket {
     lp_rems: i32,
 }
 
+#[derive(Default)]
 struct PoolSeries {
     buckets: VecDeque<(u64, Bucket)>,
 }
 
-impl Default for PoolSeries {
-    fn default() -> Self { Self { buckets: VecDeque::new() } }
-}
-
 pub struct HypeAggregator {
     cfg: HypeConfig,
     map: RwLock<HashMap<Pubkey, PoolSeries>>,
@@ -144,6 +141,7 @@ fn classify(logs: &[String]) -> (bool,bool,bool,bool,bool) {
     (is_swap,is_buy,is_sell,lp_add,lp_rem)
 }
 
+#[allow(clippy::too_many_arguments)]
 fn score_simple(w1:f32, w2:f32, w3:f32, w4:f32,
                 swaps:u32, uniq:u32, bsr:f32, lp:i32) -> u8 {
     let n_swaps = (swaps as f32 / 50.0).min(1.0);
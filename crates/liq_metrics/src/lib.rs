@@ -1,7 +1,11 @@
-use anyhow::{Result, Context};
+use anyhow::Result;
 use solana_client::rpc_client::RpcClient;
 use solana_sdk::{pubkey::Pubkey, account::Account};
 use common_types::QuickLiq;
+use common_types::liquidity::LiquidityDistribution;
+
+/// Price move used to measure CLMM depth (2%).
+pub const DEPTH_PRICE_MOVE: f64 = 0.02;
 
 #[derive(Clone)]
 pub struct PoolInput {
@@ -13,9 +17,30 @@ pub struct PoolInput {
     pub decimals_b: u8,
     pub vault_a: Option<Pubkey>,
     pub vault_b: Option<Pubkey>,
+    /// Vault balance that is not pool liquidity (uncollected fees, pending PnL).
+    pub vault_fees_a: u64,
+    pub vault_fees_b: u64,
+    /// Meteora vaults holding the liquidity of a dynamic AMM (v1) pool, in
+    /// place of `vault_a` and `vault_b`.
+    pub vault_shares: Option<VaultShares>,
     pub sqrt_price_x64: Option<u128>,
     pub is_clmm: bool,
+    /// Active bin and bin step for liquidity-book pools (Meteora DLMM).
+    pub active_bin: Option<(i32, u16)>,
     pub quote_mints: Vec<Pubkey>,
+    /// Liquidity by price of a CLMM. Depth is measured from it instead of the
+    /// vault balances, which include liquidity far from the current price.
+    pub liquidity: Option<LiquidityDistribution>,
+}
+
+/// A pool's stake in two Meteora vaults: the vault accounts and the pool's
+/// token accounts of each vault's LP mint.
+#[derive(Clone, Copy, Debug)]
+pub struct VaultShares {
+    pub vault_a: Pubkey,
+    pub vault_b: Pubkey,
+    pub vault_lp_a: Pubkey,
+    pub vault_lp_b: Pubkey,
 }
 
 pub fn compute_quick(
@@ -24,7 +49,12 @@ pub fn compute_quick(
 ) -> Result<QuickLiq> {
     let (reserves_a, reserves_b) = if let (Some(v_a), Some(v_b)) = (inp.vault_a, inp.vault_b) {
         let accs = rpc.get_multiple_accounts(&[v_a, v_b])?;
-        (read_token_balance(accs.get(0)), read_token_balance(accs.get(1)))
+        (
+            read_token_balance(accs.first()).saturating_sub(inp.vault_fees_a),
+            read_token_balance(accs.get(1)).saturating_sub(inp.vault_fees_b),
+        )
+    } else if let Some(shares) = &inp.vault_shares {
+        vault_share_reserves(rpc, shares)?
     } else { (0u64, 0u64) };
 
     let price_ab = if inp.is_clmm {
@@ -32,14 +62,14 @@ pub fn compute_quick(
             let p = price_from_sqrtp_q64(sp, inp.decimals_a, inp.decimals_b);
             Some(p)
         } else { None }
-    } else {
-        if reserves_a > 0 && reserves_b > 0 {
-            let adj = 10f64.powi((inp.decimals_a as i32) - (inp.decimals_b as i32));
-            Some((reserves_b as f64 / reserves_a as f64) / adj)
-        } else { None }
-    };
+    } else if let Some((active_id, bin_step)) = inp.active_bin {
+        Some(price_from_bin(active_id, bin_step, inp.decimals_a, inp.decimals_b))
+    } else if reserves_a > 0 && reserves_b > 0 {
+        let adj = 10f64.powi((inp.decimals_a as i32) - (inp.decimals_b as i32));
+        Some((reserves_b as f64 / reserves_a as f64) / adj)
+    } else { None };
 
-    let (tvl_quote, qliq) = if let Some(is_a_quote) = is_quote(&inp.mint_a, &inp.mint_b, &inp.quote_mints) {
+    let (tvl_quote, qliq, depth) = if let Some(is_a_quote) = is_quote(&inp.mint_a, &inp.mint_b, &inp.quote_mints) {
         let (dec_quote, dec_other, reserves_quote, reserves_other, price_other_in_quote) =
             if is_a_quote {
                 (inp.decimals_a, inp.decimals_b, reserves_a, reserves_b, price_ab.map(|p| p.recip()))
@@ -52,9 +82,17 @@ pub fn compute_quick(
             let other_in_quote = o_ui * p_oiq;
             let tvl = q + other_in_quote;
             let qliq = q.min(other_in_quote);
-            (Some(tvl), Some(qliq))
-        } else { (None, None) }
-    } else { (None, None) };
+            let depth = inp.liquidity.as_ref().map(|dist| {
+                let depth = dist.depth(DEPTH_PRICE_MOVE);
+                let (raw_quote, raw_other) =
+                    if is_a_quote { (depth.base_up, depth.quote_down) } else { (depth.quote_down, depth.base_up) };
+                (raw_quote / 10f64.powi(dec_quote as i32), raw_other / 10f64.powi(dec_other as i32) * p_oiq)
+            });
+            (Some(tvl), Some(qliq), depth)
+        } else { (None, None, None) }
+    } else { (None, None, None) };
+    // a CLMM can only absorb what sits near the price, whatever its vaults hold
+    let qliq = depth.map(|(quote, other)| quote.min(other)).or(qliq);
 
     Ok(QuickLiq {
         price_ab,
@@ -62,6 +100,7 @@ pub fn compute_quick(
         reserves_b,
         tvl_quote,
         quote_liquidity: qliq,
+        depth_quote: depth.map(|(quote, other)| quote + other),
     })
 }
 
@@ -77,6 +116,42 @@ fn read_token_balance(maybe_acc: Option<&Option<Account>>) -> u64 {
     0
 }
 
+/// Tokens of each Meteora vault owed to the pool: its share of the vault LP
+/// supply times the amount the vault holds.
+fn vault_share_reserves(rpc: &RpcClient, shares: &VaultShares) -> Result<(u64, u64)> {
+    let accs = rpc.get_multiple_accounts(&[shares.vault_a, shares.vault_b, shares.vault_lp_a, shares.vault_lp_b])?;
+    let vault = |i: usize| accs.get(i).and_then(Option::as_ref).and_then(|acc| read_vault(&acc.data));
+    let (Some((total_a, lp_mint_a)), Some((total_b, lp_mint_b))) = (vault(0), vault(1)) else {
+        return Ok((0, 0));
+    };
+    let mints = rpc.get_multiple_accounts(&[lp_mint_a, lp_mint_b])?;
+    let share = |total: u64, lp: Option<&Option<Account>>, mint: Option<&Option<Account>>| {
+        let supply = read_mint_supply(mint);
+        if supply == 0 {
+            return 0;
+        }
+        (total as u128 * read_token_balance(lp) as u128 / supply as u128) as u64
+    };
+    Ok((
+        share(total_a, accs.get(2), mints.first()),
+        share(total_b, accs.get(3), mints.get(1)),
+    ))
+}
+
+/// `total_amount` and `lp_mint` of a Meteora dynamic vault.
+fn read_vault(data: &[u8]) -> Option<(u64, Pubkey)> {
+    let total = u64::from_le_bytes(data.get(11..19)?.try_into().ok()?);
+    let lp_mint = Pubkey::new_from_array(data.get(115..147)?.try_into().ok()?);
+    Some((total, lp_mint))
+}
+
+fn read_mint_supply(maybe_acc: Option<&Option<Account>>) -> u64 {
+    match maybe_acc {
+        Some(Some(acc)) if acc.data.len() >= 44 => u64::from_le_bytes(acc.data[36..44].try_into().unwrap()),
+        _ => 0,
+    }
+}
+
 fn price_from_sqrtp_q64(sqrt_price_x64: u128, dec_a: u8, dec_b: u8) -> f64 {
     let sp = sqrt_price_x64 as f64;
     let p = (sp * sp) / (2f64.powi(128));
@@ -84,6 +159,13 @@ fn price_from_sqrtp_q64(sqrt_price_x64: u128, dec_a: u8, dec_b: u8) -> f64 {
     p * adj
 }
 
+/// Liquidity-book bin price: `(1 + bin_step / 10_000) ^ active_id`, in raw units.
+fn price_from_bin(active_id: i32, bin_step: u16, dec_a: u8, dec_b: u8) -> f64 {
+    let p = (1.0 + bin_step as f64 / 10_000.0).powi(active_id);
+    let adj = 10f64.powi((dec_a as i32) - (dec_b as i32));
+    p * adj
+}
+
 fn units_to_ui(amount: u64, decimals: u8) -> f64 {
     (amount as f64) / 10f64.powi(decimals as i32)
 }
@@ -0,0 +1,151 @@
+use anyhow::Result;
+use dashmap::{DashMap, DashSet};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::sync::Arc;
+use crate::decoders::{
+    orca_whirl::{self, FeeTier, WhirlpoolsConfig},
+    raydium_clmm::{self, ClmmAmmConfig},
+    raydium_cpmm::{self, CpmmAmmConfig},
+};
+use crate::source::AccountSource;
+use crate::types::{DexKind, FeeRate, PoolInfo};
+
+/// Config account shared by the pools of a DEX.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ConfigAccount {
+    RaydiumClmm(ClmmAmmConfig),
+    RaydiumCpmm(CpmmAmmConfig),
+    WhirlpoolsConfig(WhirlpoolsConfig),
+    WhirlpoolFeeTier(FeeTier),
+}
+
+impl ConfigAccount {
+    /// Parse a config account of a `dex` program.
+    pub fn parse(dex: DexKind, data: &[u8]) -> Option<Self> {
+        match dex {
+            DexKind::RaydiumClmm => raydium_clmm::parse_amm_config(data).map(Self::RaydiumClmm),
+            DexKind::RaydiumCpmm => raydium_cpmm::parse_amm_config(data).map(Self::RaydiumCpmm),
+            DexKind::OrcaWhirlpools => orca_whirl::parse_config(data),
+            _ => None,
+        }
+    }
+
+    /// Trade fee charged by the pools referencing this config.
+    pub fn trade_fee(&self) -> Option<FeeRate> {
+        match self {
+            ConfigAccount::RaydiumClmm(c) => Some(FeeRate::from_ppm(c.trade_fee_rate)),
+            ConfigAccount::RaydiumCpmm(c) => u32::try_from(c.trade_fee_rate).ok().map(FeeRate::from_ppm),
+            // Whirlpools keep their own fee; configs and tiers only seed new pools
+            ConfigAccount::WhirlpoolsConfig(_) | ConfigAccount::WhirlpoolFeeTier(_) => None,
+        }
+    }
+}
+
+/// Config accounts referenced by the watched pools, keyed by address.
+/// [`PoolWatcher`](crate::PoolWatcher) fills it from snapshots and the
+/// program subscription, and fetches configs it has not seen yet.
+#[derive(Clone, Default)]
+pub struct ConfigRegistry {
+    configs: Arc<DashMap<Pubkey, ConfigAccount>>,
+    // configs being fetched, so that pools sharing one trigger a single request
+    pending: Arc<DashSet<Pubkey>>,
+    // configs fetched but missing or unparseable, not fetched again until the
+    // next resync or until the subscription delivers them
+    absent: Arc<DashSet<Pubkey>>,
+}
+
+impl ConfigRegistry {
+    pub fn get(&self, key: &Pubkey) -> Option<ConfigAccount> {
+        self.configs.get(key).map(|c| c.clone())
+    }
+
+    /// Store `config`, returning whether the trade fee it sets changed.
+    pub fn insert(&self, key: Pubkey, config: ConfigAccount) -> bool {
+        let fee = config.trade_fee();
+        let previous = self.configs.insert(key, config);
+        self.pending.remove(&key);
+        self.absent.remove(&key);
+        previous.and_then(|c| c.trade_fee()) != fee
+    }
+
+    /// Fee tier of a Whirlpool config for `tick_spacing`, if seen.
+    pub fn fee_tier(&self, whirlpools_config: &Pubkey, tick_spacing: u16) -> Option<FeeTier> {
+        match self.get(&orca_whirl::fee_tier_address(whirlpools_config, tick_spacing))? {
+            ConfigAccount::WhirlpoolFeeTier(tier) => Some(tier),
+            _ => None,
+        }
+    }
+
+    /// Set the fee of `info` from its config, when the config determines it.
+    pub fn apply(&self, info: &mut PoolInfo) {
+        if let Some(fee) = info.config.and_then(|key| self.get(&key)).and_then(|c| c.trade_fee()) {
+            info.fee = Some(fee);
+        }
+    }
+
+    /// Config accounts referenced by `info` that are neither known, being
+    /// fetched nor found absent.
+    pub fn missing(&self, info: &PoolInfo) -> Vec<Pubkey> {
+        referenced(info)
+            .into_iter()
+            .filter(|key| !self.configs.contains_key(key) && !self.pending.contains(key) && !self.absent.contains(key))
+            .collect()
+    }
+
+    /// Let the configs an earlier fetch did not find be fetched again.
+    pub fn retry_absent(&self) {
+        self.absent.clear();
+    }
+
+    /// Fetch the configs `pools` reference but the registry lacks. Returns the
+    /// configs whose trade fee changed, i.e. whose pools need their fee set.
+    pub async fn fetch_missing<'a>(
+        &self,
+        source: &dyn AccountSource,
+        pools: impl IntoIterator<Item = &'a PoolInfo>,
+    ) -> Result<Vec<Pubkey>> {
+        let mut wanted: Vec<(Pubkey, DexKind)> = Vec::new();
+        for info in pools {
+            for key in self.missing(info) {
+                if self.pending.insert(key) {
+                    wanted.push((key, info.dex));
+                }
+            }
+        }
+        let keys: Vec<Pubkey> = wanted.iter().map(|(key, _)| *key).collect();
+        let accounts = match source.get_accounts(&keys).await {
+            Ok(accounts) => accounts,
+            Err(e) => {
+                for key in &keys {
+                    self.pending.remove(key);
+                }
+                return Err(e);
+            }
+        };
+        let mut changed = Vec::new();
+        for ((key, dex), account) in wanted.iter().zip(accounts) {
+            match ConfigAccount::parse(*dex, &account.data) {
+                Some(config) => {
+                    if self.insert(*key, config) {
+                        changed.push(*key);
+                    }
+                }
+                None => {
+                    self.absent.insert(*key);
+                    self.pending.remove(key);
+                }
+            }
+        }
+        Ok(changed)
+    }
+}
+
+/// Config accounts a pool depends on.
+fn referenced(info: &PoolInfo) -> Vec<Pubkey> {
+    let Some(config) = info.config else { return Vec::new() };
+    match (info.dex, info.tick_spacing) {
+        (DexKind::OrcaWhirlpools, Some(spacing)) => vec![config, orca_whirl::fee_tier_address(&config, spacing)],
+        _ => vec![config],
+    }
+}
@@ -0,0 +1,322 @@
+use std::{collections::HashMap, path::PathBuf};
+use anyhow::{anyhow, bail, Context};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use solana_sdk::pubkey::Pubkey;
+use crate::decoders::{account_type, route_anchor, AccountTypes, DecodeOutcome, PoolDecoder, PoolLayout};
+use crate::types::{DexKind, DexState, FeeRate, PoolId, PoolInfo};
+
+/// Where to find a program's Anchor IDL and which fields of its pool account
+/// map onto [`PoolInfo`]. Field names may be dotted paths into nested structs.
+#[derive(Clone, Debug, Deserialize)]
+pub struct IdlDecoderConfig {
+    pub path: PathBuf,
+    /// Name of the pool account type in the IDL.
+    pub account: String,
+    pub mint_a: String,
+    pub mint_b: String,
+    pub fee: Option<String>,
+    /// Denominator of the `fee` field; the default reads it as ppm.
+    #[serde(default = "default_fee_denominator")]
+    pub fee_denominator: u64,
+    pub vault_a: Option<String>,
+    pub vault_b: Option<String>,
+    pub lp_mint: Option<String>,
+    pub tick_spacing: Option<String>,
+    pub sqrt_price: Option<String>,
+    pub liquidity: Option<String>,
+    pub tick_current: Option<String>,
+}
+
+fn default_fee_denominator() -> u64 {
+    1_000_000
+}
+
+/// Decoder built at runtime from an Anchor IDL. Both the 0.30+ IDL format
+/// and the legacy one are understood. Accounts are read sequentially as borsh;
+/// zero-copy layouts are only supported when they are packed or pad explicitly.
+pub struct IdlDecoder {
+    program: Pubkey,
+    kind: DexKind,
+    account_types: &'static AccountTypes,
+    pool_type: &'static str,
+    layout: Value,
+    types: HashMap<String, Value>,
+    cfg: IdlDecoderConfig,
+}
+
+impl IdlDecoder {
+    pub fn load(program: Pubkey, kind: DexKind, cfg: &IdlDecoderConfig) -> anyhow::Result<Self> {
+        let raw = std::fs::read_to_string(&cfg.path)
+            .with_context(|| format!("reading IDL {}", cfg.path.display()))?;
+        let idl: Value = serde_json::from_str(&raw)
+            .with_context(|| format!("parsing IDL {}", cfg.path.display()))?;
+        Self::from_idl(program, kind, &idl, cfg.clone())
+    }
+
+    pub fn from_idl(
+        program: Pubkey,
+        kind: DexKind,
+        idl: &Value,
+        cfg: IdlDecoderConfig,
+    ) -> anyhow::Result<Self> {
+        let types: HashMap<String, Value> = idl["types"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|t| Some((t["name"].as_str()?.to_string(), t.clone())))
+            .collect();
+
+        let mut account_types = Vec::new();
+        let mut layout = None;
+        for account in idl["accounts"].as_array().into_iter().flatten() {
+            let name = account["name"].as_str().ok_or_else(|| anyhow!("IDL account without a name"))?;
+            let disc = match account["discriminator"].as_array() {
+                Some(bytes) => bytes
+                    .iter()
+                    .map(|b| b.as_u64().and_then(|b| u8::try_from(b).ok()))
+                    .collect::<Option<Vec<u8>>>()
+                    .and_then(|v| <[u8; 8]>::try_from(v).ok())
+                    .ok_or_else(|| anyhow!("bad discriminator for account {name}"))?,
+                None => anchor_discriminator(name),
+            };
+            if name == cfg.account {
+                // legacy IDLs inline the layout in the account entry
+                layout = Some(if account["type"].is_object() {
+                    account.clone()
+                } else {
+                    types.get(name).cloned().ok_or_else(|| anyhow!("no type for account {name}"))?
+                });
+            }
+            // names are leaked once per configured program to fit `DecodeOutcome`
+            account_types.push((disc, &*Box::leak(name.to_string().into_boxed_str())));
+        }
+        let layout = layout.ok_or_else(|| anyhow!("account {} not found in IDL", cfg.account))?;
+        let pool_type = account_types
+            .iter()
+            .find(|(_, name)| *name == cfg.account)
+            .map(|(_, name)| *name)
+            .expect("pool account was registered");
+
+        let field_names: Vec<&str> = layout["type"]["fields"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|f| f["name"].as_str())
+            .collect();
+        for path in cfg.field_paths() {
+            let top = path.split('.').next().unwrap_or_default();
+            if !field_names.contains(&top) {
+                bail!("field {top} not found in account {}", cfg.account);
+            }
+        }
+
+        Ok(Self {
+            program,
+            kind,
+            account_types: Box::leak(account_types.into_boxed_slice()),
+            pool_type,
+            layout,
+            types,
+            cfg,
+        })
+    }
+
+    /// All fields of a pool account as JSON. 128-bit integers are strings.
+    pub fn decode_fields(&self, data: &[u8]) -> Option<Value> {
+        let mut reader = Reader { data, pos: 8 };
+        self.read_typedef(&self.layout, &mut reader)
+    }
+
+    fn to_pool(&self, program: Pubkey, account: Pubkey, data: &[u8]) -> Option<PoolInfo> {
+        let state = self.decode_fields(data)?;
+        let cfg = &self.cfg;
+        let pubkey = |path: Option<&str>| -> Option<Pubkey> {
+            lookup(&state, path?)?.as_str()?.parse().ok()
+        };
+        let int = |path: Option<&str>| -> Option<i128> { as_int(lookup(&state, path?)?) };
+        let fee = int(cfg.fee.as_deref())
+            .and_then(|f| u64::try_from(f).ok())
+            .and_then(|f| FeeRate::from_ratio(f, cfg.fee_denominator));
+
+        Some(PoolInfo {
+            dex: self.kind,
+            id: PoolId { program, account },
+            base_mint: Some(pubkey(Some(&cfg.mint_a))?),
+            quote_mint: Some(pubkey(Some(&cfg.mint_b))?),
+            fee,
+            config: None,
+            tick_spacing: int(cfg.tick_spacing.as_deref()).and_then(|v| u16::try_from(v).ok()),
+            lp_mint: pubkey(cfg.lp_mint.as_deref()),
+            is_token2022_base: false,
+            is_token2022_quote: false,
+            base_vault: pubkey(cfg.vault_a.as_deref()),
+            quote_vault: pubkey(cfg.vault_b.as_deref()),
+            sqrt_price_x64: int(cfg.sqrt_price.as_deref()).and_then(|v| u128::try_from(v).ok()),
+            liquidity: int(cfg.liquidity.as_deref()).and_then(|v| u128::try_from(v).ok()),
+            tick_current: int(cfg.tick_current.as_deref()).and_then(|v| i32::try_from(v).ok()),
+            swap_enabled: None,
+            open_at: None,
+            state: Some(DexState::Other(Box::new(state))),
+        })
+    }
+
+    fn read_typedef(&self, def: &Value, r: &mut Reader) -> Option<Value> {
+        let ty = &def["type"];
+        match ty["kind"].as_str()? {
+            "struct" => self.read_fields(ty.get("fields").unwrap_or(&Value::Null), r),
+            "enum" => {
+                let variant = ty["variants"].as_array()?.get(r.take(1)?[0] as usize)?;
+                let name = variant["name"].as_str()?.to_string();
+                match variant.get("fields") {
+                    Some(fields) => {
+                        let mut map = Map::new();
+                        map.insert(name, self.read_fields(fields, r)?);
+                        Some(Value::Object(map))
+                    }
+                    None => Some(Value::String(name)),
+                }
+            }
+            "type" => self.read_type(&ty["alias"], r),
+            _ => None,
+        }
+    }
+
+    /// Named fields become an object, tuple fields an array.
+    fn read_fields(&self, fields: &Value, r: &mut Reader) -> Option<Value> {
+        let fields = match fields {
+            Value::Null => return Some(Value::Object(Map::new())),
+            f => f.as_array()?,
+        };
+        if fields.iter().all(|f| f.get("name").is_some()) {
+            let mut map = Map::new();
+            for field in fields {
+                map.insert(field["name"].as_str()?.to_string(), self.read_type(&field["type"], r)?);
+            }
+            Some(Value::Object(map))
+        } else {
+            fields.iter().map(|ty| self.read_type(ty, r)).collect::<Option<Vec<_>>>().map(Value::Array)
+        }
+    }
+
+    fn read_type(&self, ty: &Value, r: &mut Reader) -> Option<Value> {
+        if let Some(name) = ty.as_str() {
+            return r.read_primitive(name);
+        }
+        if let Some([inner, len]) = ty.get("array").and_then(Value::as_array).map(Vec::as_slice) {
+            let len = len.as_u64()? as usize;
+            return (0..len).map(|_| self.read_type(inner, r)).collect::<Option<Vec<_>>>().map(Value::Array);
+        }
+        if let Some(inner) = ty.get("option") {
+            return match r.take(1)?[0] {
+                0 => Some(Value::Null),
+                _ => self.read_type(inner, r),
+            };
+        }
+        if let Some(inner) = ty.get("coption") {
+            return match u32::from_le_bytes(r.take(4)?.try_into().ok()?) {
+                0 => Some(Value::Null),
+                _ => self.read_type(inner, r),
+            };
+        }
+        if let Some(inner) = ty.get("vec") {
+            let len = u32::from_le_bytes(r.take(4)?.try_into().ok()?) as usize;
+            return (0..len).map(|_| self.read_type(inner, r)).collect::<Option<Vec<_>>>().map(Value::Array);
+        }
+        let defined = ty.get("defined")?;
+        let name = defined.as_str().or_else(|| defined["name"].as_str())?;
+        self.read_typedef(self.types.get(name)?, r)
+    }
+}
+
+impl IdlDecoderConfig {
+    fn field_paths(&self) -> impl Iterator<Item = &String> {
+        [&self.fee, &self.vault_a, &self.vault_b, &self.lp_mint, &self.tick_spacing, &self.sqrt_price, &self.liquidity, &self.tick_current]
+            .into_iter()
+            .flatten()
+            .chain([&self.mint_a, &self.mint_b])
+    }
+}
+
+impl PoolDecoder for IdlDecoder {
+    fn kind(&self) -> DexKind { self.kind }
+    fn program_ids(&self) -> Vec<Pubkey> { vec![self.program] }
+    fn matches(&self, data: &[u8]) -> bool { account_type(data, self.account_types).is_some() }
+    fn decode(&self, program: Pubkey, account: Pubkey, data: &[u8]) -> DecodeOutcome {
+        route_anchor(data, self.account_types, self.pool_type, || self.to_pool(program, account, data))
+    }
+    fn pool_layouts(&self) -> Vec<PoolLayout> {
+        self.account_types
+            .iter()
+            .filter(|(_, name)| *name == self.pool_type)
+            .map(|(disc, _)| PoolLayout { discriminator: Some(*disc), data_size: None, mint_offsets: None })
+            .collect()
+    }
+}
+
+/// `sha256("account:<name>")[..8]`, used by IDLs that predate explicit discriminators.
+pub fn anchor_discriminator(name: &str) -> [u8; 8] {
+    let hash = Sha256::digest(format!("account:{name}"));
+    hash[..8].try_into().expect("sha256 is 32 bytes")
+}
+
+fn lookup<'a>(state: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(state, |v, key| v.get(key))
+}
+
+fn as_int(value: &Value) -> Option<i128> {
+    match value {
+        Value::Number(n) => n.as_i64().map(i128::from).or_else(|| n.as_u64().map(i128::from)),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos.checked_add(n)?)?;
+        self.pos += n;
+        Some(bytes)
+    }
+
+    fn read_primitive(&mut self, name: &str) -> Option<Value> {
+        macro_rules! int {
+            ($t:ty) => {
+                Value::from(<$t>::from_le_bytes(self.take(std::mem::size_of::<$t>())?.try_into().ok()?))
+            };
+        }
+        Some(match name {
+            "bool" => Value::Bool(self.take(1)?[0] != 0),
+            "u8" => int!(u8),
+            "i8" => int!(i8),
+            "u16" => int!(u16),
+            "i16" => int!(i16),
+            "u32" => int!(u32),
+            "i32" => int!(i32),
+            "u64" => int!(u64),
+            "i64" => int!(i64),
+            "f32" => int!(f32),
+            "f64" => int!(f64),
+            "u128" => Value::String(u128::from_le_bytes(self.take(16)?.try_into().ok()?).to_string()),
+            "i128" => Value::String(i128::from_le_bytes(self.take(16)?.try_into().ok()?).to_string()),
+            "pubkey" | "publicKey" => {
+                Value::String(Pubkey::new_from_array(self.take(32)?.try_into().ok()?).to_string())
+            }
+            "string" => {
+                let len = u32::from_le_bytes(self.take(4)?.try_into().ok()?) as usize;
+                Value::String(String::from_utf8(self.take(len)?.to_vec()).ok()?)
+            }
+            "bytes" => {
+                let len = u32::from_le_bytes(self.take(4)?.try_into().ok()?) as usize;
+                Value::from(self.take(len)?.to_vec())
+            }
+            _ => return None,
+        })
+    }
+}
@@ -0,0 +1,196 @@
+use serde::{Deserialize, Serialize};
+use solana_sdk::{pubkey, pubkey::Pubkey};
+use crate::decoders::{account_type, route_anchor, AccountTypes, DecodeOutcome, read_pubkey, read_u128, read_u16, read_u64};
+use crate::types::{DexKind, DexState, FeeRate, OpenAt, PoolId, PoolInfo};
+
+/// Dynamic AMM v1.
+pub const V1_PROGRAM_ID: Pubkey = pubkey!("Eo7WjKq67rjJQSZxS6z3YkapzY3eMj6Xy8X5EQVn5UaB");
+/// DAMM v2 (cp-amm), decoded by the same decoder.
+pub const V2_PROGRAM_ID: Pubkey = pubkey!("cpamdpZCGKUy5JxQXB4dcpGPiikHswburH8Y7u8MQmG");
+/// Account types of both programs; `Pool` and `Config` exist in each.
+pub const ACCOUNT_TYPES: &AccountTypes = &[
+    ([241, 154, 109, 4, 17, 177, 109, 188], "Pool"),
+    ([155, 12, 170, 224, 30, 250, 204, 130], "Config"),
+    ([190, 106, 121, 6, 200, 182, 21, 75], "LockEscrow"),
+    ([170, 188, 143, 228, 122, 64, 247, 208], "Position"),
+    ([116, 219, 204, 229, 249, 116, 255, 150], "TokenBadge"),
+    ([100, 149, 66, 138, 95, 200, 128, 241], "Vesting"),
+    ([166, 48, 134, 86, 34, 200, 188, 150], "ClaimFeeOperator"),
+];
+/// Anchor discriminator of `Pool`, shared by the v1 and v2 programs.
+pub const POOL_DISCRIMINATOR: [u8; 8] = ACCOUNT_TYPES[0].0;
+/// Size of a DAMM v2 (cp-amm) `Pool` account.
+pub const V2_POOL_LEN: usize = 1112;
+/// DAMM v1 pools are borsh encoded; this covers every field read below.
+pub const V1_POOL_MIN_LEN: usize = 476;
+/// Fees in both programs are expressed with 1e9 precision.
+const FEE_DENOMINATOR: u64 = 1_000_000_000;
+
+/// Meteora Dynamic AMM (v1) `Pool` account. Liquidity sits in Meteora vaults
+/// rather than plain token accounts, so reserves need the vault state.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DammV1State {
+    pub lp_mint: Pubkey,
+    pub token_a_mint: Pubkey,
+    pub token_b_mint: Pubkey,
+    pub a_vault: Pubkey,
+    pub b_vault: Pubkey,
+    pub a_vault_lp: Pubkey,
+    pub b_vault_lp: Pubkey,
+    pub enabled: bool,
+    pub trade_fee_numerator: u64,
+    pub trade_fee_denominator: u64,
+    pub protocol_trade_fee_numerator: u64,
+    pub protocol_trade_fee_denominator: u64,
+    pub pool_type: u8,
+    pub total_locked_lp: u64,
+    pub activation_point: u64,
+    /// 0 means `activation_point` is a slot, 1 a unix timestamp.
+    pub activation_type: u8,
+    pub pool_creator: Pubkey,
+}
+
+/// Meteora DAMM v2 (cp-amm) `Pool` account.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DammV2State {
+    /// Base fee numerator over 1e9 before any fee scheduler reduction.
+    pub cliff_fee_numerator: u64,
+    pub fee_scheduler_mode: u8,
+    pub number_of_period: u16,
+    pub period_frequency: u64,
+    pub reduction_factor: u64,
+    pub protocol_fee_percent: u8,
+    pub partner_fee_percent: u8,
+    pub referral_fee_percent: u8,
+    pub dynamic_fee_initialized: bool,
+    pub token_a_mint: Pubkey,
+    pub token_b_mint: Pubkey,
+    pub token_a_vault: Pubkey,
+    pub token_b_vault: Pubkey,
+    pub partner: Pubkey,
+    pub liquidity: u128,
+    pub protocol_a_fee: u64,
+    pub protocol_b_fee: u64,
+    pub partner_a_fee: u64,
+    pub partner_b_fee: u64,
+    pub sqrt_min_price: u128,
+    pub sqrt_max_price: u128,
+    pub sqrt_price: u128,
+    pub activation_point: u64,
+    /// 0 means `activation_point` is a slot, 1 a unix timestamp.
+    pub activation_type: u8,
+    /// 0 when trading is enabled.
+    pub pool_status: u8,
+    pub collect_fee_mode: u8,
+    pub pool_type: u8,
+}
+
+impl DammV2State {
+    pub fn swap_enabled(&self) -> bool {
+        self.pool_status == 0
+    }
+}
+
+pub fn matches(data: &[u8]) -> bool {
+    account_type(data, ACCOUNT_TYPES).is_some()
+}
+
+pub fn decode_account(program: Pubkey, account: Pubkey, data: &[u8]) -> DecodeOutcome {
+    route_anchor(data, ACCOUNT_TYPES, "Pool", || try_decode(program, account, data))
+}
+
+pub fn parse_v1_state(data: &[u8]) -> Option<DammV1State> {
+    if data.len() < V1_POOL_MIN_LEN { return None; }
+    Some(DammV1State {
+        lp_mint: read_pubkey(data, 8)?,
+        token_a_mint: read_pubkey(data, 40)?,
+        token_b_mint: read_pubkey(data, 72)?,
+        a_vault: read_pubkey(data, 104)?,
+        b_vault: read_pubkey(data, 136)?,
+        a_vault_lp: read_pubkey(data, 168)?,
+        b_vault_lp: read_pubkey(data, 200)?,
+        enabled: *data.get(233)? != 0,
+        trade_fee_numerator: read_u64(data, 330)?,
+        trade_fee_denominator: read_u64(data, 338)?,
+        protocol_trade_fee_numerator: read_u64(data, 346)?,
+        protocol_trade_fee_denominator: read_u64(data, 354)?,
+        pool_type: *data.get(362)?,
+        total_locked_lp: read_u64(data, 395)?,
+        activation_point: read_u64(data, 403)?,
+        pool_creator: read_pubkey(data, 443)?,
+        activation_type: *data.get(475)?,
+    })
+}
+
+pub fn parse_v2_state(data: &[u8]) -> Option<DammV2State> {
+    if data.len() != V2_POOL_LEN { return None; }
+    Some(DammV2State {
+        cliff_fee_numerator: read_u64(data, 8)?,
+        fee_scheduler_mode: *data.get(16)?,
+        number_of_period: read_u16(data, 22)?,
+        period_frequency: read_u64(data, 24)?,
+        reduction_factor: read_u64(data, 32)?,
+        protocol_fee_percent: *data.get(48)?,
+        partner_fee_percent: *data.get(49)?,
+        referral_fee_percent: *data.get(50)?,
+        dynamic_fee_initialized: *data.get(56)? != 0,
+        token_a_mint: read_pubkey(data, 168)?,
+        token_b_mint: read_pubkey(data, 200)?,
+        token_a_vault: read_pubkey(data, 232)?,
+        token_b_vault: read_pubkey(data, 264)?,
+        partner: read_pubkey(data, 328)?,
+        liquidity: read_u128(data, 360)?,
+        protocol_a_fee: read_u64(data, 392)?,
+        protocol_b_fee: read_u64(data, 400)?,
+        partner_a_fee: read_u64(data, 408)?,
+        partner_b_fee: read_u64(data, 416)?,
+        sqrt_min_price: read_u128(data, 424)?,
+        sqrt_max_price: read_u128(data, 440)?,
+        sqrt_price: read_u128(data, 456)?,
+        activation_point: read_u64(data, 472)?,
+        activation_type: *data.get(480)?,
+        pool_status: *data.get(481)?,
+        collect_fee_mode: *data.get(484)?,
+        pool_type: *data.get(485)?,
+    })
+}
+
+/// Decode a Meteora dynamic AMM pool. Both program versions use the `Pool`
+/// account name, so the v2 layout is recognised by its fixed size.
+pub fn try_decode(program: Pubkey, account: Pubkey, data: &[u8]) -> Option<PoolInfo> {
+    if data.get(..8)? != POOL_DISCRIMINATOR { return None; }
+
+    let info = PoolInfo {
+        dex: DexKind::MeteoraDamm,
+        id: PoolId { program, account },
+        ..Default::default()
+    };
+    if data.len() == V2_POOL_LEN {
+        let state = parse_v2_state(data)?;
+        return Some(PoolInfo {
+            base_mint: Some(state.token_a_mint),
+            quote_mint: Some(state.token_b_mint),
+            fee: FeeRate::from_ratio(state.cliff_fee_numerator, FEE_DENOMINATOR),
+            base_vault: Some(state.token_a_vault),
+            quote_vault: Some(state.token_b_vault),
+            sqrt_price_x64: Some(state.sqrt_price),
+            liquidity: Some(state.liquidity),
+            swap_enabled: Some(state.swap_enabled()),
+            open_at: OpenAt::from_activation(state.activation_type, state.activation_point),
+            state: Some(DexState::MeteoraDammV2(Box::new(state))),
+            ..info
+        });
+    }
+
+    let state = parse_v1_state(data)?;
+    Some(PoolInfo {
+        base_mint: Some(state.token_a_mint),
+        quote_mint: Some(state.token_b_mint),
+        fee: FeeRate::from_ratio(state.trade_fee_numerator, state.trade_fee_denominator),
+        lp_mint: Some(state.lp_mint),
+        swap_enabled: Some(state.enabled),
+        open_at: OpenAt::from_activation(state.activation_type, state.activation_point),
+        state: Some(DexState::MeteoraDammV1(Box::new(state))),
+        ..info
+    })
+}
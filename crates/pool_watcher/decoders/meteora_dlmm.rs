@@ -0,0 +1,140 @@
+use serde::{Deserialize, Serialize};
+use solana_sdk::{pubkey, pubkey::Pubkey};
+use crate::decoders::{account_type, route_anchor, AccountTypes, DecodeOutcome, read_i32, read_pubkey, read_u16, read_u32, read_u64};
+use crate::types::{DexKind, DexState, FeeRate, OpenAt, PoolId, PoolInfo};
+
+pub const PROGRAM_ID: Pubkey = pubkey!("LBUZKhRxPF3XUpBCjp4YzTKgLccjZhTSDM9YuVaPwxo");
+pub const ACCOUNT_TYPES: &AccountTypes = &[
+    ([33, 11, 49, 98, 181, 101, 177, 13], "LbPair"),
+    ([92, 142, 92, 220, 5, 148, 70, 181], "BinArray"),
+    ([80, 111, 124, 113, 55, 237, 18, 5], "BinArrayBitmapExtension"),
+    ([139, 194, 131, 179, 140, 179, 229, 244], "Oracle"),
+    ([170, 188, 143, 228, 122, 64, 247, 208], "Position"),
+    ([117, 176, 212, 199, 245, 180, 133, 182], "PositionV2"),
+    ([242, 62, 244, 34, 181, 112, 58, 170], "PresetParameter"),
+    ([171, 236, 148, 115, 162, 113, 222, 174], "PresetParameter2"),
+    ([116, 219, 204, 229, 249, 116, 255, 150], "TokenBadge"),
+    ([166, 48, 134, 86, 34, 200, 188, 150], "ClaimFeeOperator"),
+];
+pub const LB_PAIR_DISCRIMINATOR: [u8; 8] = ACCOUNT_TYPES[0].0;
+/// Size of the `LbPair` account including the discriminator.
+pub const LB_PAIR_LEN: usize = 904;
+/// Fees in DLMM are expressed with 1e9 precision.
+const FEE_PRECISION_PER_PPM: u128 = 1_000;
+
+/// Meteora DLMM `LbPair` account. Token X is the base side, Y the quote side.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LbPairState {
+    pub base_factor: u16,
+    pub filter_period: u16,
+    pub decay_period: u16,
+    pub reduction_factor: u16,
+    pub variable_fee_control: u32,
+    pub max_volatility_accumulator: u32,
+    pub min_bin_id: i32,
+    pub max_bin_id: i32,
+    pub protocol_share: u16,
+    pub base_fee_power_factor: u8,
+    pub volatility_accumulator: u32,
+    pub pair_type: u8,
+    pub active_id: i32,
+    pub bin_step: u16,
+    /// 0 when trading is enabled.
+    pub status: u8,
+    /// 0 means `activation_point` is a slot, 1 a unix timestamp.
+    pub activation_type: u8,
+    pub token_x_mint: Pubkey,
+    pub token_y_mint: Pubkey,
+    pub reserve_x: Pubkey,
+    pub reserve_y: Pubkey,
+    pub protocol_fee_x: u64,
+    pub protocol_fee_y: u64,
+    pub oracle: Pubkey,
+    pub activation_point: u64,
+    pub pre_activation_duration: u64,
+    pub creator: Pubkey,
+}
+
+impl LbPairState {
+    pub const ACTIVATION_SLOT: u8 = 0;
+    pub const ACTIVATION_TIMESTAMP: u8 = 1;
+
+    /// Base fee, excluding the volatility-driven variable component.
+    pub fn base_fee(&self) -> Option<FeeRate> {
+        let fee = (self.base_factor as u128)
+            .checked_mul(self.bin_step as u128)?
+            .checked_mul(10)?
+            .checked_mul(10u128.checked_pow(self.base_fee_power_factor as u32)?)?;
+        u32::try_from(fee / FEE_PRECISION_PER_PPM).ok().map(FeeRate::from_ppm)
+    }
+
+    pub fn swap_enabled(&self) -> bool {
+        self.status == 0
+    }
+}
+
+pub fn matches(data: &[u8]) -> bool {
+    account_type(data, ACCOUNT_TYPES).is_some()
+}
+
+pub fn decode_account(program: Pubkey, account: Pubkey, data: &[u8]) -> DecodeOutcome {
+    route_anchor(data, ACCOUNT_TYPES, "LbPair", || try_decode(program, account, data))
+}
+
+pub fn parse_state(data: &[u8]) -> Option<LbPairState> {
+    if data.len() != LB_PAIR_LEN || !data.starts_with(&LB_PAIR_DISCRIMINATOR) { return None; }
+    Some(LbPairState {
+        base_factor: read_u16(data, 8)?,
+        filter_period: read_u16(data, 10)?,
+        decay_period: read_u16(data, 12)?,
+        reduction_factor: read_u16(data, 14)?,
+        variable_fee_control: read_u32(data, 16)?,
+        max_volatility_accumulator: read_u32(data, 20)?,
+        min_bin_id: read_i32(data, 24)?,
+        max_bin_id: read_i32(data, 28)?,
+        protocol_share: read_u16(data, 32)?,
+        base_fee_power_factor: *data.get(34)?,
+        volatility_accumulator: read_u32(data, 40)?,
+        pair_type: *data.get(75)?,
+        active_id: read_i32(data, 76)?,
+        bin_step: read_u16(data, 80)?,
+        status: *data.get(82)?,
+        activation_type: *data.get(86)?,
+        token_x_mint: read_pubkey(data, 88)?,
+        token_y_mint: read_pubkey(data, 120)?,
+        reserve_x: read_pubkey(data, 152)?,
+        reserve_y: read_pubkey(data, 184)?,
+        protocol_fee_x: read_u64(data, 216)?,
+        protocol_fee_y: read_u64(data, 224)?,
+        oracle: read_pubkey(data, 552)?,
+        activation_point: read_u64(data, 816)?,
+        pre_activation_duration: read_u64(data, 824)?,
+        creator: read_pubkey(data, 848)?,
+    })
+}
+
+/// Decode a Meteora DLMM `LbPair` account.
+pub fn try_decode(program: Pubkey, account: Pubkey, data: &[u8]) -> Option<PoolInfo> {
+    let state = parse_state(data)?;
+
+    Some(PoolInfo {
+        dex: DexKind::MeteoraDlmm,
+        id: PoolId { program, account },
+        base_mint: Some(state.token_x_mint),
+        quote_mint: Some(state.token_y_mint),
+        fee: state.base_fee(),
+        config: None,
+        tick_spacing: None,
+        lp_mint: None,
+        is_token2022_base: false,
+        is_token2022_quote: false,
+        base_vault: Some(state.reserve_x),
+        quote_vault: Some(state.reserve_y),
+        sqrt_price_x64: None,
+        liquidity: None,
+        tick_current: None,
+        swap_enabled: Some(state.swap_enabled()),
+        open_at: OpenAt::from_activation(state.activation_type, state.activation_point),
+        state: Some(DexState::MeteoraDlmm(Box::new(state))),
+    })
+}
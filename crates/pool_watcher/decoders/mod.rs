@@ -1,35 +1,418 @@
+use crate::configs::ConfigAccount;
+use crate::decoders::raydium_clmm::TickArrayBitmapExtension;
+use crate::liquidity::TickArray;
+use crate::positions::Position;
+use crate::source::AccountSource;
 use crate::types::{DexKind, PoolInfo};
+use futures::future::BoxFuture;
+use once_cell::sync::Lazy;
 use solana_sdk::pubkey::Pubkey;
+use std::{collections::HashMap, sync::Arc};
+pub mod idl;
+pub mod meteora_damm;
+pub mod meteora_dlmm;
 pub mod orca_whirl;
+pub mod pump_fun;
+pub mod pump_swap;
+pub mod raydium_amm_v4;
 pub mod raydium_clmm;
+pub mod raydium_cpmm;
 
-// Reuse existing token decoding utilities via crate::token or a passed-in trait.
+/// Tells Token-2022 mints from SPL Token ones, for the flags of [`PoolInfo`].
 pub trait TokenIntrospectionProvider: Send + Sync {
-    fn is_token2022(&self, mint: &Pubkey) -> anyhow::Result<bool>;
+    /// Whether each of `mints` is a Token-2022 mint, in order. Mints the
+    /// provider does not know yet are read through `source`, in one batch.
+    fn token2022_flags<'a>(
+        &'a self,
+        source: &'a dyn AccountSource,
+        mints: &'a [Pubkey],
+    ) -> BoxFuture<'a, anyhow::Result<Vec<bool>>>;
 }
 
-pub fn decode_pool(
+/// Result of decoding one program account.
+#[derive(Debug, Clone)]
+pub enum DecodeOutcome {
+    Pool(Box<PoolInfo>),
+    /// Initialized ticks of a concentrated liquidity pool.
+    TickArray(Box<TickArray>),
+    /// Raydium CLMM bitmap of the tick arrays beyond the pool's own bitmap.
+    TickArrayBitmap(Box<TickArrayBitmapExtension>),
+    /// A liquidity position in a concentrated liquidity pool.
+    Position(Box<Position>),
+    /// A config account whose fields pools depend on (fee tiers).
+    Config(Box<ConfigAccount>),
+    /// A known account type of the program that is not a pool.
+    Other { account_type: &'static str },
+    /// A known account type whose data could not be parsed.
+    Invalid { account_type: &'static str },
+    /// No decoder recognised the account; holds its first eight bytes.
+    Unknown { discriminator: Option<[u8; 8]> },
+}
+
+impl DecodeOutcome {
+    pub fn into_pool(self) -> Option<PoolInfo> {
+        match self {
+            DecodeOutcome::Pool(info) => Some(*info),
+            _ => None,
+        }
+    }
+
+    /// Whether the watcher keeps the decoded account, in the inventory or the
+    /// config registry.
+    pub fn is_stored(&self) -> bool {
+        matches!(
+            self,
+            DecodeOutcome::Pool(_)
+                | DecodeOutcome::TickArray(_)
+                | DecodeOutcome::TickArrayBitmap(_)
+                | DecodeOutcome::Position(_)
+                | DecodeOutcome::Config(_)
+        )
+    }
+}
+
+/// Anchor account types of a program keyed by their 8-byte discriminator,
+/// `sha256("account:<Name>")[..8]`.
+pub type AccountTypes = [([u8; 8], &'static str)];
+
+pub(crate) fn discriminator(data: &[u8]) -> Option<[u8; 8]> {
+    data.get(..8)?.try_into().ok()
+}
+
+/// Name of the account type in `types` that `data` belongs to.
+pub(crate) fn account_type(data: &[u8], types: &AccountTypes) -> Option<&'static str> {
+    let disc = discriminator(data)?;
+    types.iter().find(|(d, _)| *d == disc).map(|(_, name)| *name)
+}
+
+/// Route an Anchor account on its discriminator: `pool_type` accounts go
+/// through `decode`, other known types are reported without decoding.
+pub(crate) fn route_anchor(
+    data: &[u8],
+    types: &AccountTypes,
+    pool_type: &'static str,
+    decode: impl FnOnce() -> Option<PoolInfo>,
+) -> DecodeOutcome {
+    match account_type(data, types) {
+        Some(account_type) if account_type == pool_type => match decode() {
+            Some(info) => DecodeOutcome::Pool(Box::new(info)),
+            None => DecodeOutcome::Invalid { account_type },
+        },
+        Some(account_type) => DecodeOutcome::Other { account_type },
+        None => DecodeOutcome::Unknown { discriminator: discriminator(data) },
+    }
+}
+
+/// What a pool account of a DEX looks like to RPC servers, so that snapshots
+/// and subscriptions can be filtered down to pools server-side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolLayout {
+    /// First eight bytes of every pool account, for Anchor programs.
+    pub discriminator: Option<[u8; 8]>,
+    /// Size of the pool account, when fixed.
+    pub data_size: Option<u64>,
+    /// Offsets of the base and quote mints.
+    pub mint_offsets: Option<[usize; 2]>,
+}
+
+/// Decoder for the pool accounts of one DEX. Implement this to watch a DEX
+/// that `pool_watcher` does not know about and register it in a
+/// [`DecoderRegistry`].
+pub trait PoolDecoder: Send + Sync {
+    /// Kind reported on the decoded pools.
+    fn kind(&self) -> DexKind;
+    /// Programs whose accounts this decoder understands.
+    fn program_ids(&self) -> Vec<Pubkey>;
+    /// Whether the account discriminator and/or size belongs to one of the
+    /// program's account types. Only matching accounts reach [`PoolDecoder::decode`].
+    fn matches(&self, data: &[u8]) -> bool;
+    /// Decode an account. DEX-specific pool fields go into [`PoolInfo::state`].
+    fn decode(&self, program: Pubkey, account: Pubkey, data: &[u8]) -> DecodeOutcome;
+    /// Layouts of the program's pool accounts. Without any, snapshots fetch
+    /// every account of the program.
+    fn pool_layouts(&self) -> Vec<PoolLayout> {
+        Vec::new()
+    }
+}
+
+/// Adapter exposing one of the built-in decoder modules as a [`PoolDecoder`].
+struct BuiltinDecoder {
     kind: DexKind,
+    program_ids: &'static [Pubkey],
+    matches: fn(&[u8]) -> bool,
+    decode: fn(Pubkey, Pubkey, &[u8]) -> DecodeOutcome,
+    layouts: &'static [PoolLayout],
+}
+
+impl PoolDecoder for BuiltinDecoder {
+    fn kind(&self) -> DexKind { self.kind }
+    fn program_ids(&self) -> Vec<Pubkey> { self.program_ids.to_vec() }
+    fn matches(&self, data: &[u8]) -> bool { (self.matches)(data) }
+    fn decode(&self, program: Pubkey, account: Pubkey, data: &[u8]) -> DecodeOutcome {
+        (self.decode)(program, account, data)
+    }
+    fn pool_layouts(&self) -> Vec<PoolLayout> { self.layouts.to_vec() }
+}
+
+/// Decoders shipped with `pool_watcher`.
+pub fn builtin_decoders() -> Vec<Arc<dyn PoolDecoder>> {
+    vec![
+        Arc::new(BuiltinDecoder {
+            kind: DexKind::OrcaWhirlpools,
+            program_ids: &[orca_whirl::PROGRAM_ID],
+            matches: orca_whirl::matches,
+            decode: orca_whirl::decode_account,
+            layouts: &[PoolLayout {
+                discriminator: Some(orca_whirl::WHIRLPOOL_DISCRIMINATOR),
+                data_size: Some(orca_whirl::WHIRLPOOL_LEN as u64),
+                mint_offsets: Some([101, 181]),
+            }],
+        }),
+        Arc::new(BuiltinDecoder {
+            kind: DexKind::RaydiumClmm,
+            program_ids: &[raydium_clmm::PROGRAM_ID],
+            matches: raydium_clmm::matches,
+            decode: raydium_clmm::decode_account,
+            layouts: &[PoolLayout {
+                discriminator: Some(raydium_clmm::POOL_STATE_DISCRIMINATOR),
+                data_size: None,
+                mint_offsets: Some([73, 105]),
+            }],
+        }),
+        Arc::new(BuiltinDecoder {
+            kind: DexKind::RaydiumCpmm,
+            program_ids: &[raydium_cpmm::PROGRAM_ID],
+            matches: raydium_cpmm::matches,
+            decode: raydium_cpmm::decode_account,
+            layouts: &[PoolLayout {
+                discriminator: Some(raydium_cpmm::POOL_STATE_DISCRIMINATOR),
+                data_size: None,
+                mint_offsets: Some([168, 200]),
+            }],
+        }),
+        Arc::new(BuiltinDecoder {
+            kind: DexKind::RaydiumAmmV4,
+            program_ids: &[raydium_amm_v4::PROGRAM_ID],
+            matches: raydium_amm_v4::matches,
+            decode: raydium_amm_v4::decode_account,
+            layouts: &[PoolLayout {
+                discriminator: None,
+                data_size: Some(raydium_amm_v4::AMM_INFO_LEN as u64),
+                mint_offsets: Some([400, 432]),
+            }],
+        }),
+        Arc::new(BuiltinDecoder {
+            kind: DexKind::MeteoraDlmm,
+            program_ids: &[meteora_dlmm::PROGRAM_ID],
+            matches: meteora_dlmm::matches,
+            decode: meteora_dlmm::decode_account,
+            layouts: &[PoolLayout {
+                discriminator: Some(meteora_dlmm::LB_PAIR_DISCRIMINATOR),
+                data_size: Some(meteora_dlmm::LB_PAIR_LEN as u64),
+                mint_offsets: Some([88, 120]),
+            }],
+        }),
+        Arc::new(BuiltinDecoder {
+            kind: DexKind::MeteoraDamm,
+            program_ids: &[meteora_damm::V1_PROGRAM_ID, meteora_damm::V2_PROGRAM_ID],
+            matches: meteora_damm::matches,
+            decode: meteora_damm::decode_account,
+            // v1 and v2 pools share the discriminator but not the layout
+            layouts: &[
+                PoolLayout {
+                    discriminator: Some(meteora_damm::POOL_DISCRIMINATOR),
+                    data_size: None,
+                    mint_offsets: Some([40, 72]),
+                },
+                PoolLayout {
+                    discriminator: Some(meteora_damm::POOL_DISCRIMINATOR),
+                    data_size: Some(meteora_damm::V2_POOL_LEN as u64),
+                    mint_offsets: Some([168, 200]),
+                },
+            ],
+        }),
+        Arc::new(BuiltinDecoder {
+            kind: DexKind::PumpFun,
+            program_ids: &[pump_fun::PROGRAM_ID],
+            matches: pump_fun::matches,
+            decode: pump_fun::decode_account,
+            // curves do not store their mint
+            layouts: &[PoolLayout {
+                discriminator: Some(pump_fun::BONDING_CURVE_DISCRIMINATOR),
+                data_size: None,
+                mint_offsets: None,
+            }],
+        }),
+        Arc::new(BuiltinDecoder {
+            kind: DexKind::PumpSwap,
+            program_ids: &[pump_swap::PROGRAM_ID],
+            matches: pump_swap::matches,
+            decode: pump_swap::decode_account,
+            layouts: &[PoolLayout {
+                discriminator: Some(pump_swap::POOL_DISCRIMINATOR),
+                data_size: None,
+                mint_offsets: Some([43, 75]),
+            }],
+        }),
+    ]
+}
+
+static BUILTIN: Lazy<DecoderRegistry> = Lazy::new(DecoderRegistry::builtin);
+
+/// Decoders looked up by program id, falling back to the configured
+/// [`DexKind`] for programs deployed under another address (forks, devnet).
+/// Decoders registered later take precedence over earlier ones.
+#[derive(Clone, Default)]
+pub struct DecoderRegistry {
+    by_program: HashMap<Pubkey, Vec<Arc<dyn PoolDecoder>>>,
+    by_kind: HashMap<DexKind, Vec<Arc<dyn PoolDecoder>>>,
+}
+
+impl DecoderRegistry {
+    /// Registry without any decoders.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry with every built-in decoder.
+    pub fn builtin() -> Self {
+        let mut registry = Self::new();
+        for decoder in builtin_decoders() {
+            registry.register(decoder);
+        }
+        registry
+    }
+
+    /// Register `decoder` for its own program ids and kind.
+    pub fn register(&mut self, decoder: Arc<dyn PoolDecoder>) -> &mut Self {
+        for program in decoder.program_ids() {
+            self.by_program.entry(program).or_default().insert(0, decoder.clone());
+        }
+        self.by_kind.entry(decoder.kind()).or_default().insert(0, decoder);
+        self
+    }
+
+    /// Register `decoder` for a program id it does not advertise itself.
+    pub fn register_for_program(&mut self, program: Pubkey, decoder: Arc<dyn PoolDecoder>) -> &mut Self {
+        self.by_program.entry(program).or_default().insert(0, decoder);
+        self
+    }
+
+    /// Decoders tried for accounts of `program`.
+    pub fn decoders_for(&self, program: &Pubkey, kind: DexKind) -> &[Arc<dyn PoolDecoder>] {
+        self.by_program
+            .get(program)
+            .or_else(|| self.by_kind.get(&kind))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    pub fn decode_account(
+        &self,
+        kind: DexKind,
+        program: Pubkey,
+        account: Pubkey,
+        data: &[u8],
+    ) -> DecodeOutcome {
+        decode_with(self.decoders_for(&program, kind), program, account, data)
+    }
+
+    pub fn decode(
+        &self,
+        kind: DexKind,
+        program: Pubkey,
+        account: Pubkey,
+        data: &[u8],
+    ) -> Option<PoolInfo> {
+        self.decode_account(kind, program, account, data).into_pool()
+    }
+}
+
+/// First pool or known account type reported by `decoders`. Unparseable
+/// accounts are only reported when no other decoder accepts them.
+fn decode_with(
+    decoders: &[Arc<dyn PoolDecoder>],
     program: Pubkey,
     account: Pubkey,
     data: &[u8],
-    token: &dyn TokenIntrospectionProvider,
-) -> Option<PoolInfo> {
-    let mut info = match kind {
-        DexKind::OrcaWhirlpools => crate::decoders::orca_whirl::try_decode(program, account, data),
-        DexKind::RaydiumClmm | DexKind::RaydiumCpmm => {
-            crate::decoders::raydium_clmm::try_decode(program, account, data)
+) -> DecodeOutcome {
+    let mut outcome = DecodeOutcome::Unknown { discriminator: discriminator(data) };
+    for decoder in decoders.iter().filter(|d| d.matches(data)) {
+        match decoder.decode(program, account, data) {
+            found @ (DecodeOutcome::Pool(_)
+            | DecodeOutcome::TickArray(_)
+            | DecodeOutcome::TickArrayBitmap(_)
+            | DecodeOutcome::Position(_)
+            | DecodeOutcome::Config(_)
+            | DecodeOutcome::Other { .. }) => {
+                return found
+            }
+            invalid @ DecodeOutcome::Invalid { .. } => outcome = invalid,
+            DecodeOutcome::Unknown { .. } => {}
         }
-    }?;
+    }
+    outcome
+}
+
+/// Set the Token-2022 flags of `pools`, looking all of their mints up at
+/// once. On error the flags stay unset.
+pub async fn set_token2022_flags<'a>(
+    pools: impl IntoIterator<Item = &'a mut PoolInfo>,
+    token: &dyn TokenIntrospectionProvider,
+    source: &dyn AccountSource,
+) -> anyhow::Result<()> {
+    let mut pools: Vec<&mut PoolInfo> = pools.into_iter().collect();
+    let mut mints: Vec<Pubkey> = pools.iter().flat_map(|info| [info.base_mint, info.quote_mint]).flatten().collect();
+    mints.sort_unstable();
+    mints.dedup();
+    if mints.is_empty() {
+        return Ok(());
+    }
+    let flags = token.token2022_flags(source, &mints).await?;
+    let is_2022: HashMap<Pubkey, bool> = mints.into_iter().zip(flags).collect();
+    let flag = |mint: Option<Pubkey>| mint.and_then(|m| is_2022.get(&m).copied()).unwrap_or(false);
+    for info in pools.iter_mut() {
+        info.is_token2022_base = flag(info.base_mint);
+        info.is_token2022_quote = flag(info.quote_mint);
+    }
+    Ok(())
+}
+
+/// Decode with the built-in decoder for `kind`, regardless of `program`.
+pub fn decode_pool(kind: DexKind, program: Pubkey, account: Pubkey, data: &[u8]) -> Option<PoolInfo> {
+    let decoders = BUILTIN.by_kind.get(&kind).map(Vec::as_slice).unwrap_or_default();
+    let mut info = decode_with(decoders, program, account, data).into_pool()?;
     // ensure the returned info reflects the requested DEX kind
     info.dex = kind;
-    info.is_token2022_base = info
-        .base_mint
-        .map(|m| token.is_token2022(&m).unwrap_or(false))
-        .unwrap_or(false);
-    info.is_token2022_quote = info
-        .quote_mint
-        .map(|m| token.is_token2022(&m).unwrap_or(false))
-        .unwrap_or(false);
     Some(info)
 }
+
+// Little-endian field readers shared by the layout decoders.
+
+pub(crate) fn read_pubkey(data: &[u8], offset: usize) -> Option<Pubkey> {
+    Some(Pubkey::new_from_array(data.get(offset..offset + 32)?.try_into().ok()?))
+}
+
+pub(crate) fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+pub(crate) fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+pub(crate) fn read_i32(data: &[u8], offset: usize) -> Option<i32> {
+    Some(i32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+pub(crate) fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(data.get(offset..offset + 8)?.try_into().ok()?))
+}
+
+pub(crate) fn read_i128(data: &[u8], offset: usize) -> Option<i128> {
+    Some(i128::from_le_bytes(data.get(offset..offset + 16)?.try_into().ok()?))
+}
+
+pub(crate) fn read_u128(data: &[u8], offset: usize) -> Option<u128> {
+    Some(u128::from_le_bytes(data.get(offset..offset + 16)?.try_into().ok()?))
+}
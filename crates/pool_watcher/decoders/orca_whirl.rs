@@ -1,30 +1,318 @@
-use solana_sdk::pubkey::Pubkey;
-use crate::types::{DexKind, PoolId, PoolInfo};
+use serde::{Deserialize, Serialize};
+use solana_sdk::{pubkey, pubkey::Pubkey};
+use crate::decoders::{account_type, route_anchor, AccountTypes, DecodeOutcome, read_i128, read_i32, read_pubkey, read_u128, read_u16, read_u64};
+use crate::configs::ConfigAccount;
+use crate::liquidity::{InitializedTick, TickArray};
+use crate::positions::Position;
+use crate::types::{DexKind, DexState, FeeRate, PoolId, PoolInfo};
 
-/// Minimal layout reader for Orca Whirlpools using on-chain account layout.
-pub fn try_decode(program: Pubkey, account: Pubkey, data: &[u8]) -> Option<PoolInfo> {
-    // Discriminator + account fields; need at least up to token_b
-    if data.len() < 181 { return None; }
+pub const PROGRAM_ID: Pubkey = pubkey!("whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc");
+pub const ACCOUNT_TYPES: &AccountTypes = &[
+    ([63, 149, 209, 12, 225, 128, 99, 9], "Whirlpool"),
+    ([157, 20, 49, 224, 217, 87, 193, 254], "WhirlpoolsConfig"),
+    ([2, 99, 215, 163, 240, 26, 153, 58], "WhirlpoolsConfigExtension"),
+    ([56, 75, 159, 76, 142, 68, 190, 105], "FeeTier"),
+    ([147, 16, 144, 116, 47, 146, 149, 46], "AdaptiveFeeTier"),
+    ([139, 194, 131, 179, 140, 179, 229, 244], "Oracle"),
+    ([170, 188, 143, 228, 122, 64, 247, 208], "Position"),
+    ([129, 169, 175, 65, 185, 95, 32, 100], "PositionBundle"),
+    ([69, 97, 189, 190, 110, 7, 66, 187], "TickArray"),
+    ([17, 216, 246, 142, 225, 199, 218, 56], "DynamicTickArray"),
+    ([116, 219, 204, 229, 249, 116, 255, 150], "TokenBadge"),
+    ([106, 47, 238, 159, 124, 12, 160, 192], "LockConfig"),
+];
+pub const WHIRLPOOL_DISCRIMINATOR: [u8; 8] = ACCOUNT_TYPES[0].0;
+/// Size of a Whirlpool account including the 8-byte discriminator.
+pub const WHIRLPOOL_LEN: usize = 653;
+pub const NUM_REWARDS: usize = 3;
+/// Ticks per `TickArray` account.
+pub const TICK_ARRAY_SIZE: usize = 88;
+/// Size of a fixed `TickArray` account including the discriminator.
+pub const TICK_ARRAY_LEN: usize = 9988;
+const TICK_LEN: usize = 113;
+/// Size of a `DynamicTickArray` without any initialized tick; each one adds
+/// its 112 bytes of data after the tick's tag.
+pub const DYNAMIC_TICK_ARRAY_MIN_LEN: usize = 148;
+const DYNAMIC_TICKS_OFFSET: usize = 60;
+/// `WhirlpoolsConfig` accounts created before `feature_flags` was added are 108
+/// bytes; this covers every field read below.
+pub const WHIRLPOOLS_CONFIG_MIN_LEN: usize = 106;
+pub const FEE_TIER_LEN: usize = 44;
+pub const POSITION_LEN: usize = 216;
+/// Offset of the whirlpool a `Position` belongs to.
+pub const POSITION_POOL_OFFSET: usize = 8;
+const FEE_TIER_SEED: &[u8] = b"fee_tier";
+
+const WHIRLPOOLS_CONFIG_OFFSET: usize = 8;
+const TICK_SPACING_OFFSET: usize = 41;
+const FEE_RATE_OFFSET: usize = 45;
+const PROTOCOL_FEE_RATE_OFFSET: usize = 47;
+const LIQUIDITY_OFFSET: usize = 49;
+const SQRT_PRICE_OFFSET: usize = 65;
+const TICK_CURRENT_INDEX_OFFSET: usize = 81;
+const PROTOCOL_FEE_OWED_A_OFFSET: usize = 85;
+const PROTOCOL_FEE_OWED_B_OFFSET: usize = 93;
+const TOKEN_MINT_A_OFFSET: usize = 101;
+const TOKEN_VAULT_A_OFFSET: usize = 133;
+const FEE_GROWTH_GLOBAL_A_OFFSET: usize = 165;
+const TOKEN_MINT_B_OFFSET: usize = 181;
+const TOKEN_VAULT_B_OFFSET: usize = 213;
+const FEE_GROWTH_GLOBAL_B_OFFSET: usize = 245;
+const REWARD_LAST_UPDATED_OFFSET: usize = 261;
+const REWARD_INFOS_OFFSET: usize = 269;
+const REWARD_INFO_LEN: usize = 128;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WhirlpoolRewardInfo {
+    pub mint: Pubkey,
+    pub vault: Pubkey,
+    pub authority: Pubkey,
+    pub emissions_per_second_x64: u128,
+    pub growth_global_x64: u128,
+}
+
+impl WhirlpoolRewardInfo {
+    /// Reward slots with a default mint are unused.
+    pub fn initialized(&self) -> bool {
+        self.mint != Pubkey::default()
+    }
+}
+
+/// Full Whirlpool account state.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WhirlpoolState {
+    pub whirlpools_config: Pubkey,
+    pub tick_spacing: u16,
+    /// Hundredths of a basis point.
+    pub fee_rate: u16,
+    /// Basis points of the fee taken by the protocol.
+    pub protocol_fee_rate: u16,
+    pub liquidity: u128,
+    pub sqrt_price: u128,
+    pub tick_current_index: i32,
+    pub protocol_fee_owed_a: u64,
+    pub protocol_fee_owed_b: u64,
+    pub token_mint_a: Pubkey,
+    pub token_vault_a: Pubkey,
+    pub fee_growth_global_a: u128,
+    pub token_mint_b: Pubkey,
+    pub token_vault_b: Pubkey,
+    pub fee_growth_global_b: u128,
+    pub reward_last_updated_timestamp: u64,
+    pub reward_infos: [WhirlpoolRewardInfo; NUM_REWARDS],
+}
+
+/// `WhirlpoolsConfig` account: authorities and the protocol fee new pools start with.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WhirlpoolsConfig {
+    pub fee_authority: Pubkey,
+    pub collect_protocol_fees_authority: Pubkey,
+    pub reward_emissions_super_authority: Pubkey,
+    /// Basis points of the fee taken by the protocol.
+    pub default_protocol_fee_rate: u16,
+}
+
+/// `FeeTier` account: the fee new pools of a config and tick spacing start with.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FeeTier {
+    pub whirlpools_config: Pubkey,
+    pub tick_spacing: u16,
+    /// Hundredths of a basis point.
+    pub default_fee_rate: u16,
+}
+
+/// Address of the fee tier of `whirlpools_config` for `tick_spacing`.
+pub fn fee_tier_address(whirlpools_config: &Pubkey, tick_spacing: u16) -> Pubkey {
+    Pubkey::find_program_address(
+        &[FEE_TIER_SEED, whirlpools_config.as_ref(), &tick_spacing.to_le_bytes()],
+        &PROGRAM_ID,
+    )
+    .0
+}
 
-    const TOKEN_A_OFFSET: usize = 69;
-    const TOKEN_B_OFFSET: usize = 149;
-    const TICK_SPACING_OFFSET: usize = 9;
-    const FEE_RATE_OFFSET: usize = 13;
+pub fn matches(data: &[u8]) -> bool {
+    account_type(data, ACCOUNT_TYPES).is_some()
+}
+
+pub fn decode_account(program: Pubkey, account: Pubkey, data: &[u8]) -> DecodeOutcome {
+    match account_type(data, ACCOUNT_TYPES) {
+        Some(account_type @ ("TickArray" | "DynamicTickArray")) => {
+            return match parse_tick_array(data) {
+                Some(array) => DecodeOutcome::TickArray(Box::new(array)),
+                None => DecodeOutcome::Invalid { account_type },
+            };
+        }
+        Some(account_type @ "Position") => {
+            return match parse_position(account, data) {
+                Some(position) => DecodeOutcome::Position(Box::new(position)),
+                None => DecodeOutcome::Invalid { account_type },
+            };
+        }
+        Some(account_type @ ("WhirlpoolsConfig" | "FeeTier")) => {
+            return match parse_config(data) {
+                Some(config) => DecodeOutcome::Config(Box::new(config)),
+                None => DecodeOutcome::Invalid { account_type },
+            };
+        }
+        _ => {}
+    }
+    route_anchor(data, ACCOUNT_TYPES, "Whirlpool", || try_decode(program, account, data))
+}
 
-    let token_a = Pubkey::new_from_array(data.get(TOKEN_A_OFFSET..TOKEN_A_OFFSET+32)?.try_into().ok()?);
-    let token_b = Pubkey::new_from_array(data.get(TOKEN_B_OFFSET..TOKEN_B_OFFSET+32)?.try_into().ok()?);
-    let tick_spacing = u16::from_le_bytes(data.get(TICK_SPACING_OFFSET..TICK_SPACING_OFFSET+2)?.try_into().ok()?);
-    let fee_bps = u16::from_le_bytes(data.get(FEE_RATE_OFFSET..FEE_RATE_OFFSET+2)?.try_into().ok()?);
+/// Parse a `TickArray` or `DynamicTickArray`. Each tick of a fixed array
+/// starts with an `initialized` flag followed by `liquidity_net`.
+pub fn parse_tick_array(data: &[u8]) -> Option<TickArray> {
+    match account_type(data, ACCOUNT_TYPES)? {
+        "TickArray" if data.len() == TICK_ARRAY_LEN => {}
+        "DynamicTickArray" => return parse_dynamic_tick_array(data),
+        _ => return None,
+    }
+    let ticks = (0..TICK_ARRAY_SIZE)
+        .filter_map(|i| {
+            let offset = 12 + i * TICK_LEN;
+            (*data.get(offset)? != 0).then_some(())?;
+            Some(InitializedTick { offset: i as u16, liquidity_net: read_i128(data, offset + 1)? })
+        })
+        .collect();
+    Some(TickArray {
+        dex: DexKind::OrcaWhirlpools,
+        pool: read_pubkey(data, 12 + TICK_ARRAY_SIZE * TICK_LEN)?,
+        start_tick_index: read_i32(data, 8)?,
+        ticks,
+    })
+}
+
+/// Dynamic arrays, used by newer pools, only store the data of initialized
+/// ticks: each tick is a tag byte, followed by its data when the tag is 1.
+fn parse_dynamic_tick_array(data: &[u8]) -> Option<TickArray> {
+    if data.len() < DYNAMIC_TICK_ARRAY_MIN_LEN {
+        return None;
+    }
+    let mut ticks = Vec::new();
+    let mut offset = DYNAMIC_TICKS_OFFSET;
+    for i in 0..TICK_ARRAY_SIZE {
+        match *data.get(offset)? {
+            0 => offset += 1,
+            1 => {
+                ticks.push(InitializedTick { offset: i as u16, liquidity_net: read_i128(data, offset + 1)? });
+                offset += TICK_LEN;
+            }
+            _ => return None,
+        }
+    }
+    Some(TickArray {
+        dex: DexKind::OrcaWhirlpools,
+        pool: read_pubkey(data, 12)?,
+        start_tick_index: read_i32(data, 8)?,
+        ticks,
+    })
+}
+
+pub fn parse_position(address: Pubkey, data: &[u8]) -> Option<Position> {
+    if data.len() != POSITION_LEN || account_type(data, ACCOUNT_TYPES) != Some("Position") {
+        return None;
+    }
+    Some(Position {
+        dex: DexKind::OrcaWhirlpools,
+        address,
+        pool: read_pubkey(data, POSITION_POOL_OFFSET)?,
+        position_mint: read_pubkey(data, 40)?,
+        liquidity: read_u128(data, 72)?,
+        tick_lower: read_i32(data, 88)?,
+        tick_upper: read_i32(data, 92)?,
+        owner: None,
+    })
+}
+
+pub fn parse_whirlpools_config(data: &[u8]) -> Option<WhirlpoolsConfig> {
+    if data.len() < WHIRLPOOLS_CONFIG_MIN_LEN || account_type(data, ACCOUNT_TYPES) != Some("WhirlpoolsConfig") {
+        return None;
+    }
+    Some(WhirlpoolsConfig {
+        fee_authority: read_pubkey(data, 8)?,
+        collect_protocol_fees_authority: read_pubkey(data, 40)?,
+        reward_emissions_super_authority: read_pubkey(data, 72)?,
+        default_protocol_fee_rate: read_u16(data, 104)?,
+    })
+}
+
+pub fn parse_fee_tier(data: &[u8]) -> Option<FeeTier> {
+    if data.len() < FEE_TIER_LEN || account_type(data, ACCOUNT_TYPES) != Some("FeeTier") {
+        return None;
+    }
+    Some(FeeTier {
+        whirlpools_config: read_pubkey(data, 8)?,
+        tick_spacing: read_u16(data, 40)?,
+        default_fee_rate: read_u16(data, 42)?,
+    })
+}
+
+/// Parse a `WhirlpoolsConfig` or `FeeTier` account.
+pub fn parse_config(data: &[u8]) -> Option<ConfigAccount> {
+    parse_whirlpools_config(data)
+        .map(ConfigAccount::WhirlpoolsConfig)
+        .or_else(|| parse_fee_tier(data).map(ConfigAccount::WhirlpoolFeeTier))
+}
+
+/// Parse the raw Whirlpool account layout.
+pub fn parse_state(data: &[u8]) -> Option<WhirlpoolState> {
+    if data.len() < WHIRLPOOL_LEN || !data.starts_with(&WHIRLPOOL_DISCRIMINATOR) { return None; }
+
+    let mut reward_infos: [WhirlpoolRewardInfo; NUM_REWARDS] = Default::default();
+    for (i, reward) in reward_infos.iter_mut().enumerate() {
+        let base = REWARD_INFOS_OFFSET + i * REWARD_INFO_LEN;
+        *reward = WhirlpoolRewardInfo {
+            mint: read_pubkey(data, base)?,
+            vault: read_pubkey(data, base + 32)?,
+            authority: read_pubkey(data, base + 64)?,
+            emissions_per_second_x64: read_u128(data, base + 96)?,
+            growth_global_x64: read_u128(data, base + 112)?,
+        };
+    }
+
+    Some(WhirlpoolState {
+        whirlpools_config: read_pubkey(data, WHIRLPOOLS_CONFIG_OFFSET)?,
+        tick_spacing: read_u16(data, TICK_SPACING_OFFSET)?,
+        fee_rate: read_u16(data, FEE_RATE_OFFSET)?,
+        protocol_fee_rate: read_u16(data, PROTOCOL_FEE_RATE_OFFSET)?,
+        liquidity: read_u128(data, LIQUIDITY_OFFSET)?,
+        sqrt_price: read_u128(data, SQRT_PRICE_OFFSET)?,
+        tick_current_index: read_i32(data, TICK_CURRENT_INDEX_OFFSET)?,
+        protocol_fee_owed_a: read_u64(data, PROTOCOL_FEE_OWED_A_OFFSET)?,
+        protocol_fee_owed_b: read_u64(data, PROTOCOL_FEE_OWED_B_OFFSET)?,
+        token_mint_a: read_pubkey(data, TOKEN_MINT_A_OFFSET)?,
+        token_vault_a: read_pubkey(data, TOKEN_VAULT_A_OFFSET)?,
+        fee_growth_global_a: read_u128(data, FEE_GROWTH_GLOBAL_A_OFFSET)?,
+        token_mint_b: read_pubkey(data, TOKEN_MINT_B_OFFSET)?,
+        token_vault_b: read_pubkey(data, TOKEN_VAULT_B_OFFSET)?,
+        fee_growth_global_b: read_u128(data, FEE_GROWTH_GLOBAL_B_OFFSET)?,
+        reward_last_updated_timestamp: read_u64(data, REWARD_LAST_UPDATED_OFFSET)?,
+        reward_infos,
+    })
+}
+
+/// Layout reader for Orca Whirlpools using on-chain account layout.
+pub fn try_decode(program: Pubkey, account: Pubkey, data: &[u8]) -> Option<PoolInfo> {
+    let state = parse_state(data)?;
 
     Some(PoolInfo {
         dex: DexKind::OrcaWhirlpools,
         id: PoolId { program, account },
-        base_mint: Some(token_a),
-        quote_mint: Some(token_b),
-        fee_bps: Some(fee_bps),
-        tick_spacing: Some(tick_spacing),
+        base_mint: Some(state.token_mint_a),
+        quote_mint: Some(state.token_mint_b),
+        fee: Some(FeeRate::from_ppm(state.fee_rate as u32)),
+        config: Some(state.whirlpools_config),
+        tick_spacing: Some(state.tick_spacing),
         lp_mint: None,
         is_token2022_base: false,
         is_token2022_quote: false,
+        base_vault: Some(state.token_vault_a),
+        quote_vault: Some(state.token_vault_b),
+        sqrt_price_x64: Some(state.sqrt_price),
+        liquidity: Some(state.liquidity),
+        tick_current: Some(state.tick_current_index),
+        swap_enabled: None,
+        open_at: None,
+        state: Some(DexState::Whirlpool(Box::new(state))),
     })
 }
@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+use solana_sdk::{pubkey, pubkey::Pubkey};
+use crate::decoders::{account_type, route_anchor, AccountTypes, DecodeOutcome, read_pubkey, read_u64};
+use crate::types::{DexKind, DexState, PoolId, PoolInfo};
+
+pub const PROGRAM_ID: Pubkey = pubkey!("6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P");
+pub const WSOL_MINT: Pubkey = pubkey!("So11111111111111111111111111111111111111112");
+pub const ACCOUNT_TYPES: &AccountTypes = &[
+    ([23, 183, 248, 55, 96, 216, 172, 96], "BondingCurve"),
+    ([167, 232, 232, 177, 200, 108, 114, 127], "Global"),
+    ([202, 42, 246, 43, 142, 190, 30, 255], "GlobalVolumeAccumulator"),
+    ([86, 255, 112, 14, 102, 53, 154, 250], "UserVolumeAccumulator"),
+];
+pub const BONDING_CURVE_DISCRIMINATOR: [u8; 8] = ACCOUNT_TYPES[0].0;
+/// Accounts created before the `creator` field was added are 49 bytes long.
+pub const BONDING_CURVE_MIN_LEN: usize = 49;
+const BONDING_CURVE_SEED: &[u8] = b"bonding-curve";
+
+/// pump.fun `BondingCurve` account. The curve does not store its mint; the
+/// account address is the PDA of `["bonding-curve", mint]`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BondingCurveState {
+    pub virtual_token_reserves: u64,
+    pub virtual_sol_reserves: u64,
+    pub real_token_reserves: u64,
+    pub real_sol_reserves: u64,
+    pub token_total_supply: u64,
+    /// Set once the curve is sold out and liquidity can migrate to an AMM.
+    pub complete: bool,
+    pub creator: Option<Pubkey>,
+}
+
+impl BondingCurveState {
+    /// Spot price in lamports per raw token unit from the virtual reserves.
+    pub fn price_lamports(&self) -> Option<f64> {
+        if self.virtual_token_reserves == 0 { return None; }
+        Some(self.virtual_sol_reserves as f64 / self.virtual_token_reserves as f64)
+    }
+}
+
+/// Address of the bonding curve for `mint`.
+pub fn bonding_curve_address(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[BONDING_CURVE_SEED, mint.as_ref()], &PROGRAM_ID).0
+}
+
+pub fn matches(data: &[u8]) -> bool {
+    account_type(data, ACCOUNT_TYPES).is_some()
+}
+
+pub fn decode_account(program: Pubkey, account: Pubkey, data: &[u8]) -> DecodeOutcome {
+    route_anchor(data, ACCOUNT_TYPES, "BondingCurve", || try_decode(program, account, data))
+}
+
+pub fn parse_state(data: &[u8]) -> Option<BondingCurveState> {
+    if data.len() < BONDING_CURVE_MIN_LEN || data[..8] != BONDING_CURVE_DISCRIMINATOR {
+        return None;
+    }
+    Some(BondingCurveState {
+        virtual_token_reserves: read_u64(data, 8)?,
+        virtual_sol_reserves: read_u64(data, 16)?,
+        real_token_reserves: read_u64(data, 24)?,
+        real_sol_reserves: read_u64(data, 32)?,
+        token_total_supply: read_u64(data, 40)?,
+        complete: *data.get(48)? != 0,
+        creator: read_pubkey(data, 49),
+    })
+}
+
+/// Decode a pump.fun bonding curve. The base mint is left unset because it
+/// cannot be recovered from the account data.
+pub fn try_decode(program: Pubkey, account: Pubkey, data: &[u8]) -> Option<PoolInfo> {
+    let state = parse_state(data)?;
+
+    Some(PoolInfo {
+        dex: DexKind::PumpFun,
+        id: PoolId { program, account },
+        quote_mint: Some(WSOL_MINT),
+        state: Some(DexState::PumpFunCurve(Box::new(state))),
+        ..Default::default()
+    })
+}
+
+/// Whether `info` is a bonding curve that has completed.
+pub fn is_complete(info: &PoolInfo) -> bool {
+    matches!(&info.state, Some(DexState::PumpFunCurve(c)) if c.complete)
+}
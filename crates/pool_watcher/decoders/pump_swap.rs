@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+use solana_sdk::{pubkey, pubkey::Pubkey};
+use crate::decoders::{account_type, route_anchor, AccountTypes, DecodeOutcome, read_pubkey, read_u16, read_u64};
+use crate::types::{DexKind, DexState, PoolId, PoolInfo};
+
+pub const PROGRAM_ID: Pubkey = pubkey!("pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA");
+pub const ACCOUNT_TYPES: &AccountTypes = &[
+    ([241, 154, 109, 4, 17, 177, 109, 188], "Pool"),
+    ([149, 8, 156, 202, 160, 252, 176, 217], "GlobalConfig"),
+    ([202, 42, 246, 43, 142, 190, 30, 255], "GlobalVolumeAccumulator"),
+    ([86, 255, 112, 14, 102, 53, 154, 250], "UserVolumeAccumulator"),
+];
+pub const POOL_DISCRIMINATOR: [u8; 8] = ACCOUNT_TYPES[0].0;
+/// Pools created before `coin_creator` was added end after `lp_supply`.
+pub const POOL_MIN_LEN: usize = 211;
+
+/// PumpSwap AMM `Pool` account. Fees are set globally in `GlobalConfig`
+/// rather than per pool.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PumpSwapPoolState {
+    pub pool_bump: u8,
+    /// Distinguishes pools created by the same creator for the same pair.
+    pub index: u16,
+    pub creator: Pubkey,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub lp_mint: Pubkey,
+    pub pool_base_token_account: Pubkey,
+    pub pool_quote_token_account: Pubkey,
+    pub lp_supply: u64,
+    /// Creator of the pump.fun coin the pool was migrated from, if any.
+    pub coin_creator: Option<Pubkey>,
+}
+
+pub fn matches(data: &[u8]) -> bool {
+    account_type(data, ACCOUNT_TYPES).is_some()
+}
+
+pub fn decode_account(program: Pubkey, account: Pubkey, data: &[u8]) -> DecodeOutcome {
+    route_anchor(data, ACCOUNT_TYPES, "Pool", || try_decode(program, account, data))
+}
+
+pub fn parse_state(data: &[u8]) -> Option<PumpSwapPoolState> {
+    if data.len() < POOL_MIN_LEN || data[..8] != POOL_DISCRIMINATOR {
+        return None;
+    }
+    Some(PumpSwapPoolState {
+        pool_bump: *data.get(8)?,
+        index: read_u16(data, 9)?,
+        creator: read_pubkey(data, 11)?,
+        base_mint: read_pubkey(data, 43)?,
+        quote_mint: read_pubkey(data, 75)?,
+        lp_mint: read_pubkey(data, 107)?,
+        pool_base_token_account: read_pubkey(data, 139)?,
+        pool_quote_token_account: read_pubkey(data, 171)?,
+        lp_supply: read_u64(data, 203)?,
+        coin_creator: read_pubkey(data, 211).filter(|c| *c != Pubkey::default()),
+    })
+}
+
+/// Decode a PumpSwap pool.
+pub fn try_decode(program: Pubkey, account: Pubkey, data: &[u8]) -> Option<PoolInfo> {
+    let state = parse_state(data)?;
+
+    Some(PoolInfo {
+        dex: DexKind::PumpSwap,
+        id: PoolId { program, account },
+        base_mint: Some(state.base_mint),
+        quote_mint: Some(state.quote_mint),
+        fee: None,
+        config: None,
+        tick_spacing: None,
+        lp_mint: Some(state.lp_mint),
+        is_token2022_base: false,
+        is_token2022_quote: false,
+        base_vault: Some(state.pool_base_token_account),
+        quote_vault: Some(state.pool_quote_token_account),
+        sqrt_price_x64: None,
+        liquidity: None,
+        tick_current: None,
+        swap_enabled: None,
+        open_at: None,
+        state: Some(DexState::PumpSwap(Box::new(state))),
+    })
+}
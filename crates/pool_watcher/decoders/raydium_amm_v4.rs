@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+use solana_sdk::{pubkey, pubkey::Pubkey};
+use crate::decoders::{discriminator, DecodeOutcome, read_pubkey, read_u64};
+use crate::types::{DexKind, DexState, FeeRate, OpenAt, PoolId, PoolInfo};
+
+pub const PROGRAM_ID: Pubkey = pubkey!("675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8");
+/// Size of the legacy (non-Anchor) `AmmInfo` account.
+pub const AMM_INFO_LEN: usize = 752;
+
+/// Raydium AMM v4 `AmmInfo` account. Coin is the base side, pc the quote side.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AmmV4State {
+    /// See [`AmmV4State::swap_enabled`] for the meaning of the values.
+    pub status: u64,
+    pub nonce: u64,
+    pub coin_decimals: u64,
+    pub pc_decimals: u64,
+    pub trade_fee_numerator: u64,
+    pub trade_fee_denominator: u64,
+    pub swap_fee_numerator: u64,
+    pub swap_fee_denominator: u64,
+    /// PnL owed to the protocol that still sits in the vaults.
+    pub need_take_pnl_coin: u64,
+    pub need_take_pnl_pc: u64,
+    /// Unix timestamp after which swaps are allowed.
+    pub pool_open_time: u64,
+    pub coin_vault: Pubkey,
+    pub pc_vault: Pubkey,
+    pub coin_mint: Pubkey,
+    pub pc_mint: Pubkey,
+    pub lp_mint: Pubkey,
+    pub open_orders: Pubkey,
+    /// OpenBook/Serum market the pool is linked to.
+    pub market: Pubkey,
+    pub market_program: Pubkey,
+    pub target_orders: Pubkey,
+    pub amm_owner: Pubkey,
+    /// Outstanding LP token supply as tracked by the program.
+    pub lp_amount: u64,
+}
+
+impl AmmV4State {
+    pub const STATUS_INITIALIZED: u64 = 1;
+    pub const STATUS_SWAP_ONLY: u64 = 6;
+    pub const STATUS_WAITING_TRADE: u64 = 7;
+
+    /// Whether the status allows swaps (`WaitingTrade` still honours `pool_open_time`).
+    pub fn swap_enabled(&self) -> bool {
+        matches!(
+            self.status,
+            Self::STATUS_INITIALIZED | Self::STATUS_SWAP_ONLY | Self::STATUS_WAITING_TRADE
+        )
+    }
+}
+
+pub fn matches(data: &[u8]) -> bool {
+    data.len() == AMM_INFO_LEN
+}
+
+/// Route an AMM v4 account. Only `AmmInfo` has a known size; anything else
+/// is reported as unknown.
+pub fn decode_account(program: Pubkey, account: Pubkey, data: &[u8]) -> DecodeOutcome {
+    if !matches(data) {
+        return DecodeOutcome::Unknown { discriminator: discriminator(data) };
+    }
+    match try_decode(program, account, data) {
+        Some(info) => DecodeOutcome::Pool(Box::new(info)),
+        None => DecodeOutcome::Invalid { account_type: "AmmInfo" },
+    }
+}
+
+pub fn parse_state(data: &[u8]) -> Option<AmmV4State> {
+    if data.len() != AMM_INFO_LEN { return None; }
+    Some(AmmV4State {
+        status: read_u64(data, 0)?,
+        nonce: read_u64(data, 8)?,
+        coin_decimals: read_u64(data, 32)?,
+        pc_decimals: read_u64(data, 40)?,
+        trade_fee_numerator: read_u64(data, 144)?,
+        trade_fee_denominator: read_u64(data, 152)?,
+        swap_fee_numerator: read_u64(data, 176)?,
+        swap_fee_denominator: read_u64(data, 184)?,
+        need_take_pnl_coin: read_u64(data, 192)?,
+        need_take_pnl_pc: read_u64(data, 200)?,
+        pool_open_time: read_u64(data, 224)?,
+        coin_vault: read_pubkey(data, 336)?,
+        pc_vault: read_pubkey(data, 368)?,
+        coin_mint: read_pubkey(data, 400)?,
+        pc_mint: read_pubkey(data, 432)?,
+        lp_mint: read_pubkey(data, 464)?,
+        open_orders: read_pubkey(data, 496)?,
+        market: read_pubkey(data, 528)?,
+        market_program: read_pubkey(data, 560)?,
+        target_orders: read_pubkey(data, 592)?,
+        amm_owner: read_pubkey(data, 688)?,
+        lp_amount: read_u64(data, 720)?,
+    })
+}
+
+/// Decode a Raydium AMM v4 pool. The program has no Anchor discriminator, so
+/// pools are recognised by their fixed account size.
+pub fn try_decode(program: Pubkey, account: Pubkey, data: &[u8]) -> Option<PoolInfo> {
+    let state = parse_state(data)?;
+    // an all-zero mint means the account is not an initialised pool
+    if state.coin_mint == Pubkey::default() { return None; }
+
+    Some(PoolInfo {
+        dex: DexKind::RaydiumAmmV4,
+        id: PoolId { program, account },
+        base_mint: Some(state.coin_mint),
+        quote_mint: Some(state.pc_mint),
+        fee: FeeRate::from_ratio(state.swap_fee_numerator, state.swap_fee_denominator),
+        config: None,
+        tick_spacing: None,
+        lp_mint: Some(state.lp_mint),
+        is_token2022_base: false,
+        is_token2022_quote: false,
+        base_vault: Some(state.coin_vault),
+        quote_vault: Some(state.pc_vault),
+        sqrt_price_x64: None,
+        liquidity: None,
+        tick_current: None,
+        swap_enabled: Some(state.swap_enabled()),
+        open_at: OpenAt::from_open_time(state.pool_open_time),
+        state: Some(DexState::RaydiumAmmV4(Box::new(state))),
+    })
+}
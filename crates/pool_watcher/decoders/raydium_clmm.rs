@@ -1,24 +1,225 @@
-use once_cell::sync::Lazy;
-use dashmap::DashMap;
-use solana_sdk::pubkey::Pubkey;
-use crate::types::{DexKind, PoolId, PoolInfo};
+use serde::{Deserialize, Serialize};
+use solana_sdk::{pubkey, pubkey::Pubkey};
+use crate::decoders::{
+    account_type, read_i128, read_i32, read_pubkey, read_u128, read_u16, read_u32, read_u64, route_anchor,
+    AccountTypes, DecodeOutcome,
+};
+use crate::configs::ConfigAccount;
+use crate::liquidity::{InitializedTick, TickArray};
+use crate::positions::Position;
+use crate::types::{DexKind, OpenAt, PoolId, PoolInfo};
 
-static CONFIG_FEES: Lazy<DashMap<Pubkey, u16>> = Lazy::new(DashMap::new);
+pub const PROGRAM_ID: Pubkey = pubkey!("CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK");
+const TICK_SPACING_OFFSET: usize = 235;
+/// Ticks per `TickArrayState` account.
+pub const TICK_ARRAY_SIZE: usize = 60;
+pub const TICK_ARRAY_LEN: usize = 10240;
+const TICK_LEN: usize = 168;
+/// Tick arrays per bitmap word group; the pool itself tracks this many on each side of 0.
+const TICK_ARRAY_BITMAP_SIZE: i32 = 512;
+pub const TICK_ARRAY_BITMAP_EXTENSION_LEN: usize = 1832;
+const EXTENSION_BITMAP_SIZE: usize = 14;
+/// Size of the CLMM `AmmConfig` account including the discriminator.
+pub const AMM_CONFIG_LEN: usize = 117;
+pub const PERSONAL_POSITION_LEN: usize = 281;
+const TICK_ARRAY_BITMAP_EXTENSION_SEED: &[u8] = b"pool_tick_array_bitmap_extension";
+/// Offset of the pool a `PersonalPositionState` belongs to.
+pub const PERSONAL_POSITION_POOL_OFFSET: usize = 41;
+
+pub const ACCOUNT_TYPES: &AccountTypes = &[
+    ([247, 237, 227, 245, 215, 195, 222, 70], "PoolState"),
+    ([218, 244, 33, 104, 203, 203, 43, 111], "AmmConfig"),
+    ([192, 155, 85, 205, 49, 249, 129, 42], "TickArrayState"),
+    ([60, 150, 36, 219, 97, 128, 139, 153], "TickArrayBitmapExtension"),
+    ([70, 111, 150, 126, 230, 15, 25, 117], "PersonalPositionState"),
+    ([100, 226, 145, 99, 146, 218, 160, 106], "ProtocolPositionState"),
+    ([122, 174, 197, 53, 129, 9, 165, 132], "ObservationState"),
+    ([19, 236, 58, 237, 81, 222, 183, 252], "OperationState"),
+    ([134, 40, 183, 79, 12, 112, 162, 53], "SupportMintAssociated"),
+];
+pub const POOL_STATE_DISCRIMINATOR: [u8; 8] = ACCOUNT_TYPES[0].0;
+
+/// Raydium CLMM `AmmConfig` account, shared by the pools of one fee tier.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClmmAmmConfig {
+    pub bump: u8,
+    pub index: u16,
+    pub owner: Pubkey,
+    /// Share of the trade fee taken by the protocol, in hundredths of a basis point.
+    pub protocol_fee_rate: u32,
+    /// Hundredths of a basis point.
+    pub trade_fee_rate: u32,
+    pub tick_spacing: u16,
+    pub fund_fee_rate: u32,
+    pub fund_owner: Pubkey,
+}
+
+pub fn matches(data: &[u8]) -> bool {
+    account_type(data, ACCOUNT_TYPES).is_some()
+}
+
+pub fn parse_amm_config(data: &[u8]) -> Option<ClmmAmmConfig> {
+    if data.len() < AMM_CONFIG_LEN || account_type(data, ACCOUNT_TYPES) != Some("AmmConfig") {
+        return None;
+    }
+    Some(ClmmAmmConfig {
+        bump: *data.get(8)?,
+        index: read_u16(data, 9)?,
+        owner: read_pubkey(data, 11)?,
+        protocol_fee_rate: read_u32(data, 43)?,
+        trade_fee_rate: read_u32(data, 47)?,
+        tick_spacing: read_u16(data, 51)?,
+        fund_fee_rate: read_u32(data, 53)?,
+        fund_owner: read_pubkey(data, 61)?,
+    })
+}
+
+/// Route a CLMM account. Pools leave `fee` unset; it comes from the
+/// `AmmConfig` named in [`PoolInfo::config`].
+pub fn decode_account(program: Pubkey, account: Pubkey, data: &[u8]) -> DecodeOutcome {
+    if account_type(data, ACCOUNT_TYPES) == Some("AmmConfig") {
+        return match parse_amm_config(data) {
+            Some(config) => DecodeOutcome::Config(Box::new(ConfigAccount::RaydiumClmm(config))),
+            None => DecodeOutcome::Invalid { account_type: "AmmConfig" },
+        };
+    }
+    if account_type(data, ACCOUNT_TYPES) == Some("PersonalPositionState") {
+        return match parse_personal_position(account, data) {
+            Some(position) => DecodeOutcome::Position(Box::new(position)),
+            None => DecodeOutcome::Invalid { account_type: "PersonalPositionState" },
+        };
+    }
+    if account_type(data, ACCOUNT_TYPES) == Some("TickArrayState") {
+        return match parse_tick_array(data) {
+            Some(array) => DecodeOutcome::TickArray(Box::new(array)),
+            None => DecodeOutcome::Invalid { account_type: "TickArrayState" },
+        };
+    }
+    if account_type(data, ACCOUNT_TYPES) == Some("TickArrayBitmapExtension") {
+        return match parse_tick_array_bitmap_extension(data) {
+            Some(ext) => DecodeOutcome::TickArrayBitmap(Box::new(ext)),
+            None => DecodeOutcome::Invalid { account_type: "TickArrayBitmapExtension" },
+        };
+    }
+    route_anchor(data, ACCOUNT_TYPES, "PoolState", || decode_pool_state(program, account, data))
+}
+
+/// Address of the tick array bitmap extension of `pool`.
+pub fn tick_array_bitmap_extension_address(program: &Pubkey, pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[TICK_ARRAY_BITMAP_EXTENSION_SEED, pool.as_ref()], program).0
+}
+
+/// Bitmap of initialized tick arrays beyond the range tracked in `PoolState`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TickArrayBitmapExtension {
+    pub pool_id: Pubkey,
+    pub positive_tick_array_bitmap: Vec<[u64; 8]>,
+    pub negative_tick_array_bitmap: Vec<[u64; 8]>,
+}
+
+impl TickArrayBitmapExtension {
+    /// Whether the tick array starting at `start` is initialized, or `None`
+    /// when it lies in the range covered by the pool's own bitmap.
+    pub fn is_initialized(&self, start: i32, tick_spacing: u16) -> Option<bool> {
+        let ticks_per_array = TICK_ARRAY_SIZE as i32 * tick_spacing as i32;
+        let max_tick = ticks_per_array * TICK_ARRAY_BITMAP_SIZE;
+        if max_tick == 0 || (-max_tick..max_tick).contains(&start) {
+            return None;
+        }
+        let mut word = start.abs() / max_tick - 1;
+        if start < 0 && start.abs() % max_tick == 0 {
+            word -= 1;
+        }
+        let bitmap = if start > 0 { &self.positive_tick_array_bitmap } else { &self.negative_tick_array_bitmap };
+        let rem = start.abs() % max_tick;
+        let mut bit = rem / ticks_per_array;
+        if start < 0 && rem != 0 {
+            bit = TICK_ARRAY_BITMAP_SIZE - bit;
+        }
+        let bits = bitmap.get(usize::try_from(word).ok()?)?;
+        let bit = usize::try_from(bit).ok().filter(|b| *b < 512)?;
+        Some(bits[bit / 64] >> (bit % 64) & 1 == 1)
+    }
+}
+
+pub fn parse_tick_array(data: &[u8]) -> Option<TickArray> {
+    if data.len() != TICK_ARRAY_LEN || account_type(data, ACCOUNT_TYPES) != Some("TickArrayState") {
+        return None;
+    }
+    // ticks carry their own index; a tick is initialized when it has gross liquidity
+    let ticks = (0..TICK_ARRAY_SIZE)
+        .filter_map(|i| {
+            let offset = 44 + i * TICK_LEN;
+            (read_u128(data, offset + 20)? != 0).then_some(())?;
+            Some(InitializedTick { offset: i as u16, liquidity_net: read_i128(data, offset + 4)? })
+        })
+        .collect();
+    Some(TickArray {
+        dex: DexKind::RaydiumClmm,
+        pool: read_pubkey(data, 8)?,
+        start_tick_index: read_i32(data, 40)?,
+        ticks,
+    })
+}
+
+/// Parse a `PersonalPositionState`, the NFT-backed position users hold.
+pub fn parse_personal_position(address: Pubkey, data: &[u8]) -> Option<Position> {
+    if data.len() < PERSONAL_POSITION_LEN || account_type(data, ACCOUNT_TYPES) != Some("PersonalPositionState") {
+        return None;
+    }
+    Some(Position {
+        dex: DexKind::RaydiumClmm,
+        address,
+        pool: read_pubkey(data, PERSONAL_POSITION_POOL_OFFSET)?,
+        position_mint: read_pubkey(data, 9)?,
+        tick_lower: read_i32(data, 73)?,
+        tick_upper: read_i32(data, 77)?,
+        liquidity: read_u128(data, 81)?,
+        owner: None,
+    })
+}
+
+pub fn parse_tick_array_bitmap_extension(data: &[u8]) -> Option<TickArrayBitmapExtension> {
+    if data.len() != TICK_ARRAY_BITMAP_EXTENSION_LEN
+        || account_type(data, ACCOUNT_TYPES) != Some("TickArrayBitmapExtension")
+    {
+        return None;
+    }
+    let words = |base: usize| -> Option<Vec<[u64; 8]>> {
+        (0..EXTENSION_BITMAP_SIZE)
+            .map(|i| {
+                let mut word = [0u64; 8];
+                for (j, w) in word.iter_mut().enumerate() {
+                    *w = read_u64(data, base + (i * 8 + j) * 8)?;
+                }
+                Some(word)
+            })
+            .collect()
+    };
+    Some(TickArrayBitmapExtension {
+        pool_id: read_pubkey(data, 8)?,
+        positive_tick_array_bitmap: words(40)?,
+        negative_tick_array_bitmap: words(40 + EXTENSION_BITMAP_SIZE * 64)?,
+    })
+}
 
 pub fn try_decode(program: Pubkey, account: Pubkey, data: &[u8]) -> Option<PoolInfo> {
-    const CONFIG_LEN: usize = 117;
-    const TRADE_FEE_OFFSET: usize = 47;
+    decode_account(program, account, data).into_pool()
+}
+
+fn decode_pool_state(program: Pubkey, account: Pubkey, data: &[u8]) -> Option<PoolInfo> {
     const AMM_CONFIG_OFFSET: usize = 9;
     const TOKEN_BASE_OFFSET: usize = 73;
     const TOKEN_QUOTE_OFFSET: usize = 105;
-    const TICK_SPACING_OFFSET: usize = 235;
-
-    if data.len() == CONFIG_LEN {
-        let fee = u32::from_le_bytes(data.get(TRADE_FEE_OFFSET..TRADE_FEE_OFFSET+4)?.try_into().ok()?);
-        let fee_bps = ((fee as u64 * 10_000) / 1_000_000) as u16;
-        CONFIG_FEES.insert(account, fee_bps);
-        return None;
-    }
+    const VAULT_BASE_OFFSET: usize = 137;
+    const VAULT_QUOTE_OFFSET: usize = 169;
+    const LIQUIDITY_OFFSET: usize = 237;
+    const SQRT_PRICE_OFFSET: usize = 253;
+    const TICK_CURRENT_OFFSET: usize = 269;
+    const STATUS_OFFSET: usize = 389;
+    const OPEN_TIME_OFFSET: usize = 1080;
+    // bit 4 of `status` disables swaps
+    const STATUS_SWAP_DISABLED: u8 = 1 << 4;
 
     if data.len() <= TICK_SPACING_OFFSET + 2 { return None; }
 
@@ -26,17 +227,25 @@ pub fn try_decode(program: Pubkey, account: Pubkey, data: &[u8]) -> Option<PoolI
     let token_base = Pubkey::new_from_array(data.get(TOKEN_BASE_OFFSET..TOKEN_BASE_OFFSET+32)?.try_into().ok()?);
     let token_quote = Pubkey::new_from_array(data.get(TOKEN_QUOTE_OFFSET..TOKEN_QUOTE_OFFSET+32)?.try_into().ok()?);
     let tick_spacing = u16::from_le_bytes(data.get(TICK_SPACING_OFFSET..TICK_SPACING_OFFSET+2)?.try_into().ok()?);
-    let fee_bps = CONFIG_FEES.get(&amm_config).map(|v| *v);
 
     Some(PoolInfo {
         dex: DexKind::RaydiumClmm,
         id: PoolId { program, account },
         base_mint: Some(token_base),
         quote_mint: Some(token_quote),
-        fee_bps,
+        fee: None,
+        config: Some(amm_config),
         tick_spacing: Some(tick_spacing),
         lp_mint: None,
         is_token2022_base: false,
         is_token2022_quote: false,
+        base_vault: read_pubkey(data, VAULT_BASE_OFFSET),
+        quote_vault: read_pubkey(data, VAULT_QUOTE_OFFSET),
+        sqrt_price_x64: read_u128(data, SQRT_PRICE_OFFSET),
+        liquidity: read_u128(data, LIQUIDITY_OFFSET),
+        tick_current: read_i32(data, TICK_CURRENT_OFFSET),
+        swap_enabled: data.get(STATUS_OFFSET).map(|status| status & STATUS_SWAP_DISABLED == 0),
+        open_at: read_u64(data, OPEN_TIME_OFFSET).and_then(OpenAt::from_open_time),
+        state: None,
     })
 }
@@ -0,0 +1,160 @@
+use serde::{Deserialize, Serialize};
+use solana_sdk::{pubkey, pubkey::Pubkey};
+use crate::decoders::{account_type, route_anchor, AccountTypes, DecodeOutcome, read_pubkey, read_u16, read_u64};
+use crate::configs::ConfigAccount;
+use crate::types::{DexKind, DexState, OpenAt, PoolId, PoolInfo};
+
+pub const PROGRAM_ID: Pubkey = pubkey!("CPMMoo8L3F4NbTegBCKVNunggL7H1ZpdTHKxQB5qKP1C");
+pub const ACCOUNT_TYPES: &AccountTypes = &[
+    ([247, 237, 227, 245, 215, 195, 222, 70], "PoolState"),
+    ([218, 244, 33, 104, 203, 203, 43, 111], "AmmConfig"),
+    ([122, 174, 197, 53, 129, 9, 165, 132], "ObservationState"),
+    ([162, 165, 164, 44, 59, 122, 213, 225], "PermissionPda"),
+];
+pub const POOL_STATE_DISCRIMINATOR: [u8; 8] = ACCOUNT_TYPES[0].0;
+pub const AMM_CONFIG_DISCRIMINATOR: [u8; 8] = ACCOUNT_TYPES[1].0;
+/// Size of the CPMM `AmmConfig` account including the discriminator.
+pub const AMM_CONFIG_LEN: usize = 236;
+/// Size of the CPMM `PoolState` account including the discriminator.
+pub const POOL_STATE_LEN: usize = 637;
+
+/// Raydium CPMM `PoolState` account.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CpmmPoolState {
+    pub amm_config: Pubkey,
+    pub pool_creator: Pubkey,
+    pub token_0_vault: Pubkey,
+    pub token_1_vault: Pubkey,
+    pub lp_mint: Pubkey,
+    pub token_0_mint: Pubkey,
+    pub token_1_mint: Pubkey,
+    pub token_0_program: Pubkey,
+    pub token_1_program: Pubkey,
+    pub observation_key: Pubkey,
+    pub auth_bump: u8,
+    /// Bit 0 disables deposits, bit 1 withdrawals and bit 2 swaps.
+    pub status: u8,
+    pub lp_mint_decimals: u8,
+    pub mint_0_decimals: u8,
+    pub mint_1_decimals: u8,
+    pub lp_supply: u64,
+    pub protocol_fees_token_0: u64,
+    pub protocol_fees_token_1: u64,
+    pub fund_fees_token_0: u64,
+    pub fund_fees_token_1: u64,
+    /// Unix timestamp after which swaps are allowed.
+    pub open_time: u64,
+    pub recent_epoch: u64,
+}
+
+impl CpmmPoolState {
+    pub const STATUS_SWAP_DISABLED: u8 = 1 << 2;
+
+    pub fn swap_enabled(&self) -> bool {
+        self.status & Self::STATUS_SWAP_DISABLED == 0
+    }
+}
+
+/// Raydium CPMM `AmmConfig` account.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CpmmAmmConfig {
+    pub bump: u8,
+    pub disable_create_pool: bool,
+    pub index: u16,
+    /// Hundredths of a basis point.
+    pub trade_fee_rate: u64,
+    pub protocol_fee_rate: u64,
+    pub fund_fee_rate: u64,
+    pub create_pool_fee: u64,
+    pub protocol_owner: Pubkey,
+    pub fund_owner: Pubkey,
+}
+
+pub fn matches(data: &[u8]) -> bool {
+    account_type(data, ACCOUNT_TYPES).is_some()
+}
+
+pub fn parse_amm_config(data: &[u8]) -> Option<CpmmAmmConfig> {
+    if data.len() != AMM_CONFIG_LEN || !data.starts_with(&AMM_CONFIG_DISCRIMINATOR) { return None; }
+    Some(CpmmAmmConfig {
+        bump: *data.get(8)?,
+        disable_create_pool: *data.get(9)? != 0,
+        index: read_u16(data, 10)?,
+        trade_fee_rate: read_u64(data, 12)?,
+        protocol_fee_rate: read_u64(data, 20)?,
+        fund_fee_rate: read_u64(data, 28)?,
+        create_pool_fee: read_u64(data, 36)?,
+        protocol_owner: read_pubkey(data, 44)?,
+        fund_owner: read_pubkey(data, 76)?,
+    })
+}
+
+pub fn parse_pool_state(data: &[u8]) -> Option<CpmmPoolState> {
+    if data.len() < POOL_STATE_LEN || !data.starts_with(&POOL_STATE_DISCRIMINATOR) { return None; }
+    Some(CpmmPoolState {
+        amm_config: read_pubkey(data, 8)?,
+        pool_creator: read_pubkey(data, 40)?,
+        token_0_vault: read_pubkey(data, 72)?,
+        token_1_vault: read_pubkey(data, 104)?,
+        lp_mint: read_pubkey(data, 136)?,
+        token_0_mint: read_pubkey(data, 168)?,
+        token_1_mint: read_pubkey(data, 200)?,
+        token_0_program: read_pubkey(data, 232)?,
+        token_1_program: read_pubkey(data, 264)?,
+        observation_key: read_pubkey(data, 296)?,
+        auth_bump: *data.get(328)?,
+        status: *data.get(329)?,
+        lp_mint_decimals: *data.get(330)?,
+        mint_0_decimals: *data.get(331)?,
+        mint_1_decimals: *data.get(332)?,
+        lp_supply: read_u64(data, 333)?,
+        protocol_fees_token_0: read_u64(data, 341)?,
+        protocol_fees_token_1: read_u64(data, 349)?,
+        fund_fees_token_0: read_u64(data, 357)?,
+        fund_fees_token_1: read_u64(data, 365)?,
+        open_time: read_u64(data, 373)?,
+        recent_epoch: read_u64(data, 381)?,
+    })
+}
+
+/// Route a CPMM account. Pools leave `fee` unset; it comes from the
+/// `AmmConfig` named in [`PoolInfo::config`].
+pub fn decode_account(program: Pubkey, account: Pubkey, data: &[u8]) -> DecodeOutcome {
+    if account_type(data, ACCOUNT_TYPES) == Some("AmmConfig") {
+        return match parse_amm_config(data) {
+            Some(config) => DecodeOutcome::Config(Box::new(ConfigAccount::RaydiumCpmm(config))),
+            None => DecodeOutcome::Invalid { account_type: "AmmConfig" },
+        };
+    }
+    route_anchor(data, ACCOUNT_TYPES, "PoolState", || decode_pool_state(program, account, data))
+}
+
+/// Decode a Raydium CPMM pool.
+pub fn try_decode(program: Pubkey, account: Pubkey, data: &[u8]) -> Option<PoolInfo> {
+    decode_account(program, account, data).into_pool()
+}
+
+fn decode_pool_state(program: Pubkey, account: Pubkey, data: &[u8]) -> Option<PoolInfo> {
+    let state = parse_pool_state(data)?;
+
+    Some(PoolInfo {
+        dex: DexKind::RaydiumCpmm,
+        id: PoolId { program, account },
+        base_mint: Some(state.token_0_mint),
+        quote_mint: Some(state.token_1_mint),
+        fee: None,
+        config: Some(state.amm_config),
+        tick_spacing: None,
+        lp_mint: Some(state.lp_mint),
+        is_token2022_base: false,
+        is_token2022_quote: false,
+        base_vault: Some(state.token_0_vault),
+        quote_vault: Some(state.token_1_vault),
+        sqrt_price_x64: None,
+        liquidity: None,
+        tick_current: None,
+        swap_enabled: Some(state.swap_enabled()),
+        open_at: OpenAt::from_open_time(state.open_time),
+        state: Some(DexState::RaydiumCpmm(Box::new(state))),
+    })
+}
@@ -0,0 +1,284 @@
+use std::{
+    fmt::Display,
+    future::Future,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Instant,
+};
+use serde::Deserialize;
+use solana_client::client_error::{ClientError, ClientErrorKind};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use tokio::{
+    sync::watch,
+    time::{sleep, timeout, Duration},
+};
+use tracing::{debug, info, warn};
+use crate::service::PoolWatcherConfig;
+
+/// Attempts of a request on one endpoint before the next one is tried.
+const REQUEST_ATTEMPTS: u32 = 3;
+/// Delay before the first retry, doubled on each one after.
+const REQUEST_BACKOFF: Duration = Duration::from_millis(200);
+
+/// RPC and websocket URL of one provider.
+#[derive(Clone, Debug, Deserialize)]
+pub struct EndpointConfig {
+    pub rpc_url: String,
+    pub ws_url: String,
+    /// Preference among healthy endpoints; latency divides it.
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+/// How endpoints are probed.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct HealthConfig {
+    pub probe_interval_ms: u64,
+    pub probe_timeout_ms: u64,
+    /// Slots an endpoint may trail the most advanced one before it counts
+    /// as unhealthy.
+    pub max_slot_lag: u64,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self { probe_interval_ms: 10_000, probe_timeout_ms: 5_000, max_slot_lag: 50 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EndpointStatus {
+    pub rpc_url: String,
+    pub ws_url: String,
+    pub weight: u32,
+    pub healthy: bool,
+    /// Round trip of the last successful probe.
+    pub latency_ms: Option<u64>,
+    /// Slot reported by the last successful probe.
+    pub slot: Option<u64>,
+    pub last_error: Option<String>,
+    /// Endpoint new requests and subscriptions go to.
+    pub active: bool,
+}
+
+/// Endpoints the watcher fails over between. Requests go to the healthy
+/// endpoint with the best weight for its latency; a failed request marks its
+/// endpoint unhealthy until the next successful probe.
+#[derive(Clone)]
+pub(crate) struct Endpoints {
+    list: Arc<[Endpoint]>,
+    active: Arc<AtomicUsize>,
+    /// Bumped whenever the health of an endpoint may have changed.
+    changed: Arc<watch::Sender<()>>,
+}
+
+struct Endpoint {
+    cfg: EndpointConfig,
+    rpc: Arc<RpcClient>,
+    health: Mutex<Health>,
+}
+
+struct Health {
+    healthy: bool,
+    latency_ms: Option<u64>,
+    slot: Option<u64>,
+    last_error: Option<String>,
+}
+
+impl Endpoints {
+    /// The configured `endpoints`, or else the single `rpc_url` and `ws_url`.
+    pub(crate) fn new(cfg: &PoolWatcherConfig) -> Self {
+        let configs = match cfg.endpoints.is_empty() {
+            true => vec![EndpointConfig { rpc_url: cfg.rpc_url.clone(), ws_url: cfg.ws_url.clone(), weight: 1 }],
+            false => cfg.endpoints.clone(),
+        };
+        let list = configs
+            .into_iter()
+            .map(|cfg| Endpoint {
+                rpc: Arc::new(RpcClient::new(cfg.rpc_url.clone())),
+                cfg,
+                health: Mutex::new(Health { healthy: true, latency_ms: None, slot: None, last_error: None }),
+            })
+            .collect();
+        Self { list, active: Arc::new(AtomicUsize::new(0)), changed: Arc::new(watch::Sender::new(())) }
+    }
+
+    fn health(&self, i: usize) -> std::sync::MutexGuard<'_, Health> {
+        self.list[i].health.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Endpoint indices, most preferred first. Unhealthy endpoints come
+    /// last, so that one is still tried when all are down.
+    fn ranked(&self) -> Vec<usize> {
+        let score = |i: usize| {
+            let health = self.health(i);
+            let latency = health.latency_ms.unwrap_or(0) as f64;
+            (health.healthy, self.list[i].cfg.weight as f64 / (1.0 + latency / 100.0))
+        };
+        let mut scored: Vec<(usize, (bool, f64))> = (0..self.list.len()).map(|i| (i, score(i))).collect();
+        scored.sort_by(|(_, a), (_, b)| b.0.cmp(&a.0).then(b.1.total_cmp(&a.1)));
+        let ranked: Vec<usize> = scored.into_iter().map(|(i, _)| i).collect();
+        let previous = self.active.swap(ranked[0], Ordering::Relaxed);
+        if previous != ranked[0] {
+            info!(endpoint = %self.list[ranked[0]].cfg.rpc_url, previous = %self.list[previous].cfg.rpc_url, "active endpoint changed");
+        }
+        ranked
+    }
+
+    /// Websocket URL of the preferred endpoint, with its index for
+    /// [`report_failure`](Self::report_failure).
+    pub(crate) fn ws(&self) -> (usize, String) {
+        let i = self.ranked()[0];
+        (i, self.list[i].cfg.ws_url.clone())
+    }
+
+    pub(crate) fn report_failure(&self, i: usize, err: &dyn Display) {
+        let mut health = self.health(i);
+        if health.healthy {
+            warn!(endpoint = %self.list[i].cfg.rpc_url, err = %err, "endpoint failed, failing over");
+        }
+        health.healthy = false;
+        health.last_error = Some(err.to_string());
+        drop(health);
+        self.changed.send_replace(());
+    }
+
+    /// Resolves once a subscription to endpoint `i` should move: the
+    /// endpoint turned unhealthy, or a healthy endpoint of higher weight took
+    /// over. Latency alone does not move subscriptions.
+    pub(crate) fn deserted(&self, i: usize) -> impl Future<Output = ()> + Send + 'static {
+        let (endpoints, mut changed) = (self.clone(), self.changed.subscribe());
+        async move {
+            loop {
+                let best = endpoints.ranked()[0];
+                let healthy = |i: usize| endpoints.health(i).healthy;
+                let weight = |i: usize| endpoints.list[i].cfg.weight;
+                if best != i && healthy(best) && (!healthy(i) || weight(best) > weight(i)) {
+                    return;
+                }
+                // never fails, `endpoints` holds the sender
+                let _ = changed.changed().await;
+            }
+        }
+    }
+
+    /// Run `request` against the endpoints in order of preference until
+    /// one succeeds. Returns the last error when all fail. Only a failed
+    /// connection marks an endpoint unhealthy; other errors, such as rate
+    /// limits, timeouts or bad answers, are retried with backoff first.
+    pub(crate) async fn call<T, F, Fut>(&self, request: F) -> anyhow::Result<T>
+    where
+        F: Fn(Arc<RpcClient>) -> Fut,
+        Fut: Future<Output = anyhow::Result<T>>,
+    {
+        let mut last = None;
+        for i in self.ranked() {
+            let mut backoff = REQUEST_BACKOFF;
+            for attempt in 1..=REQUEST_ATTEMPTS {
+                match request(self.list[i].rpc.clone()).await {
+                    Ok(value) => return Ok(value),
+                    Err(e) if is_transport_error(&e) => {
+                        self.report_failure(i, &format!("{e:#}"));
+                        last = Some(e);
+                        break;
+                    }
+                    Err(e) => {
+                        debug!(endpoint = %self.list[i].cfg.rpc_url, attempt, err = %format!("{e:#}"), "request failed");
+                        last = Some(e);
+                        if attempt < REQUEST_ATTEMPTS {
+                            sleep(backoff).await;
+                            backoff *= 2;
+                        }
+                    }
+                }
+            }
+        }
+        Err(last.expect("at least one endpoint"))
+    }
+
+    /// Measure the slot and latency of every endpoint. Endpoints that fail,
+    /// time out or trail the others by more than `max_slot_lag` are unhealthy.
+    pub(crate) async fn probe(&self, cfg: &HealthConfig) {
+        let probes = self.list.iter().map(|endpoint| async {
+            let start = Instant::now();
+            let slot = timeout(Duration::from_millis(cfg.probe_timeout_ms), endpoint.rpc.get_slot()).await;
+            let latency = start.elapsed().as_millis() as u64;
+            match slot {
+                Ok(Ok(slot)) => Ok((slot, latency)),
+                Ok(Err(e)) => Err(e.to_string()),
+                Err(_) => Err(format!("probe timed out after {} ms", cfg.probe_timeout_ms)),
+            }
+        });
+        let results = futures::future::join_all(probes).await;
+        let top = results.iter().filter_map(|r| r.as_ref().ok()).map(|(slot, _)| *slot).max().unwrap_or(0);
+        for (i, result) in results.into_iter().enumerate() {
+            let url = &self.list[i].cfg.rpc_url;
+            let mut health = self.health(i);
+            let was_healthy = health.healthy;
+            match result {
+                Ok((slot, latency)) => {
+                    health.slot = Some(slot);
+                    health.latency_ms = Some(latency);
+                    health.healthy = slot + cfg.max_slot_lag >= top;
+                    if !health.healthy {
+                        health.last_error = Some(format!("{} slots behind", top - slot));
+                    }
+                }
+                Err(e) => {
+                    health.healthy = false;
+                    health.last_error = Some(e);
+                }
+            }
+            match (was_healthy, health.healthy) {
+                (false, true) => info!(endpoint = %url, latency_ms = health.latency_ms, "endpoint healthy again"),
+                (true, false) => warn!(endpoint = %url, err = health.last_error.as_deref(), "endpoint unhealthy"),
+                _ => {}
+            }
+        }
+        self.ranked();
+        self.changed.send_replace(());
+    }
+
+    /// Lowest slot reported by the last successful probes, which no endpoint
+    /// serves requests below.
+    pub(crate) fn probed_slot(&self) -> u64 {
+        (0..self.list.len()).filter_map(|i| self.health(i).slot).min().unwrap_or(0)
+    }
+
+    pub(crate) fn status(&self) -> Vec<EndpointStatus> {
+        let active = self.active.load(Ordering::Relaxed);
+        (0..self.list.len())
+            .map(|i| {
+                let health = self.health(i);
+                let cfg = &self.list[i].cfg;
+                EndpointStatus {
+                    rpc_url: cfg.rpc_url.clone(),
+                    ws_url: cfg.ws_url.clone(),
+                    weight: cfg.weight,
+                    healthy: health.healthy,
+                    latency_ms: health.latency_ms,
+                    slot: health.slot,
+                    last_error: health.last_error.clone(),
+                    active: i == active,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Whether `err` comes from reaching the endpoint at all, as opposed to an
+/// answer it gave or a request that took too long.
+fn is_transport_error(err: &anyhow::Error) -> bool {
+    match err.downcast_ref::<ClientError>().map(ClientError::kind) {
+        Some(ClientErrorKind::Io(_)) => true,
+        Some(ClientErrorKind::Reqwest(e)) => e.is_connect(),
+        _ => false,
+    }
+}
@@ -0,0 +1,152 @@
+use serde::Deserialize;
+use solana_client::rpc_filter::{Memcmp, MemcmpEncodedBytes, RpcFilterType};
+use solana_sdk::pubkey::Pubkey;
+use crate::decoders::{DecoderRegistry, PoolLayout};
+use crate::service::ProgramConfig;
+
+/// Server-side account filter of a [`ProgramConfig`], e.g.
+/// `filters = [{ data_size = 653 }, { memcmp = { offset = 0, bytes = "<base58>" } }]`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountFilter {
+    DataSize(u64),
+    /// `bytes` are base58 encoded.
+    Memcmp { offset: usize, bytes: String },
+}
+
+impl From<&AccountFilter> for RpcFilterType {
+    fn from(filter: &AccountFilter) -> Self {
+        match filter {
+            AccountFilter::DataSize(size) => RpcFilterType::DataSize(*size),
+            AccountFilter::Memcmp { offset, bytes } => {
+                RpcFilterType::Memcmp(Memcmp::new(*offset, MemcmpEncodedBytes::Base58(bytes.clone())))
+            }
+        }
+    }
+}
+
+/// Filters of one request. Servers AND the filters of a request, so
+/// alternatives (either mint slot, several layouts) take one request each.
+pub type FilterSet = Vec<RpcFilterType>;
+
+/// Pool layouts of the decoders handling `program`.
+pub fn program_layouts(decoders: &DecoderRegistry, program: &ProgramConfig) -> Vec<PoolLayout> {
+    decoders
+        .decoders_for(&program.id, program.kind)
+        .iter()
+        .flat_map(|decoder| decoder.pool_layouts())
+        .collect()
+}
+
+/// Requests a snapshot of `program` is split into: the configured filters,
+/// or else one per pool layout, narrowed to the configured mints. A single
+/// empty set fetches every account.
+pub fn snapshot_filters(program: &ProgramConfig, layouts: &[PoolLayout]) -> Vec<FilterSet> {
+    snapshot_requests(program, layouts).into_iter().map(|(set, _)| set).collect()
+}
+
+/// Longest mint prefix snapshots are sharded by; two bytes already make
+/// 65536 requests per layout.
+pub const MAX_PREFIX_BYTES: u8 = 2;
+
+/// [`snapshot_filters`] with each set split into shards by the first
+/// `prefix_bytes` bytes of the base mint, i.e. 256 shards per prefix byte.
+/// The shards of a set partition its accounts. Configured filters, sets
+/// narrowed to configured mints and layouts without mint offsets stay whole.
+pub fn snapshot_shards(program: &ProgramConfig, layouts: &[PoolLayout], prefix_bytes: u8) -> Vec<FilterSet> {
+    let width = prefix_bytes.min(MAX_PREFIX_BYTES) as u32;
+    let mut shards = Vec::new();
+    for (set, offset) in snapshot_requests(program, layouts) {
+        let Some(offset) = offset.filter(|_| width > 0) else {
+            shards.push(set);
+            continue;
+        };
+        for prefix in 0..1u32 << (8 * width) {
+            let bytes = &prefix.to_be_bytes()[4 - width as usize..];
+            let mut shard = set.clone();
+            shard.push(RpcFilterType::Memcmp(Memcmp::new_base58_encoded(offset, bytes)));
+            shards.push(shard);
+        }
+    }
+    shards
+}
+
+/// Snapshot filter sets, each with the offset of its layout's base mint
+/// when the set may be sharded by it.
+fn snapshot_requests(program: &ProgramConfig, layouts: &[PoolLayout]) -> Vec<(FilterSet, Option<usize>)> {
+    let bases: Vec<(FilterSet, Vec<[usize; 2]>)> = match &program.filters {
+        Some(filters) => {
+            let offsets = layouts.iter().filter_map(|l| l.mint_offsets).collect();
+            vec![(filters.iter().map(RpcFilterType::from).collect(), offsets)]
+        }
+        None if layouts.is_empty() => vec![(Vec::new(), Vec::new())],
+        None => layouts.iter().map(|l| (layout_filters(l), l.mint_offsets.into_iter().collect())).collect(),
+    };
+    let mut sets: Vec<(FilterSet, Option<usize>)> = Vec::new();
+    for (base, offsets) in bases {
+        if program.mints.is_empty() || offsets.is_empty() {
+            // configured filters may match accounts shorter than the mint offset
+            let shard_offset = match program.filters {
+                Some(_) => None,
+                None => offsets.first().map(|[base_mint, _]| *base_mint),
+            };
+            sets.push((base, shard_offset));
+            continue;
+        }
+        for offset in offsets.iter().flatten() {
+            for mint in &program.mints {
+                let mut set = base.clone();
+                set.push(RpcFilterType::Memcmp(Memcmp::new_base58_encoded(*offset, mint.as_ref())));
+                sets.push((set, None));
+            }
+        }
+    }
+    dedup_sets(sets)
+}
+
+/// Requests the program subscription is split into. Unlike snapshots, the
+/// subscription stays unfiltered unless filters or mints are configured, as
+/// it also carries the configs, tick arrays and positions of the pools.
+pub fn subscription_filters(program: &ProgramConfig, layouts: &[PoolLayout]) -> Vec<FilterSet> {
+    if program.filters.is_none() && program.mints.is_empty() {
+        return vec![Vec::new()];
+    }
+    snapshot_filters(program, layouts)
+}
+
+fn layout_filters(layout: &PoolLayout) -> FilterSet {
+    let mut filters = Vec::new();
+    if let Some(size) = layout.data_size {
+        filters.push(RpcFilterType::DataSize(size));
+    }
+    if let Some(disc) = layout.discriminator {
+        filters.push(RpcFilterType::Memcmp(Memcmp::new_base58_encoded(0, &disc)));
+    }
+    filters
+}
+
+/// Drop sets that another set already covers, i.e. whose filters include all
+/// of a less specific set's filters.
+fn dedup_sets<T: Clone>(sets: Vec<(FilterSet, T)>) -> Vec<(FilterSet, T)> {
+    let covers = |broad: &FilterSet, narrow: &FilterSet| broad.iter().all(|f| narrow.contains(f));
+    let mut kept = Vec::new();
+    for (i, (set, _)) in sets.iter().enumerate() {
+        let covered = sets.iter().enumerate().any(|(j, (other, _))| {
+            j != i && covers(other, set) && (!covers(set, other) || j < i)
+        });
+        if !covered {
+            kept.push(sets[i].clone());
+        }
+    }
+    kept
+}
+
+pub(crate) fn mint_list<'de, D>(deserializer: D) -> Result<Vec<Pubkey>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|s| s.parse().map_err(serde::de::Error::custom))
+        .collect()
+}
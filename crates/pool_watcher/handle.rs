@@ -0,0 +1,202 @@
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use solana_sdk::pubkey::Pubkey;
+use tokio::sync::watch;
+use crate::endpoints::EndpointStatus;
+use crate::service::ProgramConfig;
+use crate::source::AccountSource;
+use crate::types::DexKind;
+
+/// Control over a [`PoolWatcher`](crate::PoolWatcher) started with
+/// [`spawn`](crate::PoolWatcher::spawn). Dropping the handle leaves the
+/// watcher running.
+pub struct PoolWatcherHandle {
+    thread: thread::JoinHandle<()>,
+    stop: watch::Sender<bool>,
+    status: StatusBoard,
+    source: Arc<dyn AccountSource>,
+}
+
+impl PoolWatcherHandle {
+    pub(crate) fn new(
+        thread: thread::JoinHandle<()>,
+        stop: watch::Sender<bool>,
+        status: StatusBoard,
+        source: Arc<dyn AccountSource>,
+    ) -> Self {
+        Self { thread, stop, status, source }
+    }
+
+    /// Ask the watcher to stop. Subscriptions unsubscribe and close their
+    /// websockets, and a snapshot in progress is abandoned. Returns at once;
+    /// [`join`](Self::join) waits for the watcher to finish.
+    pub fn shutdown(&self) {
+        self.stop.send_replace(true);
+    }
+
+    /// Block until the watcher thread exits, i.e. after [`shutdown`](Self::shutdown)
+    /// or a panic. From async code, call it through `spawn_blocking`.
+    pub fn join(self) -> thread::Result<()> {
+        self.thread.join()
+    }
+
+    pub fn is_running(&self) -> bool {
+        !self.thread.is_finished()
+    }
+
+    pub fn status(&self) -> WatcherStatus {
+        WatcherStatus {
+            running: self.is_running(),
+            programs: self.status.programs(),
+            endpoints: self.source.endpoints(),
+        }
+    }
+}
+
+/// State of the watcher, of each watched program and of each endpoint, in
+/// config order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatcherStatus {
+    pub running: bool,
+    pub programs: Vec<ProgramStatus>,
+    pub endpoints: Vec<EndpointStatus>,
+}
+
+impl WatcherStatus {
+    /// Whether the watcher runs with every subscription connected.
+    pub fn healthy(&self) -> bool {
+        self.running
+            && self.programs.iter().all(|p| {
+                matches!(p.subscription, SubscriptionState::Connected { .. })
+                    && matches!(p.logs, SubscriptionState::Connected { .. })
+            })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgramStatus {
+    pub program: Pubkey,
+    pub kind: DexKind,
+    pub snapshot: SnapshotState,
+    /// Program account subscription.
+    pub subscription: SubscriptionState,
+    /// Log subscription.
+    pub logs: SubscriptionState,
+    /// Unix time in ms of the last account or log notification, or snapshot
+    /// shard, received for the program.
+    pub last_event_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SnapshotState {
+    /// The program is watched through its subscription only.
+    Disabled,
+    /// Waiting for the first snapshot.
+    Pending,
+    Running,
+    /// Last snapshot completed at `at_ms` with `count` pools.
+    Done { count: usize, at_ms: u64 },
+    Failed { error: String, at_ms: u64 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SubscriptionState {
+    Connecting,
+    /// Subscribed through the websocket at `endpoint`.
+    Connected { endpoint: String, since_ms: u64 },
+    /// Waiting to reconnect; `error` is why the last attempt failed, `None`
+    /// when the server ended the stream.
+    Disconnected { error: Option<String> },
+    /// Unsubscribed on shutdown.
+    Stopped,
+}
+
+/// Subscription of a program whose state is tracked.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Feed {
+    Program,
+    Logs,
+}
+
+/// Program states shared between the watcher tasks and the handle.
+#[derive(Clone)]
+pub(crate) struct StatusBoard {
+    programs: Arc<Mutex<Vec<ProgramStatus>>>,
+}
+
+impl StatusBoard {
+    pub(crate) fn new(programs: &[ProgramConfig]) -> Self {
+        let programs = programs
+            .iter()
+            .map(|p| ProgramStatus {
+                program: p.id,
+                kind: p.kind,
+                snapshot: if p.snapshot { SnapshotState::Pending } else { SnapshotState::Disabled },
+                subscription: SubscriptionState::Connecting,
+                logs: SubscriptionState::Connecting,
+                last_event_ms: None,
+            })
+            .collect();
+        Self { programs: Arc::new(Mutex::new(programs)) }
+    }
+
+    fn programs(&self) -> Vec<ProgramStatus> {
+        self.programs.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn update(&self, program: &Pubkey, f: impl FnOnce(&mut ProgramStatus)) {
+        let mut programs = self.programs.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(status) = programs.iter_mut().find(|p| p.program == *program) {
+            f(status);
+        }
+    }
+
+    pub(crate) fn set_snapshot(&self, program: &Pubkey, state: SnapshotState) {
+        self.update(program, |p| p.snapshot = state);
+    }
+
+    pub(crate) fn set_feed(&self, program: &Pubkey, feed: Feed, state: SubscriptionState) {
+        self.update(program, |p| match feed {
+            Feed::Program => p.subscription = state,
+            Feed::Logs => p.logs = state,
+        });
+    }
+
+    /// Record that an event for `program` just arrived.
+    pub(crate) fn touch(&self, program: &Pubkey) {
+        let now = unix_ms();
+        self.update(program, |p| p.last_event_ms = Some(now));
+    }
+}
+
+/// Stop signal the watcher tasks wait on.
+#[derive(Clone)]
+pub(crate) struct Shutdown(watch::Receiver<bool>);
+
+impl Shutdown {
+    pub(crate) fn new(rx: watch::Receiver<bool>) -> Self {
+        Self(rx)
+    }
+
+    pub(crate) fn is_requested(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// Resolves once shutdown is requested. A handle dropped without
+    /// requesting it never stops the watcher.
+    pub(crate) async fn requested(&mut self) {
+        if self.0.wait_for(|stop| *stop).await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+pub(crate) fn unix_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
@@ -1,11 +1,34 @@
-use dashmap::DashMap;
-use std::sync::Arc;
+use dashmap::{DashMap, DashSet};
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+use crate::liquidity::{self, tick_array_address, tick_array_start, ticks_per_array, LiquidityDistribution, TickArray};
+use crate::decoders::raydium_clmm::{self, TickArrayBitmapExtension};
+use crate::positions::Position;
 use crate::types::{PoolId, PoolInfo};
 
 #[derive(Clone, Default)]
 pub struct Inventory {
     // program -> account -> PoolInfo
     inner: Arc<DashMap<String, DashMap<String, PoolInfo>>>,
+    // pool -> start tick index -> tick array
+    tick_arrays: Arc<DashMap<Pubkey, BTreeMap<i32, TickArray>>>,
+    // pool -> Raydium CLMM tick array bitmap extension
+    tick_array_bitmaps: Arc<DashMap<Pubkey, TickArrayBitmapExtension>>,
+    // tick array account -> pool and start tick index
+    tick_array_keys: Arc<DashMap<Pubkey, (Pubkey, i32)>>,
+    // pool -> position account -> position
+    positions: Arc<DashMap<Pubkey, HashMap<Pubkey, Position>>>,
+    // position account -> pool
+    position_pools: Arc<DashMap<Pubkey, Pubkey>>,
+    // account -> slot and write version of the last update applied, for
+    // stored accounts and for tombstones
+    slots: Arc<DashMap<Pubkey, (u64, Option<u64>)>>,
+    // deleted accounts whose slot is kept until the next completed resync, so
+    // that a stale read does not bring them back
+    tombstones: Arc<DashSet<Pubkey>>,
 }
 impl Inventory {
     pub fn upsert(&self, info: PoolInfo) {
@@ -14,9 +37,201 @@ impl Inventory {
         let map = self.inner.entry(pid).or_insert_with(DashMap::new);
         map.insert(aid, info);
     }
-    pub fn remove(&self, id: &PoolId) {
-        if let Some(map) = self.inner.get(&id.program.to_string()) {
-            let _ = map.remove(&id.account.to_string());
+    pub fn get(&self, id: &PoolId) -> Option<PoolInfo> {
+        self.inner
+            .get(&id.program.to_string())
+            .and_then(|map| map.get(&id.account.to_string()).map(|v| v.clone()))
+    }
+    /// Remove a closed pool along with its tick arrays and positions, which
+    /// become tombstones.
+    pub fn remove(&self, id: &PoolId) -> Option<PoolInfo> {
+        if let Some((_, arrays)) = self.tick_arrays.remove(&id.account) {
+            let addresses: Vec<Pubkey> = arrays
+                .values()
+                .filter_map(|array| tick_array_address(array.dex, &id.program, &id.account, array.start_tick_index))
+                .collect();
+            for address in &addresses {
+                self.tick_array_keys.remove(address);
+            }
+            self.bury(addresses);
+        }
+        if self.tick_array_bitmaps.remove(&id.account).is_some() {
+            self.bury([raydium_clmm::tick_array_bitmap_extension_address(&id.program, &id.account)]);
+        }
+        if let Some((_, positions)) = self.positions.remove(&id.account) {
+            for address in positions.keys() {
+                self.position_pools.remove(address);
+            }
+            self.bury(positions.into_keys());
+        }
+        self.bury([id.account]);
+        let map = self.inner.get(&id.program.to_string())?;
+        map.remove(&id.account.to_string()).map(|(_, info)| info)
+    }
+    /// Forget a pool that still exists, along with its slot, so that its next
+    /// update brings it back as new.
+    pub fn evict(&self, id: &PoolId) -> Option<PoolInfo> {
+        self.slots.remove(&id.account);
+        self.tombstones.remove(&id.account);
+        let map = self.inner.get(&id.program.to_string())?;
+        map.remove(&id.account.to_string()).map(|(_, info)| info)
+    }
+    /// Evict the pools of `program` whose last update trails the latest one
+    /// among them by more than `slots`. Returns the number evicted.
+    pub fn evict_idle(&self, program: &Pubkey, slots: u64) -> usize {
+        let Some(map) = self.inner.get(&program.to_string()) else { return 0 };
+        let pools: Vec<(Pubkey, u64)> = map
+            .iter()
+            .filter_map(|pool| Some((pool.id.account, self.slot(&pool.id.account)?)))
+            .collect();
+        drop(map);
+        let Some(latest) = pools.iter().map(|(_, slot)| *slot).max() else { return 0 };
+        let idle: Vec<Pubkey> = pools.into_iter().filter(|(_, slot)| slot + slots < latest).map(|(a, _)| a).collect();
+        for account in &idle {
+            self.evict(&PoolId { program: *program, account: *account });
+        }
+        idle.len()
+    }
+    /// Pools of `program` in the inventory.
+    pub fn program_pools(&self, program: &Pubkey) -> Vec<PoolInfo> {
+        self.inner
+            .get(&program.to_string())
+            .map(|map| map.iter().map(|pool| pool.clone()).collect())
+            .unwrap_or_default()
+    }
+    /// Pools referencing the config account `config`.
+    pub fn pools_with_config(&self, config: &Pubkey) -> Vec<PoolInfo> {
+        self.inner
+            .iter()
+            .flat_map(|program| {
+                program
+                    .iter()
+                    .filter(|pool| pool.config.as_ref() == Some(config))
+                    .map(|pool| pool.clone())
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+    pub fn upsert_tick_array(&self, address: Pubkey, array: TickArray) {
+        self.tick_array_keys.insert(address, (array.pool, array.start_tick_index));
+        self.tick_arrays
+            .entry(array.pool)
+            .or_default()
+            .insert(array.start_tick_index, array);
+    }
+    pub fn upsert_tick_array_bitmap(&self, ext: TickArrayBitmapExtension) {
+        self.tick_array_bitmaps.insert(ext.pool_id, ext);
+    }
+    pub fn tick_array_bitmap(&self, pool: &Pubkey) -> Option<TickArrayBitmapExtension> {
+        self.tick_array_bitmaps.get(pool).map(|ext| ext.clone())
+    }
+    /// Forget a closed tick array account, keeping its slot as a tombstone.
+    pub fn remove_tick_array(&self, address: &Pubkey) -> Option<TickArray> {
+        self.bury([*address]);
+        let (_, (pool, start)) = self.tick_array_keys.remove(address)?;
+        self.tick_arrays.get_mut(&pool)?.remove(&start)
+    }
+    pub fn tick_arrays(&self, pool: &Pubkey) -> Vec<TickArray> {
+        self.tick_arrays
+            .get(pool)
+            .map(|arrays| arrays.values().cloned().collect())
+            .unwrap_or_default()
+    }
+    /// Distribution of a CLMM pool from the tick arrays seen so far, when they
+    /// hold every array within `price_move` of the current price. Snapshots
+    /// skip tick arrays, so older pools usually need them fetched instead.
+    pub fn liquidity_distribution(&self, info: &PoolInfo, price_move: f64) -> Option<LiquidityDistribution> {
+        let arrays = self.tick_arrays.get(&info.id.account)?;
+        let (tick, spacing) = (info.tick_current?, info.tick_spacing?);
+        let reach = liquidity::ticks_for_move(price_move);
+        let first = tick_array_start(info.dex, tick - reach, spacing)?;
+        let last = tick_array_start(info.dex, tick + reach, spacing)?;
+        let span = ticks_per_array(info.dex)? * spacing as i32;
+        let mut starts = (first..=last).step_by(span as usize);
+        if !starts.all(|start| arrays.contains_key(&start)) {
+            return None;
+        }
+        liquidity::distribution(info, arrays.range(first..=last).map(|(_, array)| array))
+    }
+    /// Store a position, keeping the owner resolved for an earlier version.
+    pub fn upsert_position(&self, mut position: Position) {
+        if let Some(previous) = self.position_pools.insert(position.address, position.pool) {
+            // a position never changes pools, but do not leave a copy behind
+            if previous != position.pool {
+                if let Some(mut positions) = self.positions.get_mut(&previous) {
+                    positions.remove(&position.address);
+                }
+            }
+        }
+        let mut positions = self.positions.entry(position.pool).or_default();
+        if let Some(previous) = positions.get(&position.address) {
+            if position.owner.is_none() && previous.position_mint == position.position_mint {
+                position.owner = previous.owner;
+            }
+        }
+        positions.insert(position.address, position);
+    }
+    /// Forget a closed position account, keeping its slot as a tombstone.
+    pub fn remove_position(&self, address: &Pubkey) -> Option<Position> {
+        self.bury([*address]);
+        let (_, pool) = self.position_pools.remove(address)?;
+        self.positions.get_mut(&pool)?.remove(address)
+    }
+    /// Whether `account` is a position or tick array in the inventory.
+    pub fn contains_account(&self, account: &Pubkey) -> bool {
+        self.tick_array_keys.contains_key(account) || self.position_pools.contains_key(account)
+    }
+    pub fn positions(&self, pool: &Pubkey) -> Vec<Position> {
+        self.positions
+            .get(pool)
+            .map(|positions| positions.values().cloned().collect())
+            .unwrap_or_default()
+    }
+    /// Record an update of `account` observed at `slot`. Returns `false` when
+    /// the stored state comes from a later slot, i.e. the update is stale.
+    pub fn record_slot(&self, account: Pubkey, slot: u64) -> bool {
+        self.record_update(account, slot, None)
+    }
+    /// Like [`record_slot`](Self::record_slot), also ordering updates of the
+    /// same slot by the validator's `write_version` when both carry one.
+    pub fn record_update(&self, account: Pubkey, slot: u64, write_version: Option<u64>) -> bool {
+        let mut last = self.slots.entry(account).or_insert((slot, write_version));
+        let (last_slot, last_version) = *last;
+        let stale = match (last_version, write_version) {
+            _ if last_slot != slot => last_slot > slot,
+            (Some(last_version), Some(version)) => last_version > version,
+            _ => false,
+        };
+        if stale {
+            return false;
+        }
+        let version = if last_slot == slot { write_version.or(last_version) } else { write_version };
+        *last = (slot, version);
+        drop(last);
+        // the account exists again
+        self.tombstones.remove(&account);
+        true
+    }
+    /// Slot of the last update applied to `account`, while it is stored or a
+    /// tombstone.
+    pub fn slot(&self, account: &Pubkey) -> Option<u64> {
+        self.slots.get(account).map(|s| s.0)
+    }
+    /// Forget the slots of accounts deleted before `slot`. Called once a
+    /// resync read every program at `slot` or later, so that older reads
+    /// cannot arrive anymore.
+    pub fn prune_tombstones(&self, slot: u64) {
+        self.tombstones.retain(|account| {
+            self.slots.remove_if(account, |_, (last, _)| *last < slot);
+            self.slots.contains_key(account)
+        });
+    }
+    /// Keep the slots of deleted `accounts` until the next completed resync.
+    fn bury(&self, accounts: impl IntoIterator<Item = Pubkey>) {
+        for account in accounts {
+            if self.slots.contains_key(&account) {
+                self.tombstones.insert(account);
+            }
         }
     }
     pub fn count_program(&self, program: &solana_sdk::pubkey::Pubkey) -> usize {
@@ -1,12 +1,24 @@
 pub mod bus;
+pub mod configs;
 pub mod decoders;
+pub mod endpoints;
+pub mod filters;
+pub mod handle;
 pub mod inventory;
+pub mod liquidity;
+pub mod positions;
 pub mod service;
+pub mod source;
 pub mod token;
 pub mod types;
 
 pub use bus::{PoolBus, SharedPoolBus};
-pub use decoders::TokenIntrospectionProvider;
-pub use service::{PoolWatcher, PoolWatcherConfig, ProgramConfig};
+pub use configs::{ConfigAccount, ConfigRegistry};
+pub use decoders::{DecodeOutcome, DecoderRegistry, PoolDecoder, TokenIntrospectionProvider};
+pub use handle::{PoolWatcherHandle, ProgramStatus, SnapshotState, SubscriptionState, WatcherStatus};
+pub use endpoints::{EndpointConfig, EndpointStatus, HealthConfig};
+pub use inventory::Inventory;
+pub use source::{AccountSource, GrpcConfig, GrpcSource, RpcSource};
+pub use service::{PoolWatcher, PoolWatcherConfig, ProgramConfig, ReconnectConfig, SnapshotConfig};
 pub use token::TokenSafetyProvider;
-pub use types::{DexKind, PoolEvent, PoolId, PoolInfo};
+pub use types::{DexKind, DexState, FeeRate, PoolEvent, PoolField, PoolId, PoolInfo};
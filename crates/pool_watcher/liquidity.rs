@@ -0,0 +1,124 @@
+use std::collections::BTreeMap;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use crate::decoders::raydium_clmm::TickArrayBitmapExtension;
+use crate::decoders::{orca_whirl, raydium_clmm};
+use crate::types::{DexKind, PoolInfo};
+
+pub use common_types::liquidity::{Depth, LiquidityDistribution, LiquidityRange};
+
+/// Tick array account of a concentrated liquidity pool. Only initialized
+/// ticks are kept.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TickArray {
+    pub dex: DexKind,
+    pub pool: Pubkey,
+    pub start_tick_index: i32,
+    pub ticks: Vec<InitializedTick>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct InitializedTick {
+    /// Position in the array; the tick index is `start + offset * tick_spacing`.
+    pub offset: u16,
+    /// Liquidity added when the price crosses this tick upwards.
+    pub liquidity_net: i128,
+}
+
+/// Number of ticks in one tick array account.
+pub fn ticks_per_array(dex: DexKind) -> Option<i32> {
+    match dex {
+        DexKind::OrcaWhirlpools => Some(orca_whirl::TICK_ARRAY_SIZE as i32),
+        DexKind::RaydiumClmm => Some(raydium_clmm::TICK_ARRAY_SIZE as i32),
+        _ => None,
+    }
+}
+
+/// Start index of the tick array holding `tick`.
+pub fn tick_array_start(dex: DexKind, tick: i32, tick_spacing: u16) -> Option<i32> {
+    let span = ticks_per_array(dex)? * tick_spacing as i32;
+    (span > 0).then(|| tick.div_euclid(span) * span)
+}
+
+/// Ticks the price crosses when it moves by `price_move` (0.02 for 2%) either way.
+pub fn ticks_for_move(price_move: f64) -> i32 {
+    ((1.0 + price_move).ln() / 1.0001f64.ln()).ceil() as i32
+}
+
+pub fn tick_array_address(dex: DexKind, program: &Pubkey, pool: &Pubkey, start: i32) -> Option<Pubkey> {
+    // Whirlpool seeds the start index as a decimal string, Raydium as big-endian bytes
+    let start_seed = match dex {
+        DexKind::OrcaWhirlpools => start.to_string().into_bytes(),
+        DexKind::RaydiumClmm => start.to_be_bytes().to_vec(),
+        _ => return None,
+    };
+    Some(Pubkey::find_program_address(&[b"tick_array", pool.as_ref(), &start_seed], program).0)
+}
+
+/// Distribution of `info` from tick arrays of the same pool.
+pub fn distribution<'a>(info: &PoolInfo, arrays: impl IntoIterator<Item = &'a TickArray>) -> Option<LiquidityDistribution> {
+    let spacing = info.tick_spacing? as i32;
+    let span = ticks_per_array(info.dex)? * spacing;
+    let tick_current = info.tick_current?;
+    let mut ticks = BTreeMap::new();
+    let mut covered: Option<(i32, i32)> = None;
+    for array in arrays.into_iter().filter(|a| a.pool == info.id.account) {
+        for tick in &array.ticks {
+            ticks.insert(array.start_tick_index + tick.offset as i32 * spacing, tick.liquidity_net);
+        }
+        let (lo, hi) = (array.start_tick_index, array.start_tick_index + span);
+        covered = Some(covered.map_or((lo, hi), |(a, b)| (a.min(lo), b.max(hi))));
+    }
+    Some(LiquidityDistribution {
+        tick_current,
+        sqrt_price_x64: info.sqrt_price_x64?,
+        liquidity: info.liquidity?,
+        ticks,
+        covered: covered?,
+    })
+}
+
+/// Fetch the tick arrays around the current price of a Whirlpool or Raydium
+/// CLMM pool and build its distribution. `arrays_each_side` arrays are loaded
+/// below and above the one holding the current tick; uninitialized ones are
+/// skipped, and not even requested when a Raydium `bitmap` extension marks
+/// them so. Returns `None` for other pools.
+pub fn fetch_distribution(
+    rpc: &RpcClient,
+    info: &PoolInfo,
+    arrays_each_side: i32,
+    bitmap: Option<&TickArrayBitmapExtension>,
+) -> Result<Option<LiquidityDistribution>> {
+    let (Some(tick), Some(spacing)) = (info.tick_current, info.tick_spacing) else { return Ok(None) };
+    let (Some(start), Some(per_array)) = (tick_array_start(info.dex, tick, spacing), ticks_per_array(info.dex)) else {
+        return Ok(None);
+    };
+    let span = per_array * spacing as i32;
+    let addresses: Vec<Pubkey> = (-arrays_each_side..=arrays_each_side)
+        .map(|i| start + i * span)
+        .filter(|start| bitmap.and_then(|ext| ext.is_initialized(*start, spacing)) != Some(false))
+        .filter_map(|start| tick_array_address(info.dex, &info.id.program, &info.id.account, start))
+        .collect();
+    let accounts: Vec<_> = rpc.get_multiple_accounts(&addresses)?.into_iter().flatten().collect();
+    let arrays: Vec<TickArray> = accounts
+        .iter()
+        .filter_map(|acc| match info.dex {
+            DexKind::OrcaWhirlpools => orca_whirl::parse_tick_array(&acc.data),
+            DexKind::RaydiumClmm => raydium_clmm::parse_tick_array(&acc.data),
+            _ => None,
+        })
+        .collect();
+    // a layout we cannot read would leave holes in the distribution
+    if arrays.len() < accounts.len() {
+        return Ok(None);
+    }
+    // a pool without any initialized array still has its active liquidity
+    let arrays = if arrays.is_empty() {
+        vec![TickArray { dex: info.dex, pool: info.id.account, start_tick_index: start, ticks: Vec::new() }]
+    } else {
+        arrays
+    };
+    Ok(distribution(info, &arrays))
+}
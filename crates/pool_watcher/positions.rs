@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use solana_sdk::pubkey::Pubkey;
+use tracing::debug;
+use crate::decoders::{orca_whirl, raydium_clmm, read_pubkey};
+use crate::types::{DexKind, PoolInfo};
+
+/// Share of a pool's liquidity above which one wallet can pull it all.
+pub const SINGLE_LP_SHARE: f64 = 0.95;
+/// Positions whose owner [`resolve_owners`] looks up, largest first.
+pub const MAX_OWNER_LOOKUPS: usize = 20;
+
+/// Liquidity position in a CLMM pool. Positions are NFTs: the owner is the
+/// wallet holding `position_mint`, which the account itself does not store.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Position {
+    pub dex: DexKind,
+    pub address: Pubkey,
+    pub pool: Pubkey,
+    pub position_mint: Pubkey,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity: u128,
+    /// Holder of `position_mint`, once resolved with [`resolve_owners`].
+    pub owner: Option<Pubkey>,
+}
+
+impl Position {
+    /// Whether the position earns fees at `tick`.
+    pub fn in_range(&self, tick: i32) -> bool {
+        (self.tick_lower..self.tick_upper).contains(&tick)
+    }
+}
+
+/// How a pool's liquidity is split between LP wallets.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Concentration {
+    /// Positions holding the liquidity measured.
+    pub positions: usize,
+    /// Distinct owners; positions with an unresolved owner count separately.
+    pub owners: usize,
+    /// Largest holder, `None` when its owner was not resolved.
+    pub top_owner: Option<Pubkey>,
+    /// Share of the measured liquidity held by the largest holder.
+    pub top_share: f64,
+}
+
+impl Concentration {
+    pub fn single_lp(&self) -> bool {
+        self.top_share >= SINGLE_LP_SHARE
+    }
+}
+
+/// Split the liquidity of `positions` by owner. With `tick_current` only
+/// positions in range count, as those make up the pool's active liquidity;
+/// when none is in range all positions are measured instead.
+pub fn concentration(positions: &[Position], tick_current: Option<i32>) -> Option<Concentration> {
+    let funded: Vec<&Position> = positions.iter().filter(|p| p.liquidity > 0).collect();
+    let active: Vec<&Position> = match tick_current {
+        Some(tick) => funded.iter().copied().filter(|p| p.in_range(tick)).collect(),
+        None => Vec::new(),
+    };
+    let measured = if active.is_empty() { funded } else { active };
+
+    let mut by_holder: HashMap<Pubkey, (u128, bool)> = HashMap::new();
+    for p in &measured {
+        let holder = by_holder.entry(p.owner.unwrap_or(p.position_mint)).or_insert((0, p.owner.is_some()));
+        holder.0 = holder.0.saturating_add(p.liquidity);
+    }
+    let total: f64 = by_holder.values().map(|(liquidity, _)| *liquidity as f64).sum();
+    let (top, (top_liquidity, resolved)) = by_holder.iter().max_by_key(|(_, (liquidity, _))| *liquidity)?;
+    Some(Concentration {
+        positions: measured.len(),
+        owners: by_holder.len(),
+        top_owner: resolved.then_some(*top),
+        top_share: *top_liquidity as f64 / total,
+    })
+}
+
+/// Load every position of a Whirlpool or Raydium CLMM pool. Returns an empty
+/// list for other pools.
+pub fn fetch_positions(rpc: &RpcClient, info: &PoolInfo) -> Result<Vec<Position>> {
+    let (size, pool_offset) = match info.dex {
+        DexKind::OrcaWhirlpools => (orca_whirl::POSITION_LEN, orca_whirl::POSITION_POOL_OFFSET),
+        DexKind::RaydiumClmm => (raydium_clmm::PERSONAL_POSITION_LEN, raydium_clmm::PERSONAL_POSITION_POOL_OFFSET),
+        _ => return Ok(Vec::new()),
+    };
+    let cfg = RpcProgramAccountsConfig {
+        filters: Some(vec![
+            RpcFilterType::DataSize(size as u64),
+            RpcFilterType::Memcmp(Memcmp::new_base58_encoded(pool_offset, info.id.account.as_ref())),
+        ]),
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            ..Default::default()
+        },
+        with_context: None,
+        sort_results: None,
+    };
+    let accounts = rpc.get_program_accounts_with_config(&info.id.program, cfg)?;
+    Ok(accounts
+        .into_iter()
+        .filter_map(|(address, acc)| match info.dex {
+            DexKind::OrcaWhirlpools => orca_whirl::parse_position(address, &acc.data),
+            _ => raydium_clmm::parse_personal_position(address, &acc.data),
+        })
+        .collect())
+}
+
+/// Fill in the owner of positions that have none by looking up the holder
+/// of each position NFT. Each NFT takes a request of its own, so only the
+/// [`MAX_OWNER_LOOKUPS`] largest positions are resolved; the rest count as
+/// separate owners. Positions whose NFT cannot be looked up stay unresolved.
+pub fn resolve_owners(rpc: &RpcClient, positions: &mut [Position]) -> Result<()> {
+    let mut unresolved: Vec<usize> = (0..positions.len()).filter(|i| positions[*i].owner.is_none()).collect();
+    unresolved.sort_by_key(|i| std::cmp::Reverse(positions[*i].liquidity));
+    let mut holders: Vec<(usize, Pubkey)> = Vec::new();
+    for i in unresolved.into_iter().take(MAX_OWNER_LOOKUPS) {
+        let mint = positions[i].position_mint;
+        let largest = match rpc.get_token_largest_accounts(&mint) {
+            Ok(largest) => largest,
+            Err(e) => {
+                debug!(err=%e, %mint, "position holder lookup failed");
+                continue;
+            }
+        };
+        let account = largest.into_iter().find(|a| a.amount.amount == "1");
+        if let Some(account) = account.and_then(|a| a.address.parse().ok()) {
+            holders.push((i, account));
+        }
+    }
+    for chunk in holders.chunks(100) {
+        let keys: Vec<Pubkey> = chunk.iter().map(|(_, account)| *account).collect();
+        let accounts = rpc.get_multiple_accounts(&keys)?;
+        for ((i, _), account) in chunk.iter().zip(accounts) {
+            // SPL token accounts store the mint first, then the owner
+            positions[*i].owner = account.and_then(|a| read_pubkey(&a.data, 32));
+        }
+    }
+    Ok(())
+}
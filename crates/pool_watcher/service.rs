@@ -1,27 +1,35 @@
 use crate::{
     bus::SharedPoolBus,
-    decoders::{decode_pool, TokenIntrospectionProvider},
+    configs::{ConfigAccount, ConfigRegistry},
+    endpoints::{EndpointConfig, HealthConfig},
+    filters::{self, AccountFilter, FilterSet},
+    handle::{unix_ms, Feed, PoolWatcherHandle, Shutdown, SnapshotState, StatusBoard, SubscriptionState},
+    decoders::{
+        idl::{IdlDecoder, IdlDecoderConfig},
+        pump_fun, pump_swap, set_token2022_flags, DecodeOutcome, DecoderRegistry, TokenIntrospectionProvider,
+    },
     inventory::Inventory,
-    types::{DexKind, PoolEvent},
+    source::{AccountSnapshot, AccountSource, AccountUpdate, GrpcConfig, GrpcSource, LogNotification, RpcSource},
+    types::{DexKind, OpenAt, PoolEvent, PoolId, PoolInfo},
 };
+use dashmap::DashMap;
 use serde::Deserialize;
-use solana_account_decoder::UiAccountEncoding;
-use solana_client::{
-    nonblocking::pubsub_client::PubsubClient,
-    rpc_client::RpcClient,
-    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcTransactionLogsConfig},
-    rpc_response::{Response, RpcKeyedAccount},
-};
-use solana_commitment_config::CommitmentConfig;
 use solana_sdk::pubkey::Pubkey;
-use std::{str::FromStr, sync::Arc, thread};
+use std::{
+    collections::HashSet,
+    future::Future,
+    str::FromStr,
+    sync::Arc,
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
 use tokio::{
     runtime::Builder,
-    time::{sleep, Duration},
+    sync::watch,
+    time::{interval, sleep, Duration},
 };
-use tracing::error;
+use tracing::{debug, error, info, warn};
 use futures::StreamExt;
-use base64::{engine::general_purpose, Engine as _};
 
 mod pubkey_serde {
     use std::str::FromStr;
@@ -42,14 +50,88 @@ pub struct ProgramConfig {
     #[serde(deserialize_with = "pubkey_serde::deserialize")]
     pub id: Pubkey,
     pub kind: DexKind,
+    /// Fetch all program accounts on start and on resync. Programs with a very
+    /// large number of accounts (pump.fun) can be watched via subscription only.
+    #[serde(default = "default_true")]
+    pub snapshot: bool,
+    /// Decode the program's pools from an Anchor IDL instead of a built-in decoder.
+    #[serde(default)]
+    pub idl: Option<IdlDecoderConfig>,
+    /// Server-side filters for snapshots and the subscription, in place of
+    /// the pool layouts of the program's decoders.
+    #[serde(default)]
+    pub filters: Option<Vec<AccountFilter>>,
+    /// Only watch pools with one of these mints on either side.
+    #[serde(default, deserialize_with = "filters::mint_list")]
+    pub mints: Vec<Pubkey>,
+}
+
+fn default_true() -> bool {
+    true
 }
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct PoolWatcherConfig {
+    /// Single endpoint, used when `endpoints` is empty.
+    #[serde(default)]
     pub rpc_url: String,
+    #[serde(default)]
     pub ws_url: String,
+    /// Endpoints to fail over between, in place of `rpc_url` and `ws_url`.
+    #[serde(default)]
+    pub endpoints: Vec<EndpointConfig>,
     pub programs: Vec<ProgramConfig>,
     pub periodic_resync_min: u64,
+    #[serde(default)]
+    pub reconnect: ReconnectConfig,
+    #[serde(default)]
+    pub snapshot: SnapshotConfig,
+    #[serde(default)]
+    pub health: HealthConfig,
+    /// Stream accounts and transactions from a Geyser gRPC endpoint instead
+    /// of websockets.
+    #[serde(default)]
+    pub grpc: Option<GrpcConfig>,
+}
+
+/// Delay before resubscribing after a websocket drops, doubled after each
+/// failed attempt up to `max_ms`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ReconnectConfig {
+    pub initial_ms: u64,
+    pub max_ms: u64,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self { initial_ms: 500, max_ms: 30_000 }
+    }
+}
+
+/// Slots, about an hour, after which a bonding curve no update touched is
+/// dropped from the inventory.
+const CURVE_TTL_SLOTS: u64 = 9_000;
+
+/// How snapshots are split into `getProgramAccounts` requests.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct SnapshotConfig {
+    /// Shard each request by this many leading bytes of the base mint, 256
+    /// shards per byte and at most 2 bytes; 0 sends one request per layout.
+    /// The default of 1 turns each snapshot into about 256 requests per
+    /// layout, `concurrency` at a time; use 0 on rate-limited providers.
+    pub shard_prefix_bytes: u8,
+    /// Shards of a program fetched at once.
+    pub concurrency: usize,
+    /// Reads of a shard, with the reconnect backoff between them, before the
+    /// program's snapshot fails.
+    pub shard_attempts: u32,
+}
+
+impl Default for SnapshotConfig {
+    fn default() -> Self {
+        Self { shard_prefix_bytes: 1, concurrency: 8, shard_attempts: 3 }
+    }
 }
 
 impl Default for PoolWatcherConfig {
@@ -57,12 +139,21 @@ impl Default for PoolWatcherConfig {
         Self {
             rpc_url: "https://api.mainnet-beta.solana.com".into(),
             ws_url: "wss://api.mainnet-beta.solana.com".into(),
+            endpoints: Vec::new(),
             periodic_resync_min: 30,
+            reconnect: ReconnectConfig::default(),
+            snapshot: SnapshotConfig::default(),
+            health: HealthConfig::default(),
+            grpc: None,
             programs: vec![
                 ProgramConfig {
                     kind: DexKind::OrcaWhirlpools,
                     id: Pubkey::from_str("whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc")
                         .expect("program id"),
+                    snapshot: true,
+                    idl: None,
+                    filters: None,
+                    mints: Vec::new(),
                 },
                 ProgramConfig {
                     kind: DexKind::RaydiumClmm,
@@ -70,13 +161,81 @@ impl Default for PoolWatcherConfig {
                         "CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK",
                     )
                     .expect("program id"),
+                    snapshot: true,
+                    idl: None,
+                    filters: None,
+                    mints: Vec::new(),
                 },
                 ProgramConfig {
                     kind: DexKind::RaydiumCpmm,
+                    id: Pubkey::from_str(
+                        "CPMMoo8L3F4NbTegBCKVNunggL7H1ZpdTHKxQB5qKP1C",
+                    )
+                    .expect("program id"),
+                    snapshot: true,
+                    idl: None,
+                    filters: None,
+                    mints: Vec::new(),
+                },
+                ProgramConfig {
+                    kind: DexKind::RaydiumAmmV4,
                     id: Pubkey::from_str(
                         "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8",
                     )
                     .expect("program id"),
+                    snapshot: true,
+                    idl: None,
+                    filters: None,
+                    mints: Vec::new(),
+                },
+                ProgramConfig {
+                    kind: DexKind::MeteoraDlmm,
+                    id: Pubkey::from_str(
+                        "LBUZKhRxPF3XUpBCjp4YzTKgLccjZhTSDM9YuVaPwxo",
+                    )
+                    .expect("program id"),
+                    snapshot: true,
+                    idl: None,
+                    filters: None,
+                    mints: Vec::new(),
+                },
+                ProgramConfig {
+                    kind: DexKind::MeteoraDamm,
+                    id: Pubkey::from_str(
+                        "Eo7WjKq67rjJQSZxS6z3YkapzY3eMj6Xy8X5EQVn5UaB",
+                    )
+                    .expect("program id"),
+                    snapshot: true,
+                    idl: None,
+                    filters: None,
+                    mints: Vec::new(),
+                },
+                ProgramConfig {
+                    kind: DexKind::MeteoraDamm,
+                    id: Pubkey::from_str(
+                        "cpamdpZCGKUy5JxQXB4dcpGPiikHswburH8Y7u8MQmG",
+                    )
+                    .expect("program id"),
+                    snapshot: true,
+                    idl: None,
+                    filters: None,
+                    mints: Vec::new(),
+                },
+                ProgramConfig {
+                    kind: DexKind::PumpFun,
+                    id: pump_fun::PROGRAM_ID,
+                    snapshot: false,
+                    idl: None,
+                    filters: None,
+                    mints: Vec::new(),
+                },
+                ProgramConfig {
+                    kind: DexKind::PumpSwap,
+                    id: pump_swap::PROGRAM_ID,
+                    snapshot: true,
+                    idl: None,
+                    filters: None,
+                    mints: Vec::new(),
                 },
             ],
         }
@@ -87,9 +246,17 @@ pub struct PoolWatcher {
     cfg: PoolWatcherConfig,
     bus: SharedPoolBus,
     inventory: Inventory,
+    configs: ConfigRegistry,
+    scheduled: Scheduled,
     token: Arc<dyn TokenIntrospectionProvider>,
+    decoders: Arc<DecoderRegistry>,
+    status: StatusBoard,
+    source: Arc<dyn AccountSource>,
 }
 
+/// Pools that do not accept swaps yet, keyed by account.
+type Scheduled = Arc<DashMap<Pubkey, PoolId>>;
+
 impl PoolWatcher {
     pub fn new(
         cfg: PoolWatcherConfig,
@@ -97,16 +264,53 @@ impl PoolWatcher {
         token: Arc<dyn TokenIntrospectionProvider>,
     ) -> Self {
         Self {
-            cfg,
             bus,
             inventory: Inventory::default(),
+            configs: ConfigRegistry::default(),
+            scheduled: Scheduled::default(),
+            decoders: Arc::new(DecoderRegistry::builtin()),
+            status: StatusBoard::new(&cfg.programs),
+            source: match cfg.grpc.clone() {
+                Some(grpc) => Arc::new(GrpcSource::new(&cfg, grpc)),
+                None => Arc::new(RpcSource::new(&cfg)),
+            },
+            cfg,
             token,
         }
     }
 
+    /// Replace the built-in decoders, e.g. with a registry that also holds
+    /// decoders for DEXes this crate does not know about. Programs configured
+    /// with an IDL get their decoder on top of these when the watcher starts.
+    pub fn with_decoders(mut self, decoders: DecoderRegistry) -> Self {
+        self.decoders = Arc::new(decoders);
+        self
+    }
+
+    /// Read accounts and logs from `source` instead of the configured RPC
+    /// endpoints, e.g. a gRPC feed or a replay.
+    pub fn with_source(mut self, source: Arc<dyn AccountSource>) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// Handle on the pools and tick arrays the watcher has seen; stays live
+    /// after [`PoolWatcher::spawn`].
+    pub fn inventory(&self) -> Inventory {
+        self.inventory.clone()
+    }
+
+    /// Config accounts (Raydium `AmmConfig`, Whirlpool configs and fee tiers)
+    /// the watched pools reference; stays live after [`PoolWatcher::spawn`].
+    pub fn configs(&self) -> ConfigRegistry {
+        self.configs.clone()
+    }
+
     /// Spawn in a dedicated OS thread with its own multi-thread Tokio runtime.
-    pub fn spawn(self) {
-        thread::Builder::new()
+    pub fn spawn(self) -> PoolWatcherHandle {
+        let (stop, rx) = watch::channel(false);
+        let (status, source) = (self.status.clone(), self.source.clone());
+        let thread = thread::Builder::new()
             .name("pool-watcher".into())
             .spawn(move || {
                 let rt = Builder::new_multi_thread()
@@ -114,204 +318,644 @@ impl PoolWatcher {
                     .enable_all()
                     .build()
                     .unwrap();
-                rt.block_on(self.run());
+                rt.block_on(self.run(Shutdown::new(rx)));
             })
             .expect("spawn pool-watcher");
+        PoolWatcherHandle::new(thread, stop, status, source)
     }
 
-    async fn run(self) {
-        let rpc = RpcClient::new(self.cfg.rpc_url.clone());
+    async fn run(mut self, mut shutdown: Shutdown) {
+        // loaded once per watcher, since IDL decoders leak their account names
+        self.decoders = Arc::new(with_idl_decoders((*self.decoders).clone(), &self.cfg.programs));
+
         // Initial snapshot
-        for prog in &self.cfg.programs {
-            self.bus
-                .publish(PoolEvent::SnapshotStarted { program: prog.id });
-            match self.full_snapshot_program(&rpc, prog).await {
-                Ok(count) => self.bus.publish(PoolEvent::SnapshotFinished {
-                    program: prog.id,
-                    count,
-                }),
-                Err(e) => error!(err=%e, "snapshot failed"),
-            }
+        let initial = async {
+            self.source.start().await;
+            self.snapshot_all().await;
+        };
+        tokio::select! {
+            _ = initial => {}
+            _ = shutdown.requested() => return,
         }
 
         // Subscriptions
+        let mut tasks = Vec::new();
         for prog in self.cfg.programs.clone() {
-            let ws = self.cfg.ws_url.clone();
-            let bus = self.bus.clone();
-            let inv = self.inventory.clone();
-            let token = self.token.clone();
+            let ctx = SubscriptionContext {
+                source: self.source.clone(),
+                bus: self.bus.clone(),
+                inventory: self.inventory.clone(),
+                configs: self.configs.clone(),
+                scheduled: self.scheduled.clone(),
+                token: self.token.clone(),
+                decoders: self.decoders.clone(),
+                status: self.status.clone(),
+                shutdown: shutdown.clone(),
+            };
+            let supervisor = |feed| Supervisor {
+                program: prog.id,
+                feed,
+                backoff: self.cfg.reconnect.clone(),
+                status: self.status.clone(),
+                shutdown: shutdown.clone(),
+            };
             let prog_clone = prog.clone();
-            tokio::spawn(async move {
-                if let Err(e) = subscribe_program(ws, prog_clone, bus, inv, token).await {
-                    error!(err=%e, "program subscribe failed");
-                }
-            });
+            tasks.push(tokio::spawn(supervisor(Feed::Program).run(move |reconnect| {
+                let (prog, ctx) = (prog_clone.clone(), ctx.clone());
+                async move { subscribe_program(&prog, &ctx, reconnect).await }
+            })));
 
-            let ws2 = self.cfg.ws_url.clone();
+            let source = self.source.clone();
             let bus2 = self.bus.clone();
             let prog_clone2 = prog.clone();
-            tokio::spawn(async move {
-                if let Err(e) = subscribe_logs(ws2, prog_clone2, bus2).await {
-                    error!(err=%e, "logs subscribe failed");
-                }
-            });
+            let (status2, shutdown2) = (self.status.clone(), shutdown.clone());
+            tasks.push(tokio::spawn(supervisor(Feed::Logs).run(move |_| {
+                subscribe_logs(
+                    source.clone(),
+                    prog_clone2.clone(),
+                    bus2.clone(),
+                    status2.clone(),
+                    shutdown2.clone(),
+                )
+            })));
         }
 
+        let (source, mut stop) = (self.source.clone(), shutdown.clone());
+        tasks.push(tokio::spawn(async move {
+            tokio::select! {
+                _ = source.maintain() => {}
+                _ = stop.requested() => {}
+            }
+        }));
+        tasks.push(tokio::spawn(watch_openings(
+            self.source.clone(),
+            self.scheduled.clone(),
+            self.inventory.clone(),
+            self.bus.clone(),
+            shutdown.clone(),
+        )));
+
         // Periodic resync
         let mins = self.cfg.periodic_resync_min.max(5);
         loop {
-            sleep(Duration::from_secs(mins * 60)).await;
+            tokio::select! {
+                _ = sleep(Duration::from_secs(mins * 60)) => {}
+                _ = shutdown.requested() => break,
+            }
             self.bus.publish(PoolEvent::ResyncTick {
                 program: Pubkey::default(),
             });
-            for prog in &self.cfg.programs {
-                self.bus
-                    .publish(PoolEvent::SnapshotStarted { program: prog.id });
-                match self.full_snapshot_program(&rpc, prog).await {
-                    Ok(count) => self.bus.publish(PoolEvent::SnapshotFinished {
+            self.configs.retry_absent();
+            // pump.fun never closes curves, not even after migration
+            for prog in self.cfg.programs.iter().filter(|p| p.kind == DexKind::PumpFun) {
+                self.inventory.evict_idle(&prog.id, CURVE_TTL_SLOTS);
+            }
+            tokio::select! {
+                _ = self.snapshot_all() => {}
+                _ = shutdown.requested() => break,
+            }
+        }
+        // let the subscriptions unsubscribe before the runtime goes away
+        for task in tasks {
+            let _ = task.await;
+        }
+        info!("pool watcher stopped");
+    }
+
+    /// Snapshot every program that has snapshots enabled, one after another.
+    /// Once all of them were read, deleted accounts no longer need their
+    /// tombstones.
+    async fn snapshot_all(&self) {
+        let mut oldest = Some(u64::MAX);
+        for prog in self.cfg.programs.iter().filter(|p| p.snapshot) {
+            self.bus
+                .publish(PoolEvent::SnapshotStarted { program: prog.id });
+            self.status.set_snapshot(&prog.id, SnapshotState::Running);
+            match self.full_snapshot_program(prog).await {
+                Ok((count, slot)) => {
+                    oldest = oldest.map(|oldest| oldest.min(slot));
+                    self.status.set_snapshot(&prog.id, SnapshotState::Done { count, at_ms: unix_ms() });
+                    self.bus.publish(PoolEvent::SnapshotFinished {
                         program: prog.id,
                         count,
-                    }),
-                    Err(e) => error!(err=%e, "snapshot failed"),
+                    })
+                }
+                Err(e) => {
+                    error!(err=%e, "snapshot failed");
+                    let error = format!("{e:#}");
+                    self.status.set_snapshot(&prog.id, SnapshotState::Failed { error, at_ms: unix_ms() });
+                    oldest = None;
                 }
             }
         }
+        if let Some(slot) = oldest {
+            self.inventory.prune_tombstones(slot);
+        }
     }
 
+    /// Fetch every pool of `program`, publishing the pools of each shard as
+    /// it arrives. Pools missing from the snapshot are deleted once all
+    /// shards were read. Returns the number of pools published and the slot
+    /// of the oldest shard.
     async fn full_snapshot_program(
         &self,
-        rpc: &RpcClient,
         program: &ProgramConfig,
-    ) -> anyhow::Result<usize> {
-        use solana_client::rpc_config::RpcProgramAccountsConfig;
-        let cfg = RpcProgramAccountsConfig {
-            filters: None,
-            account_config: RpcAccountInfoConfig {
-                encoding: Some(UiAccountEncoding::Base64),
-                commitment: Some(CommitmentConfig::processed()),
-                data_slice: None,
-                min_context_slot: None,
-            },
-            with_context: None,
-            sort_results: None,
-        };
-        let list = rpc.get_program_accounts_with_config(&program.id, cfg)?;
-        let mut count = 0usize;
-        for (acc_key, acc) in list {
-            let data = acc.data;
-            if let Some(info) = decode_pool(
-                program.kind,
-                program.id,
-                acc_key,
-                &data,
-                self.token.as_ref(),
-            ) {
-                self.inventory.upsert(info.clone());
-                self.bus.publish(PoolEvent::AccountNew {
-                    info,
-                    data_len: data.len(),
-                    slot: 0,
-                });
-                count += 1;
+    ) -> anyhow::Result<(usize, u64)> {
+        let layouts = filters::program_layouts(&self.decoders, program);
+        let shards = filters::snapshot_shards(program, &layouts, self.cfg.snapshot.shard_prefix_bytes);
+        let total = shards.len();
+        let mut results = futures::stream::iter(shards)
+            .map(|filters| self.snapshot_shard(&program.id, filters))
+            .buffer_unordered(self.cfg.snapshot.concurrency.max(1));
+
+        let mut counts = SnapshotCounts::default();
+        // accounts matching several filter sets keep the first copy read
+        let mut present: HashSet<Pubkey> = HashSet::new();
+        let mut oldest = u64::MAX;
+        let mut failed: Option<(usize, anyhow::Error)> = None;
+        while let Some(result) = results.next().await {
+            match result {
+                Ok(AccountSnapshot { slot, accounts }) => {
+                    self.status.touch(&program.id);
+                    oldest = oldest.min(slot);
+                    let accounts = accounts.into_iter().filter(|(key, _)| present.insert(*key)).collect();
+                    self.publish_shard(program, slot, accounts, &mut counts).await;
+                }
+                Err(e) => {
+                    warn!(err=%e, program=%program.id, "snapshot shard failed");
+                    failed = Some((failed.map_or(1, |(n, _)| n + 1), e));
+                }
+            }
+        }
+        if counts.unknown > 0 {
+            warn!(program=%program.id, unknown = counts.unknown, "accounts with unknown discriminator");
+        }
+        if counts.stale > 0 {
+            debug!(program=%program.id, stale = counts.stale, "snapshot accounts older than inventory");
+        }
+        // without every shard, a missing pool may just not have been read
+        if let Some((n, e)) = failed {
+            return Err(e.context(format!("{n} of {total} snapshot shards failed")));
+        }
+        // pools missing from the snapshot were closed, unless the subscription
+        // saw them after the snapshot was read
+        let slot = oldest;
+        for info in self.inventory.program_pools(&program.id) {
+            let account = info.id.account;
+            if !present.contains(&account) && self.inventory.record_slot(account, slot) {
+                delete_pool(&self.inventory, &self.scheduled, &self.bus, &info.id, slot);
+            }
+        }
+        Ok((counts.pools, oldest))
+    }
+
+    /// Read one snapshot shard, retrying failed reads with the reconnect
+    /// backoff up to `shard_attempts` times.
+    async fn snapshot_shard(&self, program: &Pubkey, filters: FilterSet) -> anyhow::Result<AccountSnapshot> {
+        let mut delay = self.cfg.reconnect.initial_ms;
+        let mut attempt = 1;
+        loop {
+            match self.source.snapshot(program, filters.clone()).await {
+                Err(e) if attempt < self.cfg.snapshot.shard_attempts => {
+                    debug!(err=%e, %program, attempt, "snapshot shard failed, retrying");
+                    sleep(Duration::from_millis(delay)).await;
+                    delay = (delay * 2).min(self.cfg.reconnect.max_ms);
+                    attempt += 1;
+                }
+                result => return result,
             }
         }
-        Ok(count)
+    }
+
+    /// Decode the accounts of one snapshot shard read at `slot` and publish
+    /// its pools.
+    async fn publish_shard(
+        &self,
+        program: &ProgramConfig,
+        slot: u64,
+        accounts: Vec<(Pubkey, Vec<u8>)>,
+        counts: &mut SnapshotCounts,
+    ) {
+        let mut pools = Vec::new();
+        for (acc_key, data) in accounts {
+            let outcome = self.decoders.decode_account(program.kind, program.id, acc_key, &data);
+            // the subscription may have delivered a newer state meanwhile
+            if outcome.is_stored() && !self.inventory.record_slot(acc_key, slot) {
+                counts.stale += 1;
+                continue;
+            }
+            match outcome {
+                // published once the configs of the shard are known
+                DecodeOutcome::Pool(info) => pools.push((*info, data.len())),
+                DecodeOutcome::Config(config) => {
+                    self.configs.insert(acc_key, *config);
+                }
+                DecodeOutcome::Invalid { account_type } => {
+                    warn!(program=%program.id, account=%acc_key, account_type, "undecodable account")
+                }
+                DecodeOutcome::TickArray(array) => self.inventory.upsert_tick_array(acc_key, *array),
+                DecodeOutcome::TickArrayBitmap(ext) => self.inventory.upsert_tick_array_bitmap(*ext),
+                DecodeOutcome::Position(position) => self.inventory.upsert_position(*position),
+                DecodeOutcome::Unknown { .. } => counts.unknown += 1,
+                DecodeOutcome::Other { .. } => {}
+            }
+        }
+        let flags = set_token2022_flags(pools.iter_mut().map(|(info, _)| info), self.token.as_ref(), self.source.as_ref());
+        if let Err(e) = flags.await {
+            warn!(err=%e, program=%program.id, "mint lookup failed");
+        }
+        let fetch = self.configs.fetch_missing(self.source.as_ref(), pools.iter().map(|(info, _)| info));
+        match fetch.await {
+            // pools the subscription published while the fetch was pending
+            Ok(changed) => {
+                for key in changed {
+                    reemit_pools_with_config(&self.inventory, &self.configs, &self.bus, &key, slot);
+                }
+            }
+            Err(e) => warn!(err=%e, program=%program.id, "config fetch failed"),
+        }
+        counts.pools += pools.len();
+        for (mut info, data_len) in pools {
+            self.configs.apply(&mut info);
+            let previous = self.inventory.get(&info.id);
+            self.inventory.upsert(info.clone());
+            track_opening(&self.scheduled, &self.bus, &info, slot);
+            self.bus.publish(PoolEvent::account_update(previous.as_ref(), info, data_len, slot));
+        }
     }
 }
 
-async fn subscribe_program(
-    ws_url: String,
-    program: ProgramConfig,
+/// Accounts seen over the shards of a snapshot.
+#[derive(Default)]
+struct SnapshotCounts {
+    pools: usize,
+    unknown: usize,
+    stale: usize,
+}
+
+/// Register an [`IdlDecoder`] for every program configured with an IDL.
+fn with_idl_decoders(mut registry: DecoderRegistry, programs: &[ProgramConfig]) -> DecoderRegistry {
+    for program in programs {
+        let Some(idl) = &program.idl else { continue };
+        match IdlDecoder::load(program.id, program.kind, idl) {
+            Ok(decoder) => {
+                registry.register_for_program(program.id, Arc::new(decoder));
+            }
+            Err(e) => error!(err=%e, program=%program.id, "IDL decoder not loaded"),
+        }
+    }
+    registry
+}
+
+/// Shared state a program subscription decodes into.
+#[derive(Clone)]
+struct SubscriptionContext {
+    source: Arc<dyn AccountSource>,
     bus: SharedPoolBus,
     inventory: Inventory,
+    configs: ConfigRegistry,
+    scheduled: Scheduled,
     token: Arc<dyn TokenIntrospectionProvider>,
-) -> anyhow::Result<()> {
-    let client = PubsubClient::new(&ws_url).await?;
-    let cfg = RpcProgramAccountsConfig {
-        filters: None,
-        account_config: RpcAccountInfoConfig {
-            encoding: Some(UiAccountEncoding::Base64),
-            commitment: Some(CommitmentConfig::processed()),
-            data_slice: None,
-            min_context_slot: None,
-        },
-        with_context: None,
-        sort_results: None,
-    };
-    let (mut notifications, unsubscribe) =
-        client.program_subscribe(&program.id, Some(cfg)).await?;
-    while let Some(Response {
-        value: RpcKeyedAccount { pubkey, account },
-        ..
-    }) = notifications.next().await
+    decoders: Arc<DecoderRegistry>,
+    status: StatusBoard,
+    shutdown: Shutdown,
+}
+
+/// Keeps one subscription of a program alive and its state up to date.
+struct Supervisor {
+    program: Pubkey,
+    feed: Feed,
+    backoff: ReconnectConfig,
+    status: StatusBoard,
+    shutdown: Shutdown,
+}
+
+impl Supervisor {
+    /// Run a subscription until the watcher shuts down, reconnecting after
+    /// its stream ends or fails. `subscribe` is told whether it replaces an
+    /// earlier attempt, and returns `Ok` once a subscription that was
+    /// established has ended.
+    async fn run<F, Fut>(mut self, mut subscribe: F)
+    where
+        F: FnMut(bool) -> Fut,
+        Fut: Future<Output = anyhow::Result<()>>,
     {
-        let acc_key = pubkey.parse::<Pubkey>().ok();
-        let data_len;
-        let data_bytes = match &account.data {
-            solana_account_decoder::UiAccountData::Binary(b64, _) => {
-                let v = general_purpose::STANDARD.decode(b64).unwrap_or_default();
-                data_len = v.len();
-                Some(v)
+        let (program, feed) = (self.program, self.feed);
+        let mut delay = self.backoff.initial_ms;
+        for attempt in 0u64.. {
+            self.status.set_feed(&program, feed, SubscriptionState::Connecting);
+            let result = subscribe(attempt > 0).await;
+            if self.shutdown.is_requested() {
+                break;
             }
-            _ => {
-                data_len = 0;
-                None
+            match result {
+                Ok(()) => {
+                    warn!(%program, ?feed, "subscription ended, reconnecting");
+                    self.status.set_feed(&program, feed, SubscriptionState::Disconnected { error: None });
+                    delay = self.backoff.initial_ms;
+                    self.pause(delay).await;
+                }
+                Err(e) => {
+                    error!(err=%e, %program, ?feed, delay_ms = delay, "subscribe failed");
+                    let error = Some(format!("{e:#}"));
+                    self.status.set_feed(&program, feed, SubscriptionState::Disconnected { error });
+                    self.pause(delay).await;
+                    delay = (delay * 2).min(self.backoff.max_ms);
+                }
             }
-        };
-        if let (Some(acc_key), Some(bytes)) = (acc_key, data_bytes) {
-            if let Some(info) =
-                decode_pool(program.kind, program.id, acc_key, &bytes, token.as_ref())
-            {
-                let existed_before = inventory.count_program(&program.id) > 0;
-                inventory.upsert(info.clone());
-                bus.publish(if existed_before {
-                    PoolEvent::AccountChanged {
-                        info,
-                        data_len,
-                        slot: 0,
-                    }
-                } else {
-                    PoolEvent::AccountNew {
-                        info,
-                        data_len,
-                        slot: 0,
-                    }
-                });
+            if self.shutdown.is_requested() {
+                break;
             }
         }
+        self.status.set_feed(&program, feed, SubscriptionState::Stopped);
+    }
+
+    /// Sleep for `ms`, or until shutdown.
+    async fn pause(&mut self, ms: u64) {
+        tokio::select! {
+            _ = sleep(Duration::from_millis(ms)) => {}
+            _ = self.shutdown.requested() => {}
+        }
+    }
+}
+
+async fn subscribe_program(
+    program: &ProgramConfig,
+    ctx: &SubscriptionContext,
+    reconnect: bool,
+) -> anyhow::Result<()> {
+    let layouts = filters::program_layouts(&ctx.decoders, program);
+    let filters = filters::subscription_filters(program, &layouts);
+    let mut subscription = ctx.source.subscribe_accounts(&program.id, filters).await?;
+    info!(program=%program.id, origin=%subscription.origin, "program subscription connected");
+    let connected = SubscriptionState::Connected { endpoint: subscription.origin.clone(), since_ms: unix_ms() };
+    ctx.status.set_feed(&program.id, Feed::Program, connected);
+    if reconnect {
+        // subscribed first, so nothing changes unseen between backfill and stream
+        let (ctx, program) = (ctx.clone(), program.clone());
+        tokio::spawn(async move {
+            match backfill_program(&ctx, &program).await {
+                Ok(count) => info!(program=%program.id, count, "backfilled pools after reconnect"),
+                Err(e) => warn!(err=%e, program=%program.id, "backfill failed"),
+            }
+        });
     }
-    unsubscribe().await;
-    drop(notifications);
-    client.shutdown().await?;
+    let mut shutdown = ctx.shutdown.clone();
+    while let Some(update) = tokio::select! {
+        update = subscription.next() => update,
+        _ = shutdown.requested() => None,
+    } {
+        ctx.status.touch(&program.id);
+        handle_account(ctx, program, update, false).await;
+    }
+    subscription.close().await;
     Ok(())
 }
 
+/// Refetch the program's pools in the inventory and publish the changes
+/// missed while its subscription was down. Returns the pools refetched.
+async fn backfill_program(ctx: &SubscriptionContext, program: &ProgramConfig) -> anyhow::Result<usize> {
+    let keys: Vec<Pubkey> = ctx.inventory.program_pools(&program.id).iter().map(|info| info.id.account).collect();
+    for update in ctx.source.get_accounts(&keys).await? {
+        handle_account(ctx, program, update, true).await;
+    }
+    Ok(keys.len())
+}
+
+/// Apply an account update to the inventory and publish the resulting
+/// events. A backfill only publishes pools whose fields changed.
+async fn handle_account(ctx: &SubscriptionContext, program: &ProgramConfig, update: AccountUpdate, backfill: bool) {
+    let SubscriptionContext { source, bus, inventory, configs, token, decoders, .. } = ctx;
+    let closed = update.is_closed(&program.id);
+    let AccountUpdate { key, data, slot, write_version, .. } = update;
+    let stale = || debug!(program=%program.id, account=%key, slot, "stale account update");
+    if closed {
+        let id = PoolId { program: program.id, account: key };
+        let pool = inventory.get(&id).is_some();
+        // slots are only kept for accounts the watcher stores
+        if !pool && !inventory.contains_account(&key) {
+            return;
+        }
+        if !inventory.record_update(key, slot, write_version) {
+            return stale();
+        }
+        if pool {
+            delete_pool(inventory, &ctx.scheduled, bus, &id, slot);
+        } else if inventory.remove_position(&key).is_none() {
+            inventory.remove_tick_array(&key);
+        }
+        return;
+    }
+    let outcome = decoders.decode_account(program.kind, program.id, key, &data);
+    // slots are only kept for accounts the watcher stores
+    if outcome.is_stored() && !inventory.record_update(key, slot, write_version) {
+        return stale();
+    }
+    match outcome {
+        DecodeOutcome::Pool(info) => {
+            let mut info = *info;
+            if let Err(e) = set_token2022_flags([&mut info], token.as_ref(), source.as_ref()).await {
+                warn!(err=%e, pool=%key, "mint lookup failed");
+            }
+            configs.apply(&mut info);
+            if !configs.missing(&info).is_empty() {
+                fetch_configs(ctx.clone(), info.clone(), slot);
+            }
+            let previous = inventory.get(&info.id);
+            let graduation = graduation_event(inventory, previous.as_ref(), &info, slot);
+            inventory.upsert(info.clone());
+            // a backfill only publishes changes, and curve trades only move
+            // reserves, which no event field covers
+            let unchanged = previous.as_ref().is_some_and(|p| info.changed_fields(p).is_empty());
+            if !(unchanged && (backfill || info.dex == DexKind::PumpFun)) {
+                bus.publish(PoolEvent::account_update(previous.as_ref(), info.clone(), data.len(), slot));
+            }
+            if let Some(ev) = graduation {
+                // the curve is history once its pool is known
+                if let PoolEvent::CurveGraduated { curve, pool: Some(_), .. } = &ev {
+                    inventory.evict(curve);
+                }
+                bus.publish(ev);
+            }
+            track_opening(&ctx.scheduled, bus, &info, slot);
+        }
+        DecodeOutcome::Invalid { account_type } => {
+            warn!(program=%program.id, account=%key, account_type, "undecodable account")
+        }
+        DecodeOutcome::TickArray(array) => inventory.upsert_tick_array(key, *array),
+        DecodeOutcome::TickArrayBitmap(ext) => inventory.upsert_tick_array_bitmap(*ext),
+        DecodeOutcome::Position(position) => inventory.upsert_position(*position),
+        DecodeOutcome::Config(config) => update_config(ctx, key, *config, slot),
+        DecodeOutcome::Unknown { discriminator } => {
+            debug!(program=%program.id, account=%key, ?discriminator, "unknown account type")
+        }
+        DecodeOutcome::Other { .. } => {}
+    }
+}
+
+/// Drop a closed pool from the inventory and announce it.
+fn delete_pool(inventory: &Inventory, scheduled: &Scheduled, bus: &SharedPoolBus, id: &PoolId, slot: u64) {
+    if inventory.remove(id).is_some() {
+        scheduled.remove(&id.account);
+        bus.publish(PoolEvent::AccountDeleted { id: id.clone(), slot });
+    }
+}
+
+/// Remember pools that cannot be traded yet and announce those that opened since.
+fn track_opening(scheduled: &Scheduled, bus: &SharedPoolBus, info: &PoolInfo, slot: u64) {
+    if !info.is_tradeable(unix_now(), slot) {
+        scheduled.insert(info.id.account, info.id.clone());
+    } else if scheduled.remove(&info.id.account).is_some() {
+        bus.publish(PoolEvent::PoolOpened { info: info.clone(), slot });
+    }
+}
+
+/// Announce scheduled pools once their open time or slot passes. Pools whose
+/// status disables swaps only open through an account update.
+async fn watch_openings(
+    source: Arc<dyn AccountSource>,
+    scheduled: Scheduled,
+    inventory: Inventory,
+    bus: SharedPoolBus,
+    mut shutdown: Shutdown,
+) {
+    let mut tick = interval(Duration::from_secs(1));
+    loop {
+        tokio::select! {
+            _ = tick.tick() => {}
+            _ = shutdown.requested() => return,
+        }
+        let due: Vec<PoolInfo> = scheduled
+            .iter()
+            .filter_map(|entry| inventory.get(entry.value()))
+            // pools waiting on their status alone open with the update flipping it
+            .filter(|info| info.open_at.is_some() && info.swap_enabled != Some(false))
+            .collect();
+        if due.is_empty() {
+            continue;
+        }
+        let slot = if due.iter().any(|info| matches!(info.open_at, Some(OpenAt::Slot(_)))) {
+            source.slot().await.unwrap_or_default()
+        } else {
+            0
+        };
+        for info in &due {
+            track_opening(&scheduled, &bus, info, slot);
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Fetch the configs `info` references in the background, then give the
+/// pools waiting on them their fee. `slot` is the slot `info` was seen at.
+fn fetch_configs(ctx: SubscriptionContext, info: PoolInfo, slot: u64) {
+    tokio::spawn(async move {
+        match ctx.configs.fetch_missing(ctx.source.as_ref(), [&info]).await {
+            Ok(changed) => {
+                for key in changed {
+                    reemit_pools_with_config(&ctx.inventory, &ctx.configs, &ctx.bus, &key, slot);
+                }
+            }
+            Err(e) => warn!(err=%e, pool=%info.id.account, "config fetch failed"),
+        }
+    });
+}
+
+/// Store an updated config account and re-emit the pools whose fee it changed.
+fn update_config(ctx: &SubscriptionContext, key: Pubkey, config: ConfigAccount, slot: u64) {
+    if ctx.configs.insert(key, config) {
+        reemit_pools_with_config(&ctx.inventory, &ctx.configs, &ctx.bus, &key, slot);
+    }
+}
+
+/// Publish the pools referencing `config` again with its current fee. The
+/// pool accounts did not change, so `data_len` is 0.
+fn reemit_pools_with_config(
+    inventory: &Inventory,
+    configs: &ConfigRegistry,
+    bus: &SharedPoolBus,
+    config: &Pubkey,
+    slot: u64,
+) {
+    for mut info in inventory.pools_with_config(config) {
+        let previous = info.clone();
+        configs.apply(&mut info);
+        inventory.upsert(info.clone());
+        let changed = info.changed_fields(&previous);
+        bus.publish(PoolEvent::AccountChanged { info, data_len: 0, slot, changed });
+    }
+}
+
+/// Detect pump.fun graduations: a tracked curve flipping to `complete`, or a
+/// brand-new pool for a mint whose bonding curve is in the inventory.
+fn graduation_event(
+    inventory: &Inventory,
+    previous: Option<&PoolInfo>,
+    info: &PoolInfo,
+    slot: u64,
+) -> Option<PoolEvent> {
+    if info.dex == DexKind::PumpFun {
+        // only a transition we observed counts; already-complete curves are history
+        let was_complete = pump_fun::is_complete(previous?);
+        if was_complete || !pump_fun::is_complete(info) {
+            return None;
+        }
+        return Some(PoolEvent::CurveGraduated {
+            curve: info.id.clone(),
+            mint: None,
+            pool: None,
+            slot,
+        });
+    }
+    if previous.is_some() {
+        return None;
+    }
+    [info.base_mint, info.quote_mint]
+        .into_iter()
+        .flatten()
+        .filter(|mint| *mint != pump_fun::WSOL_MINT)
+        .find_map(|mint| {
+            let curve = PoolId {
+                program: pump_fun::PROGRAM_ID,
+                account: pump_fun::bonding_curve_address(&mint),
+            };
+            inventory.get(&curve)?;
+            Some(PoolEvent::CurveGraduated {
+                curve,
+                mint: Some(mint),
+                pool: Some(info.clone()),
+                slot,
+            })
+        })
+}
+
 async fn subscribe_logs(
-    ws_url: String,
+    source: Arc<dyn AccountSource>,
     program: ProgramConfig,
     bus: SharedPoolBus,
+    status: StatusBoard,
+    mut shutdown: Shutdown,
 ) -> anyhow::Result<()> {
-    let client = PubsubClient::new(&ws_url).await?;
-    let filter = solana_client::rpc_config::RpcTransactionLogsFilter::Mentions(vec![
-        program.id.to_string(),
-    ]);
-    let (mut notifications, unsubscribe) = client
-        .logs_subscribe(
-            filter,
-            RpcTransactionLogsConfig {
-                commitment: Some(CommitmentConfig::processed()),
-            },
-        )
-        .await?;
-    while let Some(Response { value, context }) = notifications.next().await {
+    let mut subscription = source.subscribe_logs(&program.id).await?;
+    let connected = SubscriptionState::Connected { endpoint: subscription.origin.clone(), since_ms: unix_ms() };
+    status.set_feed(&program.id, Feed::Logs, connected);
+    while let Some(LogNotification { signature, slot }) = tokio::select! {
+        notification = subscription.next() => notification,
+        _ = shutdown.requested() => None,
+    } {
+        status.touch(&program.id);
         bus.publish(PoolEvent::ProgramLog {
             program: program.id,
-            signature: value.signature,
-            slot: context.slot,
+            signature,
+            slot,
         });
     }
-    unsubscribe().await;
-    drop(notifications);
-    client.shutdown().await?;
+    subscription.close().await;
     Ok(())
 }
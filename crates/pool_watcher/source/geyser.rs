@@ -0,0 +1,264 @@
+//! Subset of the Yellowstone Geyser gRPC protocol (`geyser.proto`) the
+//! watcher speaks: account and transaction subscriptions, pings and the
+//! current slot. Messages keep the upstream field numbers, so unknown fields
+//! sent by a full server are skipped when decoding.
+
+use std::collections::HashMap;
+use futures::Stream;
+use tonic::{
+    client::Grpc,
+    codec::{ProstCodec, Streaming},
+    codegen::http::uri::PathAndQuery,
+    metadata::AsciiMetadataValue,
+    transport::{Channel, ClientTlsConfig, Endpoint},
+    Request, Status,
+};
+use tokio::time::Duration;
+
+/// Geyser updates can carry large accounts.
+const MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
+pub const SUBSCRIBE_PATH: &str = "/geyser.Geyser/Subscribe";
+pub const GET_SLOT_PATH: &str = "/geyser.Geyser/GetSlot";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum CommitmentLevel {
+    Processed = 0,
+    Confirmed = 1,
+    Finalized = 2,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeRequest {
+    #[prost(map = "string, message", tag = "1")]
+    pub accounts: HashMap<String, SubscribeRequestFilterAccounts>,
+    #[prost(map = "string, message", tag = "3")]
+    pub transactions: HashMap<String, SubscribeRequestFilterTransactions>,
+    #[prost(enumeration = "CommitmentLevel", optional, tag = "6")]
+    pub commitment: Option<i32>,
+    #[prost(message, optional, tag = "9")]
+    pub ping: Option<SubscribeRequestPing>,
+    #[prost(uint64, optional, tag = "11")]
+    pub from_slot: Option<u64>,
+}
+
+/// Accounts matching any of `account` or `owner`, and every filter.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeRequestFilterAccounts {
+    #[prost(string, repeated, tag = "2")]
+    pub account: Vec<String>,
+    #[prost(string, repeated, tag = "3")]
+    pub owner: Vec<String>,
+    #[prost(message, repeated, tag = "4")]
+    pub filters: Vec<SubscribeRequestFilterAccountsFilter>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeRequestFilterAccountsFilter {
+    #[prost(oneof = "subscribe_request_filter_accounts_filter::Filter", tags = "1, 2, 3")]
+    pub filter: Option<subscribe_request_filter_accounts_filter::Filter>,
+}
+
+pub mod subscribe_request_filter_accounts_filter {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Filter {
+        #[prost(message, tag = "1")]
+        Memcmp(super::SubscribeRequestFilterAccountsFilterMemcmp),
+        #[prost(uint64, tag = "2")]
+        Datasize(u64),
+        #[prost(bool, tag = "3")]
+        TokenAccountState(bool),
+    }
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeRequestFilterAccountsFilterMemcmp {
+    #[prost(uint64, tag = "1")]
+    pub offset: u64,
+    #[prost(oneof = "subscribe_request_filter_accounts_filter_memcmp::Data", tags = "2, 3, 4")]
+    pub data: Option<subscribe_request_filter_accounts_filter_memcmp::Data>,
+}
+
+pub mod subscribe_request_filter_accounts_filter_memcmp {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Data {
+        #[prost(bytes, tag = "2")]
+        Bytes(Vec<u8>),
+        #[prost(string, tag = "3")]
+        Base58(String),
+        #[prost(string, tag = "4")]
+        Base64(String),
+    }
+}
+
+/// Transactions mentioning any of `account_include`.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeRequestFilterTransactions {
+    #[prost(bool, optional, tag = "1")]
+    pub vote: Option<bool>,
+    #[prost(bool, optional, tag = "2")]
+    pub failed: Option<bool>,
+    #[prost(string, repeated, tag = "3")]
+    pub account_include: Vec<String>,
+    #[prost(string, repeated, tag = "4")]
+    pub account_exclude: Vec<String>,
+    #[prost(string, repeated, tag = "6")]
+    pub account_required: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeRequestPing {
+    #[prost(int32, tag = "1")]
+    pub id: i32,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeUpdate {
+    /// Names of the request filters the update matched.
+    #[prost(string, repeated, tag = "1")]
+    pub filters: Vec<String>,
+    #[prost(oneof = "subscribe_update::UpdateOneof", tags = "2, 4, 6, 9")]
+    pub update_oneof: Option<subscribe_update::UpdateOneof>,
+}
+
+pub mod subscribe_update {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum UpdateOneof {
+        #[prost(message, tag = "2")]
+        Account(super::SubscribeUpdateAccount),
+        #[prost(message, tag = "4")]
+        Transaction(super::SubscribeUpdateTransaction),
+        /// Keepalive; answered with a [`SubscribeRequestPing`](super::SubscribeRequestPing).
+        #[prost(message, tag = "6")]
+        Ping(super::SubscribeUpdatePing),
+        #[prost(message, tag = "9")]
+        Pong(super::SubscribeUpdatePong),
+    }
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeUpdateAccount {
+    #[prost(message, optional, tag = "1")]
+    pub account: Option<SubscribeUpdateAccountInfo>,
+    #[prost(uint64, tag = "2")]
+    pub slot: u64,
+    #[prost(bool, tag = "3")]
+    pub is_startup: bool,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeUpdateAccountInfo {
+    #[prost(bytes, tag = "1")]
+    pub pubkey: Vec<u8>,
+    #[prost(uint64, tag = "2")]
+    pub lamports: u64,
+    #[prost(bytes, tag = "3")]
+    pub owner: Vec<u8>,
+    #[prost(bool, tag = "4")]
+    pub executable: bool,
+    #[prost(uint64, tag = "5")]
+    pub rent_epoch: u64,
+    #[prost(bytes, tag = "6")]
+    pub data: Vec<u8>,
+    /// Order of the write among all account writes of the validator.
+    #[prost(uint64, tag = "7")]
+    pub write_version: u64,
+    #[prost(bytes, optional, tag = "8")]
+    pub txn_signature: Option<Vec<u8>>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeUpdateTransaction {
+    #[prost(message, optional, tag = "1")]
+    pub transaction: Option<SubscribeUpdateTransactionInfo>,
+    #[prost(uint64, tag = "2")]
+    pub slot: u64,
+}
+
+/// Transaction without its message and meta, which the watcher skips.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeUpdateTransactionInfo {
+    #[prost(bytes, tag = "1")]
+    pub signature: Vec<u8>,
+    #[prost(bool, tag = "2")]
+    pub is_vote: bool,
+    #[prost(uint64, tag = "5")]
+    pub index: u64,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeUpdatePing {}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeUpdatePong {
+    #[prost(int32, tag = "1")]
+    pub id: i32,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetSlotRequest {
+    #[prost(enumeration = "CommitmentLevel", optional, tag = "1")]
+    pub commitment: Option<i32>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetSlotResponse {
+    #[prost(uint64, tag = "1")]
+    pub slot: u64,
+}
+
+/// Client of the `geyser.Geyser` service.
+#[derive(Clone)]
+pub struct GeyserClient {
+    grpc: Grpc<Channel>,
+    x_token: Option<AsciiMetadataValue>,
+}
+
+impl GeyserClient {
+    /// Connect to `url`, over TLS for `https` URLs. `x_token` authenticates
+    /// with providers that require it.
+    pub async fn connect(url: &str, x_token: Option<&str>) -> anyhow::Result<Self> {
+        let mut endpoint = Endpoint::from_shared(url.to_string())?
+            .connect_timeout(Duration::from_secs(10))
+            .tcp_nodelay(true)
+            .http2_keep_alive_interval(Duration::from_secs(30));
+        if url.starts_with("https") {
+            endpoint = endpoint.tls_config(ClientTlsConfig::new().with_native_roots())?;
+        }
+        let channel = endpoint.connect().await?;
+        Ok(Self {
+            grpc: Grpc::new(channel).max_decoding_message_size(MAX_MESSAGE_SIZE),
+            x_token: x_token.map(str::parse).transpose()?,
+        })
+    }
+
+    fn request<T>(&self, message: T) -> Request<T> {
+        let mut request = Request::new(message);
+        if let Some(token) = &self.x_token {
+            request.metadata_mut().insert("x-token", token.clone());
+        }
+        request
+    }
+
+    /// Open a subscription, with the filters of the last request sent on
+    /// `requests`. Dropping the returned stream ends it.
+    pub async fn subscribe(
+        &mut self,
+        requests: impl Stream<Item = SubscribeRequest> + Send + 'static,
+    ) -> Result<Streaming<SubscribeUpdate>, Status> {
+        self.grpc.ready().await.map_err(|e| Status::unavailable(e.to_string()))?;
+        let path = PathAndQuery::from_static(SUBSCRIBE_PATH);
+        let response = self.grpc.streaming(self.request(requests), path, ProstCodec::default()).await?;
+        Ok(response.into_inner())
+    }
+
+    pub async fn get_slot(&mut self, commitment: CommitmentLevel) -> Result<u64, Status> {
+        self.grpc.ready().await.map_err(|e| Status::unavailable(e.to_string()))?;
+        let request = self.request(GetSlotRequest { commitment: Some(commitment as i32) });
+        let path = PathAndQuery::from_static(GET_SLOT_PATH);
+        let response: tonic::Response<GetSlotResponse> =
+            self.grpc.unary(request, path, ProstCodec::default()).await?;
+        Ok(response.into_inner().slot)
+    }
+}
@@ -0,0 +1,320 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+use futures::{
+    channel::{mpsc, oneshot},
+    future::{self, BoxFuture},
+    StreamExt,
+};
+use serde::Deserialize;
+use solana_client::rpc_filter::RpcFilterType;
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use tokio::sync::{Mutex, OnceCell};
+use tonic::codec::Streaming;
+use tracing::warn;
+use super::geyser::{
+    subscribe_request_filter_accounts_filter::Filter,
+    subscribe_request_filter_accounts_filter_memcmp::Data,
+    subscribe_update::UpdateOneof,
+    CommitmentLevel, GeyserClient, SubscribeRequest, SubscribeRequestFilterAccounts,
+    SubscribeRequestFilterAccountsFilter, SubscribeRequestFilterAccountsFilterMemcmp,
+    SubscribeRequestFilterTransactions, SubscribeRequestPing, SubscribeUpdate, SubscribeUpdateAccount,
+    SubscribeUpdateAccountInfo, SubscribeUpdateTransaction,
+};
+use super::{AccountSnapshot, AccountSource, AccountUpdate, LogNotification, RpcSource, Subscription};
+use crate::endpoints::EndpointStatus;
+use crate::filters::FilterSet;
+use crate::service::PoolWatcherConfig;
+
+/// Updates buffered between the stream and each subscription.
+const CHANNEL_CAPACITY: usize = 4096;
+
+/// Yellowstone Geyser gRPC endpoint.
+#[derive(Clone, Debug, Deserialize)]
+pub struct GrpcConfig {
+    pub url: String,
+    #[serde(default)]
+    pub x_token: Option<String>,
+}
+
+/// [`AccountSource`] streaming accounts and transactions from a Yellowstone
+/// Geyser gRPC endpoint. Snapshots and account reads, which Geyser does not
+/// serve, go to the RPC endpoints.
+///
+/// Every subscription shares one Subscribe stream. Its request holds the
+/// filters of all of them, and updates are routed by the names of the
+/// filters they matched.
+pub struct GrpcSource {
+    cfg: GrpcConfig,
+    client: OnceCell<GeyserClient>,
+    rpc: RpcSource,
+    stream: Arc<Mutex<Option<SharedStream>>>,
+    next_id: AtomicU64,
+}
+
+/// The Subscribe stream, while open.
+struct SharedStream {
+    /// Filters of every subscription, resent whenever they change.
+    request: SubscribeRequest,
+    requests: mpsc::UnboundedSender<SubscribeRequest>,
+    /// Subscription each filter belongs to, by filter name.
+    routes: Routes,
+    /// Dropped to end the stream.
+    _stop: oneshot::Sender<()>,
+}
+
+type Routes = Arc<std::sync::Mutex<HashMap<String, Route>>>;
+
+struct Route {
+    id: u64,
+    tx: mpsc::Sender<UpdateOneof>,
+}
+
+impl GrpcSource {
+    pub fn new(cfg: &PoolWatcherConfig, grpc: GrpcConfig) -> Self {
+        Self {
+            cfg: grpc,
+            client: OnceCell::new(),
+            rpc: RpcSource::new(cfg),
+            stream: Arc::default(),
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    /// The client is connected once; its channel reconnects on its own.
+    async fn client(&self) -> anyhow::Result<GeyserClient> {
+        let client = self
+            .client
+            .get_or_try_init(|| GeyserClient::connect(&self.cfg.url, self.cfg.x_token.as_deref()))
+            .await?;
+        Ok(client.clone())
+    }
+
+    /// Add `accounts` and `transactions` to the filters of the shared stream,
+    /// opening it when none is, and receive the updates matching them. The
+    /// subscription ends with the stream; closing it removes its filters.
+    async fn subscribe<T: Send + 'static>(
+        &self,
+        accounts: HashMap<String, SubscribeRequestFilterAccounts>,
+        transactions: HashMap<String, SubscribeRequestFilterTransactions>,
+        notification: fn(UpdateOneof) -> Option<T>,
+    ) -> anyhow::Result<Subscription<T>> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let names: Vec<String> = accounts.keys().chain(transactions.keys()).cloned().collect();
+        let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+
+        let mut stream = self.stream.lock().await;
+        // a failed stream is replaced by the first subscription after it
+        let shared = stream.take().filter(|shared| !shared.requests.is_closed());
+        let mut request = match &shared {
+            Some(shared) => shared.request.clone(),
+            None => SubscribeRequest { commitment: Some(CommitmentLevel::Processed as i32), ..Default::default() },
+        };
+        request.accounts.extend(accounts);
+        request.transactions.extend(transactions);
+        // routed before the request goes out, so no update matching it is lost
+        let routes = shared.as_ref().map_or_else(Routes::default, |shared| shared.routes.clone());
+        routes
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .extend(names.iter().map(|name| (name.clone(), Route { id, tx: tx.clone() })));
+        match shared {
+            Some(mut shared) => {
+                // Geyser applies the filters of the last request on the stream
+                shared.requests.unbounded_send(request.clone())?;
+                shared.request = request;
+                *stream = Some(shared);
+            }
+            None => *stream = Some(self.open(request, routes.clone()).await?),
+        }
+        drop(stream);
+
+        let state = self.stream.clone();
+        let updates = rx.filter_map(move |update| future::ready(notification(update)));
+        Ok(Subscription::new(self.cfg.url.clone(), updates.boxed()).on_close(async move {
+            let mut stream = state.lock().await;
+            let Some(shared) = stream.as_mut().filter(|shared| Arc::ptr_eq(&shared.routes, &routes)) else {
+                // the stream this subscription used is gone
+                return;
+            };
+            let mut current = routes.lock().unwrap_or_else(|e| e.into_inner());
+            for name in &names {
+                // routes of a lagging subscription are already gone
+                if current.get(name).is_none_or(|route| route.id == id) {
+                    current.remove(name);
+                    shared.request.accounts.remove(name);
+                    shared.request.transactions.remove(name);
+                }
+            }
+            if current.is_empty() {
+                drop(current);
+                *stream = None;
+            } else {
+                let _ = shared.requests.unbounded_send(shared.request.clone());
+            }
+        }))
+    }
+
+    /// Open the stream with `request` and hand its updates to `routes` in a
+    /// task of their own. Pings are answered to keep the stream open through
+    /// load balancers.
+    async fn open(&self, request: SubscribeRequest, routes: Routes) -> anyhow::Result<SharedStream> {
+        let (requests, rx) = mpsc::unbounded();
+        requests.unbounded_send(request.clone())?;
+        let updates = self.client().await?.subscribe(rx).await?;
+        let (stop_tx, stop_rx) = oneshot::channel();
+        tokio::spawn(route(updates, requests.clone(), routes.clone(), stop_rx, self.cfg.url.clone()));
+        Ok(SharedStream { request, requests, routes, _stop: stop_tx })
+    }
+}
+
+/// Hand each update to the subscriptions whose filters it matched, until
+/// the stream fails or is stopped. Ending drops every route, which ends
+/// their subscriptions; a subscription whose channel is full loses its
+/// routes alone.
+async fn route(
+    mut updates: Streaming<SubscribeUpdate>,
+    requests: mpsc::UnboundedSender<SubscribeRequest>,
+    routes: Routes,
+    mut stop: oneshot::Receiver<()>,
+    url: String,
+) {
+    loop {
+        let update = tokio::select! {
+            update = updates.next() => update,
+            _ = &mut stop => break,
+        };
+        let SubscribeUpdate { filters, update_oneof } = match update {
+            Some(Ok(update)) => update,
+            Some(Err(status)) => {
+                warn!(endpoint=%url, err=%status, "geyser stream failed");
+                break;
+            }
+            None => break,
+        };
+        let Some(update) = update_oneof else { continue };
+        if let UpdateOneof::Ping(_) = update {
+            let ping = SubscribeRequest { ping: Some(SubscribeRequestPing { id: 1 }), ..Default::default() };
+            let _ = requests.unbounded_send(ping);
+            continue;
+        }
+        let mut current = routes.lock().unwrap_or_else(|e| e.into_inner());
+        // an update matching several filters of one subscription is sent once
+        let (mut sent, mut lagging) = (Vec::new(), Vec::new());
+        for name in &filters {
+            let Some(route) = current.get_mut(name).filter(|route| !sent.contains(&route.id)) else { continue };
+            sent.push(route.id);
+            // sent through the route's own sender: a clone would get a slot of
+            // its own and never find the channel full
+            if route.tx.try_send(update.clone()).is_err_and(|e| e.is_full()) {
+                lagging.push(route.id);
+            }
+        }
+        // a full channel would hold up every other subscription on the
+        // stream; the lagging one is ended instead, and resubscribes
+        if !lagging.is_empty() {
+            warn!(endpoint=%url, "subscription lagging behind the geyser stream, ending it");
+            current.retain(|_, route| !lagging.contains(&route.id));
+        }
+    }
+    requests.close_channel();
+    routes.lock().unwrap_or_else(|e| e.into_inner()).clear();
+}
+
+impl AccountSource for GrpcSource {
+    fn start(&self) -> BoxFuture<'_, ()> {
+        self.rpc.start()
+    }
+
+    fn maintain(&self) -> BoxFuture<'_, ()> {
+        self.rpc.maintain()
+    }
+
+    fn snapshot<'a>(&'a self, program: &'a Pubkey, filters: FilterSet) -> BoxFuture<'a, anyhow::Result<AccountSnapshot>> {
+        self.rpc.snapshot(program, filters)
+    }
+
+    /// One named account filter per filter set, on accounts owned by
+    /// `program`. Updates are ordered by slot and write version.
+    fn subscribe_accounts<'a>(
+        &'a self,
+        program: &'a Pubkey,
+        filters: Vec<FilterSet>,
+    ) -> BoxFuture<'a, anyhow::Result<Subscription<AccountUpdate>>> {
+        let accounts = filters
+            .iter()
+            .enumerate()
+            .map(|(i, filters)| {
+                let filter = SubscribeRequestFilterAccounts {
+                    account: Vec::new(),
+                    owner: vec![program.to_string()],
+                    filters: filters.iter().filter_map(account_filter).collect(),
+                };
+                (format!("{program}-{i}"), filter)
+            })
+            .collect();
+        Box::pin(self.subscribe(accounts, HashMap::new(), |update| match update {
+            UpdateOneof::Account(SubscribeUpdateAccount { account: Some(account), slot, .. }) => {
+                account_update(account, slot)
+            }
+            _ => None,
+        }))
+    }
+
+    /// Non-vote transactions mentioning `program`, failed ones included.
+    fn subscribe_logs<'a>(&'a self, program: &'a Pubkey) -> BoxFuture<'a, anyhow::Result<Subscription<LogNotification>>> {
+        let filter = SubscribeRequestFilterTransactions {
+            vote: Some(false),
+            account_include: vec![program.to_string()],
+            ..Default::default()
+        };
+        let transactions = [(program.to_string(), filter)].into();
+        Box::pin(self.subscribe(HashMap::new(), transactions, |update| match update {
+            UpdateOneof::Transaction(SubscribeUpdateTransaction { transaction: Some(tx), slot }) => {
+                Signature::try_from(tx.signature.as_slice())
+                    .ok()
+                    .map(|signature| LogNotification { signature: signature.to_string(), slot })
+            }
+            _ => None,
+        }))
+    }
+
+    fn get_accounts<'a>(&'a self, keys: &'a [Pubkey]) -> BoxFuture<'a, anyhow::Result<Vec<AccountUpdate>>> {
+        self.rpc.get_accounts(keys)
+    }
+
+    fn slot(&self) -> BoxFuture<'_, anyhow::Result<u64>> {
+        Box::pin(async move { Ok(self.client().await?.get_slot(CommitmentLevel::Processed).await?) })
+    }
+
+    fn endpoints(&self) -> Vec<EndpointStatus> {
+        self.rpc.endpoints()
+    }
+}
+
+fn account_filter(filter: &RpcFilterType) -> Option<SubscribeRequestFilterAccountsFilter> {
+    let filter = match filter {
+        RpcFilterType::DataSize(size) => Filter::Datasize(*size),
+        RpcFilterType::Memcmp(memcmp) => Filter::Memcmp(SubscribeRequestFilterAccountsFilterMemcmp {
+            offset: memcmp.offset() as u64,
+            data: Some(Data::Bytes(memcmp.bytes()?.into_owned())),
+        }),
+        RpcFilterType::TokenAccountState => Filter::TokenAccountState(true),
+    };
+    Some(SubscribeRequestFilterAccountsFilter { filter: Some(filter) })
+}
+
+fn account_update(account: SubscribeUpdateAccountInfo, slot: u64) -> Option<AccountUpdate> {
+    Some(AccountUpdate {
+        key: Pubkey::try_from(account.pubkey.as_slice()).ok()?,
+        lamports: account.lamports,
+        owner: Pubkey::try_from(account.owner.as_slice()).ok(),
+        data: account.data,
+        slot,
+        write_version: Some(account.write_version),
+    })
+}
@@ -0,0 +1,133 @@
+use std::future::Future;
+use futures::{
+    future::{self, BoxFuture},
+    stream::BoxStream,
+    StreamExt,
+};
+use solana_sdk::pubkey::Pubkey;
+use crate::endpoints::EndpointStatus;
+use crate::filters::FilterSet;
+pub mod geyser;
+pub mod grpc;
+pub mod rpc;
+
+pub use grpc::{GrpcConfig, GrpcSource};
+pub use rpc::RpcSource;
+
+/// Where [`PoolWatcher`](crate::PoolWatcher) reads accounts and logs from.
+/// Every source drives the same decode, inventory and bus path; the default
+/// is [`RpcSource`], over JSON-RPC and websockets.
+pub trait AccountSource: Send + Sync {
+    /// Called once before the first snapshot, e.g. to probe endpoints.
+    fn start(&self) -> BoxFuture<'_, ()> {
+        Box::pin(future::ready(()))
+    }
+
+    /// Background work of the source, e.g. probing endpoints. Polled from the
+    /// first subscription until the watcher shuts down, then dropped.
+    fn maintain(&self) -> BoxFuture<'_, ()> {
+        Box::pin(future::pending())
+    }
+
+    /// Accounts of `program` matching every filter of `filters`, along with
+    /// the slot they were read at.
+    fn snapshot<'a>(&'a self, program: &'a Pubkey, filters: FilterSet) -> BoxFuture<'a, anyhow::Result<AccountSnapshot>>;
+
+    /// Updates of the accounts of `program` matching any of the filter sets,
+    /// as they happen. An empty set matches every account.
+    fn subscribe_accounts<'a>(
+        &'a self,
+        program: &'a Pubkey,
+        filters: Vec<FilterSet>,
+    ) -> BoxFuture<'a, anyhow::Result<Subscription<AccountUpdate>>>;
+
+    /// Transactions mentioning `program`, as they happen.
+    fn subscribe_logs<'a>(&'a self, program: &'a Pubkey) -> BoxFuture<'a, anyhow::Result<Subscription<LogNotification>>>;
+
+    /// Current state of `keys`, in order. Missing accounts come back closed:
+    /// no lamports, no data and no owner.
+    fn get_accounts<'a>(&'a self, keys: &'a [Pubkey]) -> BoxFuture<'a, anyhow::Result<Vec<AccountUpdate>>>;
+
+    /// Latest slot the source has seen.
+    fn slot(&self) -> BoxFuture<'_, anyhow::Result<u64>>;
+
+    /// Endpoints the source reads from, for the watcher status.
+    fn endpoints(&self) -> Vec<EndpointStatus> {
+        Vec::new()
+    }
+}
+
+/// Accounts read by [`AccountSource::snapshot`].
+#[derive(Debug, Clone, Default)]
+pub struct AccountSnapshot {
+    pub slot: u64,
+    /// Address and data of each account.
+    pub accounts: Vec<(Pubkey, Vec<u8>)>,
+}
+
+/// State of an account at `slot`. Closing an account zeroes its lamports and
+/// data and hands it back to the system program.
+///
+/// `write_version`, when the source knows it, orders writes within a slot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountUpdate {
+    pub key: Pubkey,
+    pub lamports: u64,
+    pub owner: Option<Pubkey>,
+    pub data: Vec<u8>,
+    pub slot: u64,
+    pub write_version: Option<u64>,
+}
+
+impl AccountUpdate {
+    /// Update for an account that does not exist at `slot`.
+    pub fn closed(key: Pubkey, slot: u64) -> Self {
+        Self { key, lamports: 0, owner: None, data: Vec::new(), slot, write_version: None }
+    }
+
+    /// Whether the account is closed or no longer belongs to `program`.
+    pub fn is_closed(&self, program: &Pubkey) -> bool {
+        self.lamports == 0 || self.data.is_empty() || self.owner != Some(*program)
+    }
+}
+
+/// Transaction that mentioned a watched program.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogNotification {
+    pub signature: String,
+    pub slot: u64,
+}
+
+/// Notifications from an [`AccountSource`], until the source ends them or
+/// the subscription is closed.
+pub struct Subscription<T> {
+    /// Where the notifications come from, e.g. a websocket URL; reported in
+    /// the watcher status.
+    pub origin: String,
+    stream: BoxStream<'static, T>,
+    close: Option<BoxFuture<'static, ()>>,
+}
+
+impl<T> Subscription<T> {
+    pub fn new(origin: impl Into<String>, stream: BoxStream<'static, T>) -> Self {
+        Self { origin: origin.into(), stream, close: None }
+    }
+
+    /// Run `close` when the subscription is closed, e.g. to unsubscribe.
+    pub fn on_close(mut self, close: impl Future<Output = ()> + Send + 'static) -> Self {
+        self.close = Some(Box::pin(close));
+        self
+    }
+
+    /// Next notification, `None` once the source ended the subscription.
+    pub async fn next(&mut self) -> Option<T> {
+        self.stream.next().await
+    }
+
+    pub async fn close(self) {
+        drop(self.stream);
+        if let Some(close) = self.close {
+            close.await;
+        }
+    }
+}
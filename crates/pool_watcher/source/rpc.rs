@@ -0,0 +1,301 @@
+use std::future::Future;
+use futures::{
+    channel::{mpsc, oneshot},
+    future::{self, BoxFuture},
+    SinkExt, Stream, StreamExt,
+};
+use solana_account_decoder::{UiAccountData, UiAccountEncoding};
+use solana_client::{
+    nonblocking::{pubsub_client::PubsubClient, rpc_client::RpcClient},
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcTransactionLogsConfig, RpcTransactionLogsFilter},
+    rpc_request::RpcRequest,
+    rpc_response::{OptionalContext, Response, RpcKeyedAccount},
+};
+use solana_commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use base64::{engine::general_purpose, Engine as _};
+use tokio::time::{interval, Duration};
+use super::{AccountSnapshot, AccountSource, AccountUpdate, LogNotification, Subscription};
+use crate::endpoints::{EndpointStatus, Endpoints, HealthConfig};
+use crate::filters::FilterSet;
+use crate::service::PoolWatcherConfig;
+
+/// Notifications buffered between a websocket and the watcher.
+const CHANNEL_CAPACITY: usize = 4096;
+
+/// [`AccountSource`] over JSON-RPC and websocket pubsub, failing over
+/// between the configured endpoints.
+#[derive(Clone)]
+pub struct RpcSource {
+    endpoints: Endpoints,
+    health: HealthConfig,
+}
+
+impl RpcSource {
+    pub fn new(cfg: &PoolWatcherConfig) -> Self {
+        Self { endpoints: Endpoints::new(cfg), health: cfg.health.clone() }
+    }
+}
+
+impl AccountSource for RpcSource {
+    /// Probe the endpoints, then keep probing them in the background so that
+    /// requests go to the best one.
+    fn start(&self) -> BoxFuture<'_, ()> {
+        Box::pin(self.endpoints.probe(&self.health))
+    }
+
+    /// Probe the endpoints every `health.probe_interval_ms`.
+    fn maintain(&self) -> BoxFuture<'_, ()> {
+        Box::pin(async move {
+            let mut tick = interval(Duration::from_millis(self.health.probe_interval_ms.max(100)));
+            tick.tick().await;
+            loop {
+                tick.tick().await;
+                self.endpoints.probe(&self.health).await;
+            }
+        })
+    }
+
+    fn snapshot<'a>(&'a self, program: &'a Pubkey, filters: FilterSet) -> BoxFuture<'a, anyhow::Result<AccountSnapshot>> {
+        let cfg = RpcProgramAccountsConfig {
+            filters: (!filters.is_empty()).then_some(filters),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                commitment: Some(CommitmentConfig::processed()),
+                data_slice: None,
+                min_context_slot: None,
+            },
+            with_context: Some(true),
+            sort_results: None,
+        };
+        // lower bound for nodes that ignore `withContext`
+        let probed = self.endpoints.probed_slot();
+        // a failed request is retried on the next endpoint
+        Box::pin(self.endpoints.call(move |rpc| {
+            let cfg = cfg.clone();
+            async move { program_accounts_at_slot(&rpc, program, cfg, probed).await }
+        }))
+    }
+
+    fn subscribe_accounts<'a>(
+        &'a self,
+        program: &'a Pubkey,
+        filters: Vec<FilterSet>,
+    ) -> BoxFuture<'a, anyhow::Result<Subscription<AccountUpdate>>> {
+        let (endpoints, program) = (self.endpoints.clone(), *program);
+        Box::pin(forward(&self.endpoints, move |endpoint, ws_url, forwarder| async move {
+            let client = match connect(&endpoints, endpoint, &ws_url).await {
+                Ok(client) => client,
+                Err(e) => return forwarder.fail(e),
+            };
+            let subscribed = async {
+                let mut streams = Vec::new();
+                let mut unsubscribes = Vec::new();
+                for filters in filters {
+                    let cfg = RpcProgramAccountsConfig {
+                        filters: (!filters.is_empty()).then_some(filters),
+                        account_config: RpcAccountInfoConfig {
+                            encoding: Some(UiAccountEncoding::Base64),
+                            commitment: Some(CommitmentConfig::processed()),
+                            data_slice: None,
+                            min_context_slot: None,
+                        },
+                        with_context: None,
+                        sort_results: None,
+                    };
+                    let (stream, unsubscribe) = client.program_subscribe(&program, Some(cfg)).await?;
+                    streams.push(stream);
+                    unsubscribes.push(unsubscribe);
+                }
+                anyhow::Ok((streams, unsubscribes))
+            };
+            let (streams, unsubscribes) = match subscribed.await {
+                Ok(subscribed) => subscribed,
+                Err(e) => {
+                    endpoints.report_failure(endpoint, &e);
+                    return forwarder.fail(e);
+                }
+            };
+            let notifications = futures::stream::select_all(streams).filter_map(|response| {
+                let Response { value: RpcKeyedAccount { pubkey, account }, context } = response;
+                let update = (|| {
+                    Some(AccountUpdate {
+                        key: pubkey.parse().ok()?,
+                        lamports: account.lamports,
+                        owner: account.owner.parse().ok(),
+                        data: account_bytes(&account.data)?,
+                        slot: context.slot,
+                        write_version: None,
+                    })
+                })();
+                future::ready(update)
+            });
+            forwarder.run(notifications).await;
+            for unsubscribe in unsubscribes {
+                unsubscribe().await;
+            }
+            let _ = client.shutdown().await;
+        }))
+    }
+
+    fn subscribe_logs<'a>(&'a self, program: &'a Pubkey) -> BoxFuture<'a, anyhow::Result<Subscription<LogNotification>>> {
+        let (endpoints, program) = (self.endpoints.clone(), *program);
+        Box::pin(forward(&self.endpoints, move |endpoint, ws_url, forwarder| async move {
+            let client = match connect(&endpoints, endpoint, &ws_url).await {
+                Ok(client) => client,
+                Err(e) => return forwarder.fail(e),
+            };
+            let filter = RpcTransactionLogsFilter::Mentions(vec![program.to_string()]);
+            let cfg = RpcTransactionLogsConfig { commitment: Some(CommitmentConfig::processed()) };
+            let (notifications, unsubscribe) = match client.logs_subscribe(filter, cfg).await {
+                Ok(subscribed) => subscribed,
+                Err(e) => {
+                    endpoints.report_failure(endpoint, &e);
+                    return forwarder.fail(e.into());
+                }
+            };
+            let notifications = notifications.map(|Response { value, context }| LogNotification {
+                signature: value.signature,
+                slot: context.slot,
+            });
+            forwarder.run(notifications).await;
+            unsubscribe().await;
+            let _ = client.shutdown().await;
+        }))
+    }
+
+    fn get_accounts<'a>(&'a self, keys: &'a [Pubkey]) -> BoxFuture<'a, anyhow::Result<Vec<AccountUpdate>>> {
+        Box::pin(async move {
+            let mut updates = Vec::with_capacity(keys.len());
+            for chunk in keys.chunks(100) {
+                let Response { context, value } = self
+                    .endpoints
+                    .call(|rpc| async move {
+                        Ok(rpc.get_multiple_accounts_with_commitment(chunk, CommitmentConfig::processed()).await?)
+                    })
+                    .await?;
+                updates.extend(chunk.iter().zip(value).map(|(key, account)| match account {
+                    Some(account) => AccountUpdate {
+                        key: *key,
+                        lamports: account.lamports,
+                        owner: Some(account.owner),
+                        data: account.data,
+                        slot: context.slot,
+                        write_version: None,
+                    },
+                    None => AccountUpdate::closed(*key, context.slot),
+                }));
+            }
+            Ok(updates)
+        })
+    }
+
+    fn slot(&self) -> BoxFuture<'_, anyhow::Result<u64>> {
+        Box::pin(self.endpoints.call(|rpc| async move { Ok(rpc.get_slot().await?) }))
+    }
+
+    fn endpoints(&self) -> Vec<EndpointStatus> {
+        self.endpoints.status()
+    }
+}
+
+/// Run a websocket subscription to the preferred endpoint in a task of its
+/// own, which owns the client its streams borrow. `subscribe` connects to the
+/// endpoint at `ws_url` and hands its notifications to the [`Forwarder`].
+async fn forward<T, F, Fut>(endpoints: &Endpoints, subscribe: F) -> anyhow::Result<Subscription<T>>
+where
+    T: Send + 'static,
+    F: FnOnce(usize, String, Forwarder<T>) -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let (endpoint, ws_url) = endpoints.ws();
+    let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+    let (ready_tx, ready_rx) = oneshot::channel();
+    let (stop_tx, stop_rx) = oneshot::channel();
+    let deserted = Box::pin(endpoints.deserted(endpoint));
+    let forwarder = Forwarder { ready: Some(ready_tx), tx, stop: stop_rx, deserted };
+    let task = tokio::spawn(subscribe(endpoint, ws_url.clone(), forwarder));
+    ready_rx.await.map_err(|_| anyhow::anyhow!("subscription task ended"))??;
+    Ok(Subscription::new(ws_url, rx.boxed()).on_close(async move {
+        let _ = stop_tx.send(());
+        let _ = task.await;
+    }))
+}
+
+/// Task side of a forwarded subscription.
+struct Forwarder<T> {
+    ready: Option<oneshot::Sender<anyhow::Result<()>>>,
+    tx: mpsc::Sender<T>,
+    stop: oneshot::Receiver<()>,
+    /// Ends the subscription so that the watcher resubscribes elsewhere.
+    deserted: BoxFuture<'static, ()>,
+}
+
+impl<T> Forwarder<T> {
+    fn fail(mut self, e: anyhow::Error) {
+        if let Some(ready) = self.ready.take() {
+            let _ = ready.send(Err(e));
+        }
+    }
+
+    /// Report the subscription established, then forward `notifications`
+    /// until they end, the subscription is closed or dropped, or its
+    /// endpoint is deserted.
+    async fn run(mut self, notifications: impl Stream<Item = T>) {
+        if let Some(ready) = self.ready.take() {
+            let _ = ready.send(Ok(()));
+        }
+        let mut notifications = std::pin::pin!(notifications);
+        loop {
+            tokio::select! {
+                notification = notifications.next() => {
+                    let Some(notification) = notification else { break };
+                    if self.tx.send(notification).await.is_err() {
+                        break;
+                    }
+                }
+                _ = &mut self.stop => break,
+                _ = &mut self.deserted => break,
+            }
+        }
+    }
+}
+
+/// Open a websocket to `endpoint`, marking it failed when that does not work.
+async fn connect(endpoints: &Endpoints, endpoint: usize, ws_url: &str) -> anyhow::Result<PubsubClient> {
+    PubsubClient::new(ws_url).await.map_err(|e| {
+        endpoints.report_failure(endpoint, &e);
+        e.into()
+    })
+}
+
+/// `getProgramAccounts` along with the slot the accounts were read at, or
+/// `before` when the node does not report it.
+async fn program_accounts_at_slot(
+    rpc: &RpcClient,
+    program: &Pubkey,
+    cfg: RpcProgramAccountsConfig,
+    before: u64,
+) -> anyhow::Result<AccountSnapshot> {
+    let response: OptionalContext<Vec<RpcKeyedAccount>> = rpc
+        .send(RpcRequest::GetProgramAccounts, serde_json::json!([program.to_string(), cfg]))
+        .await?;
+    let (slot, accounts) = match response {
+        OptionalContext::Context(Response { context, value }) => (context.slot, value),
+        OptionalContext::NoContext(value) => (before, value),
+    };
+    let accounts = accounts
+        .into_iter()
+        .filter_map(|RpcKeyedAccount { pubkey, account }| {
+            Some((pubkey.parse().ok()?, account_bytes(&account.data)?))
+        })
+        .collect();
+    Ok(AccountSnapshot { slot, accounts })
+}
+
+fn account_bytes(data: &UiAccountData) -> Option<Vec<u8>> {
+    match data {
+        UiAccountData::Binary(b64, UiAccountEncoding::Base64) => general_purpose::STANDARD.decode(b64).ok(),
+        _ => None,
+    }
+}
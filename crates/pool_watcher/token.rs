@@ -1,72 +1,46 @@
 use anyhow::Result;
 use dashmap::DashMap;
+use futures::future::BoxFuture;
 use once_cell::sync::Lazy;
-use solana_client::rpc_client::RpcClient;
-use solana_sdk::{account::Account, pubkey::Pubkey};
-use std::{str::FromStr, thread, time::Duration};
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
 
 static TOKEN_2022_PROGRAM_ID: Lazy<Pubkey> =
     Lazy::new(|| Pubkey::from_str("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb").unwrap());
 
 use crate::decoders::TokenIntrospectionProvider;
-
-/// Trait for fetching mint accounts and current epoch information.
-pub trait MintFetcher: Send + Sync {
-    fn get_account(&self, mint: &Pubkey) -> Result<Account>;
-    fn get_epoch(&self) -> Result<u64>;
-}
-
-impl MintFetcher for RpcClient {
-    fn get_account(&self, mint: &Pubkey) -> Result<Account> {
-        Ok(RpcClient::get_account(self, mint)?)
-    }
-
-    fn get_epoch(&self) -> Result<u64> {
-        Ok(RpcClient::get_epoch_info(self)?.epoch)
-    }
-}
+use crate::source::AccountSource;
 
 /// Provider that inspects token metadata using direct account owner checks.
-pub struct TokenSafetyProvider<F: MintFetcher> {
-    rpc: F,
+/// Owners are cached, so each mint is read once.
+#[derive(Default)]
+pub struct TokenSafetyProvider {
     cache: DashMap<Pubkey, bool>,
 }
 
-impl<F: MintFetcher> TokenSafetyProvider<F> {
-    pub fn new(rpc: F) -> Self {
-        Self {
-            rpc,
-            cache: DashMap::new(),
-        }
+impl TokenSafetyProvider {
+    pub fn new() -> Self {
+        Self::default()
     }
+}
 
-    fn get_account_retry(&self, mint: &Pubkey) -> Result<Account> {
-        const MAX_RETRIES: usize = 5;
-        let mut delay = Duration::from_millis(200);
-        for attempt in 0..MAX_RETRIES {
-            match self.rpc.get_account(mint) {
-                Ok(acc) => return Ok(acc),
-                Err(_e) if attempt + 1 < MAX_RETRIES => {
-                    // retry on transient errors with exponential backoff
-                    thread::sleep(delay);
-                    delay *= 2;
-                    continue;
+impl TokenIntrospectionProvider for TokenSafetyProvider {
+    fn token2022_flags<'a>(
+        &'a self,
+        source: &'a dyn AccountSource,
+        mints: &'a [Pubkey],
+    ) -> BoxFuture<'a, Result<Vec<bool>>> {
+        Box::pin(async move {
+            let unknown: Vec<Pubkey> = mints.iter().filter(|mint| !self.cache.contains_key(mint)).copied().collect();
+            if !unknown.is_empty() {
+                for account in source.get_accounts(&unknown).await? {
+                    // missing mints are looked up again next time
+                    if let Some(owner) = account.owner {
+                        self.cache.insert(account.key, owner == *TOKEN_2022_PROGRAM_ID);
+                    }
                 }
-                Err(e) => return Err(e),
             }
-        }
-        unreachable!("retry loop should return or error before this point")
-    }
-}
-
-impl<F: MintFetcher> TokenIntrospectionProvider for TokenSafetyProvider<F> {
-    fn is_token2022(&self, mint: &Pubkey) -> Result<bool> {
-        if let Some(v) = self.cache.get(mint) {
-            return Ok(*v);
-        }
-        let account = self.get_account_retry(mint)?;
-        let is_2022 = account.owner == *TOKEN_2022_PROGRAM_ID;
-        self.cache.insert(*mint, is_2022);
-        Ok(is_2022)
+            Ok(mints.iter().map(|mint| self.cache.get(mint).is_some_and(|v| *v)).collect())
+        })
     }
 }
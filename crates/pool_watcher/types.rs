@@ -1,11 +1,80 @@
 use serde::{Serialize,Deserialize};
 use solana_sdk::pubkey::Pubkey;
+use crate::decoders::{
+    meteora_damm::{DammV1State, DammV2State},
+    meteora_dlmm::LbPairState,
+    pump_fun::BondingCurveState, pump_swap::PumpSwapPoolState, orca_whirl::WhirlpoolState, raydium_amm_v4::AmmV4State,
+    raydium_cpmm::CpmmPoolState,
+};
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Default)]
+pub enum DexKind {
+    #[default]
+    OrcaWhirlpools,
+    RaydiumClmm,
+    RaydiumCpmm,
+    RaydiumAmmV4,
+    MeteoraDlmm,
+    MeteoraDamm,
+    PumpFun,
+    PumpSwap,
+    /// A DEX handled by a decoder registered outside this crate.
+    Other,
+}
+
+/// Trade fee in parts per million of the swapped amount, i.e. hundredths of a
+/// basis point. This is the native precision of Whirlpool and Raydium fee fields.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[serde(transparent)]
+pub struct FeeRate(u32);
+
+impl FeeRate {
+    pub const PPM_PER_BPS: u32 = 100;
+
+    pub const fn from_ppm(ppm: u32) -> Self { Self(ppm) }
+    pub const fn from_bps(bps: u16) -> Self { Self(bps as u32 * Self::PPM_PER_BPS) }
+    /// Fee expressed as `numerator / denominator`, as used by constant-product AMMs.
+    pub fn from_ratio(numerator: u64, denominator: u64) -> Option<Self> {
+        if denominator == 0 { return None; }
+        let ppm = (numerator as u128 * 1_000_000) / denominator as u128;
+        u32::try_from(ppm).ok().map(Self)
+    }
+    pub const fn ppm(self) -> u32 { self.0 }
+    pub fn bps(self) -> f64 { self.0 as f64 / Self::PPM_PER_BPS as f64 }
+    /// Fee as a fraction of the input amount (3000 ppm -> 0.003).
+    pub fn fraction(self) -> f64 { self.0 as f64 / 1_000_000.0 }
+}
+
+/// Point after which a scheduled pool accepts swaps.
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
-pub enum DexKind { OrcaWhirlpools, RaydiumClmm, RaydiumCpmm }
+pub enum OpenAt {
+    /// Unix timestamp in seconds.
+    Timestamp(u64),
+    Slot(u64),
+}
+
+impl OpenAt {
+    /// Meteora activation: type 0 counts in slots, 1 in seconds. A zero
+    /// point means the pool opened on creation.
+    pub fn from_activation(activation_type: u8, point: u64) -> Option<Self> {
+        match (activation_type, point) {
+            (_, 0) => None,
+            (0, slot) => Some(OpenAt::Slot(slot)),
+            (_, ts) => Some(OpenAt::Timestamp(ts)),
+        }
+    }
 
-impl Default for DexKind {
-    fn default() -> Self { DexKind::OrcaWhirlpools }
+    /// Raydium `open_time`, where zero means no schedule.
+    pub fn from_open_time(open_time: u64) -> Option<Self> {
+        (open_time > 0).then_some(OpenAt::Timestamp(open_time))
+    }
+
+    pub fn reached(self, now: u64, slot: u64) -> bool {
+        match self {
+            OpenAt::Timestamp(ts) => now >= ts,
+            OpenAt::Slot(s) => slot >= s,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -20,11 +89,107 @@ pub struct PoolInfo {
     pub id: PoolId,
     pub base_mint: Option<Pubkey>,
     pub quote_mint: Option<Pubkey>,
-    pub fee_bps: Option<u16>,
+    pub fee: Option<FeeRate>,
+    /// Config account the pool references (Raydium `AmmConfig`,
+    /// `WhirlpoolsConfig`). Raydium pools take their fee from it.
+    pub config: Option<Pubkey>,
     pub tick_spacing: Option<u16>,
     pub lp_mint: Option<Pubkey>,         // if applicable
     pub is_token2022_base: bool,
     pub is_token2022_quote: bool,
+    pub base_vault: Option<Pubkey>,
+    pub quote_vault: Option<Pubkey>,
+    pub sqrt_price_x64: Option<u128>,    // concentrated liquidity pools
+    pub liquidity: Option<u128>,
+    pub tick_current: Option<i32>,
+    /// Whether the pool status allows swaps; `None` when the DEX has no status.
+    pub swap_enabled: Option<bool>,
+    /// When swaps open, for pools created with a schedule.
+    pub open_at: Option<OpenAt>,
+    pub state: Option<DexState>,
+}
+
+impl PoolInfo {
+    /// Whether a swap would be accepted at unix time `now` and `slot`.
+    pub fn is_tradeable(&self, now: u64, slot: u64) -> bool {
+        self.swap_enabled != Some(false) && self.open_at.is_none_or(|open| open.reached(now, slot))
+    }
+
+    /// Fields that differ from `previous`, a former state of the same pool.
+    pub fn changed_fields(&self, previous: &PoolInfo) -> Vec<PoolField> {
+        let mut changed = Vec::new();
+        if self.fee != previous.fee {
+            changed.push(PoolField::Fee);
+        }
+        if (self.base_mint, self.quote_mint) != (previous.base_mint, previous.quote_mint) {
+            changed.push(PoolField::Mints);
+        }
+        if (self.sqrt_price_x64, self.tick_current) != (previous.sqrt_price_x64, previous.tick_current) {
+            changed.push(PoolField::Price);
+        }
+        if self.liquidity != previous.liquidity {
+            changed.push(PoolField::Liquidity);
+        }
+        if (self.swap_enabled, self.open_at) != (previous.swap_enabled, previous.open_at) {
+            changed.push(PoolField::Status);
+        }
+        changed
+    }
+}
+
+/// `PoolInfo` fields reported by [`PoolEvent::AccountChanged`].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum PoolField {
+    Fee,
+    /// `base_mint` or `quote_mint`.
+    Mints,
+    /// `sqrt_price_x64` or `tick_current`.
+    Price,
+    Liquidity,
+    /// `swap_enabled` or `open_at`.
+    Status,
+}
+
+/// Full decoded account state for DEXes that expose more than the common fields.
+/// Variants are boxed to keep `PoolInfo` small when cloned onto the bus.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum DexState {
+    Whirlpool(Box<WhirlpoolState>),
+    RaydiumCpmm(Box<CpmmPoolState>),
+    RaydiumAmmV4(Box<AmmV4State>),
+    MeteoraDlmm(Box<LbPairState>),
+    MeteoraDammV1(Box<DammV1State>),
+    MeteoraDammV2(Box<DammV2State>),
+    PumpFunCurve(Box<BondingCurveState>),
+    PumpSwap(Box<PumpSwapPoolState>),
+    /// State produced by a decoder registered outside this crate.
+    Other(Box<serde_json::Value>),
+}
+
+impl DexState {
+    /// Token amounts held in the pool vaults that do not belong to liquidity
+    /// providers (uncollected protocol/fund fees, pending PnL), as (base, quote).
+    pub fn vault_fees(&self) -> (u64, u64) {
+        match self {
+            DexState::Whirlpool(s) => (s.protocol_fee_owed_a, s.protocol_fee_owed_b),
+            DexState::RaydiumCpmm(s) => (
+                s.protocol_fees_token_0.saturating_add(s.fund_fees_token_0),
+                s.protocol_fees_token_1.saturating_add(s.fund_fees_token_1),
+            ),
+            DexState::RaydiumAmmV4(s) => (s.need_take_pnl_coin, s.need_take_pnl_pc),
+            DexState::MeteoraDlmm(s) => (s.protocol_fee_x, s.protocol_fee_y),
+            // v1 liquidity lives in Meteora vaults; reserves come from the
+            // pool's share of their LP supply, which excludes vault fees
+            DexState::MeteoraDammV1(_) => (0, 0),
+            DexState::MeteoraDammV2(s) => (
+                s.protocol_a_fee.saturating_add(s.partner_a_fee),
+                s.protocol_b_fee.saturating_add(s.partner_b_fee),
+            ),
+            // protocol and creator fees are transferred out on every swap
+            DexState::PumpFunCurve(_) | DexState::PumpSwap(_) => (0, 0),
+            DexState::Other(_) => (0, 0),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -32,8 +197,29 @@ pub enum PoolEvent {
     SnapshotStarted { program: Pubkey },
     SnapshotFinished { program: Pubkey, count: usize },
     AccountNew { info: PoolInfo, data_len: usize, slot: u64 },
-    AccountChanged { info: PoolInfo, data_len: usize, slot: u64 },
+    /// A known pool was updated. `changed` lists the `PoolInfo` fields that
+    /// differ; it is empty when only the DEX state changed.
+    AccountChanged { info: PoolInfo, data_len: usize, slot: u64, changed: Vec<PoolField> },
     AccountDeleted { id: PoolId, slot: u64 },
     ProgramLog { program: Pubkey, signature: String, slot: u64 },
+    /// A pump.fun bonding curve completed (`pool` is `None`) or its token got an
+    /// AMM pool (`pool` is the new pool and `mint` the graduated token). The
+    /// second case needs the curve in the inventory. pump.fun is watched
+    /// without snapshots by default, so only curves the subscription delivered
+    /// since the watcher started are known.
+    CurveGraduated { curve: PoolId, mint: Option<Pubkey>, pool: Option<PoolInfo>, slot: u64 },
+    /// A pool that was disabled or scheduled to open later started accepting swaps.
+    PoolOpened { info: PoolInfo, slot: u64 },
     ResyncTick { program: Pubkey },
 }
+
+impl PoolEvent {
+    /// `AccountNew` for a pool not seen before, otherwise `AccountChanged`
+    /// with the fields that differ from `previous`.
+    pub fn account_update(previous: Option<&PoolInfo>, info: PoolInfo, data_len: usize, slot: u64) -> Self {
+        match previous {
+            Some(previous) => PoolEvent::AccountChanged { changed: info.changed_fields(previous), info, data_len, slot },
+            None => PoolEvent::AccountNew { info, data_len, slot },
+        }
+    }
+}
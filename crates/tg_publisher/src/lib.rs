@@ -171,6 +171,18 @@ fn short(pk: &solana_sdk::pubkey::Pubkey) -> String {
     format!("{}…{}", &s[..4], &s[s.len() - 4..])
 }
 
+/// Render a ppm fee as basis points without losing sub-bps precision
+/// (3000 -> "30", 500 -> "5", 25 -> "0.25").
+fn fee_bps_str(fee_ppm: Option<u32>) -> String {
+    match fee_ppm {
+        Some(ppm) if ppm % 100 == 0 => (ppm / 100).to_string(),
+        Some(ppm) => format!("{:.2}", ppm as f64 / 100.0)
+            .trim_end_matches('0')
+            .to_string(),
+        None => "n/a".into(),
+    }
+}
+
 fn format_pool_message(b: &PoolTokenBundle) -> String {
     let a_ok = if b.token_a.decision_safe {
         "✅"
@@ -184,9 +196,7 @@ fn format_pool_message(b: &PoolTokenBundle) -> String {
     };
     let head = format!(
         "🆕 *New Pool*  fee: *{}* bps  tick: *{}*\nPool: `{}`",
-        b.fee_bps
-            .map(|v| v.to_string())
-            .unwrap_or_else(|| "n/a".into()),
+        fee_bps_str(b.fee_ppm),
         b.tick_spacing
             .map(|v| v.to_string())
             .unwrap_or_else(|| "n/a".into()),
@@ -223,11 +233,14 @@ fn format_pool_message(b: &PoolTokenBundle) -> String {
 
 fn format_enriched_message(a: &EnrichedPoolAlert) -> String {
     let b = &a.bundle;
+    let title = match a.graduated_from {
+        Some(curve) => format!("🎓 *Graduated* from `{}`", short(&curve)),
+        None => "🆕 *New Pool*".to_string(),
+    };
     let head = escape_md_v2(&format!(
-        "🆕 *New Pool*\nfee: *{}* bps, tick: *{}*\nPool: `{}`",
-        b.fee_bps
-            .map(|v| v.to_string())
-            .unwrap_or_else(|| "n/a".into()),
+        "{}\nfee: *{}* bps, tick: *{}*\nPool: `{}`",
+        title,
+        fee_bps_str(b.fee_ppm),
         b.tick_spacing
             .map(|v| v.to_string())
             .unwrap_or_else(|| "n/a".into()),
@@ -285,6 +298,18 @@ fn format_enriched_message(a: &EnrichedPoolAlert) -> String {
     } else {
         escape_md_v2("🔥 *Hype*\nN/A")
     };
+    let lp = a.lp.as_ref().map(|lp| {
+        let warn = if lp.single_lp { "⚠️ single LP" } else { "✅" };
+        let owner = lp.top_owner.map(|o| short(&o)).unwrap_or_else(|| "n/a".into());
+        escape_md_v2(&format!(
+            "👤 *LP*\npositions: {} | owners: {} | top: `{}` {:.0}% {}",
+            lp.positions,
+            lp.owners,
+            owner,
+            lp.top_share * 100.0,
+            warn
+        ))
+    });
     let mut reasons = Vec::new();
     reasons.extend(b.token_a.reasons.iter().cloned());
     reasons.extend(b.token_b.reasons.iter().cloned());
@@ -294,5 +319,22 @@ fn format_enriched_message(a: &EnrichedPoolAlert) -> String {
         reasons.join(", ")
     };
     let reasons = escape_md_v2(&reasons);
-    format!("{head}\n{a_line}\n{b_line}\n{liq}\n{hype}\n*Reasons:* {reasons}")
+    match lp {
+        Some(lp) => format!("{head}\n{a_line}\n{b_line}\n{liq}\n{lp}\n{hype}\n*Reasons:* {reasons}"),
+        None => format!("{head}\n{a_line}\n{b_line}\n{liq}\n{hype}\n*Reasons:* {reasons}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fee_bps_keeps_precision() {
+        assert_eq!(fee_bps_str(Some(3000)), "30");
+        assert_eq!(fee_bps_str(Some(100)), "1");
+        assert_eq!(fee_bps_str(Some(25)), "0.25");
+        assert_eq!(fee_bps_str(Some(150)), "1.5");
+        assert_eq!(fee_bps_str(None), "n/a");
+    }
 }
@@ -29,14 +29,14 @@ pub fn analyze_extensions(data: &[u8], _now_epoch: u64) -> (Flags, Option<Transf
         match ext_type {
             EXT_NON_TRANSFERABLE => flags.non_transferable = true,
             EXT_DEFAULT_ACCOUNT_STATE => {
-                if let Some(&state) = slice.get(0) {
+                if let Some(&state) = slice.first() {
                     if state == 2 { flags.default_frozen = true; }
                 }
             }
             EXT_PERMANENT_DELEGATE => flags.permanent_delegate = true,
             EXT_TRANSFER_HOOK => flags.transfer_hook = true,
             EXT_MEMO_TRANSFER => {
-                if let Some(&b) = slice.get(0) {
+                if let Some(&b) = slice.first() {
                     flags.memo_required = b != 0;
                 }
             }
@@ -1,5 +1,6 @@
 use std::{
     fs,
+    hash::Hash,
     num::NonZeroUsize,
     path::PathBuf,
     str::FromStr,
@@ -9,18 +10,21 @@ use std::{
 
 use anyhow::Result;
 use common_types::{
-    EnrichedPoolAlert, PoolTokenBundle, TokenExtensionFlags, TokenProgramKind, TokenSafetyReport,
+    EnrichedPoolAlert, LpConcentration, PoolTokenBundle, TokenExtensionFlags, TokenProgramKind, TokenSafetyReport,
 };
 use file_sink::{FileSink, FileSinkCfg};
 use futures::{SinkExt, StreamExt};
 use hype_score::{HypeAggregator, HypeConfig, PoolLogEvent};
-use liq_metrics::{compute_quick, PoolInput};
+use liq_metrics::{compute_quick, PoolInput, VaultShares, DEPTH_PRICE_MOVE};
 use lru::LruCache;
 use pool_watcher::{
-    token::TokenSafetyProvider, types::PoolEvent, PoolBus, PoolWatcher, PoolWatcherConfig,
+    liquidity, positions, token::TokenSafetyProvider, types::PoolEvent, DexKind, DexState, Inventory, PoolBus,
+    EndpointConfig, GrpcConfig, HealthConfig, PoolInfo, PoolWatcher, PoolWatcherConfig, ProgramConfig, ReconnectConfig,
+    SnapshotConfig,
 };
 use serde::Deserialize;
-use solana_client::rpc_client::RpcClient;
+use solana_account_decoder::UiAccountData;
+use solana_client::{rpc_client::RpcClient, rpc_request::TokenAccountsFilter};
 use solana_sdk::{pubkey, pubkey::Pubkey};
 use token_decode::{analyze_mint, policy::Policy};
 use tokio::{
@@ -31,6 +35,8 @@ use tokio_tungstenite::{accept_async, tungstenite::Message};
 use tracing::{info, warn};
 
 const SOL_MINT: Pubkey = pubkey!("So11111111111111111111111111111111111111112");
+const TOKEN_PROGRAM: Pubkey = pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+const TOKEN_2022_PROGRAM: Pubkey = pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -47,14 +53,16 @@ async fn main() -> Result<()> {
     let hype = Arc::new(HypeAggregator::new(cfg.hype_cfg.clone()));
 
     let bus = Arc::new(PoolBus::new(2048));
-    let watcher_rpc = RpcClient::new(cfg.rpc_url.clone());
-    let token_provider = Arc::new(TokenSafetyProvider::new(watcher_rpc));
-    PoolWatcher::new(default_watcher_cfg(&cfg), bus.clone(), token_provider).spawn();
+    let token_provider = Arc::new(TokenSafetyProvider::new());
+    let watcher = PoolWatcher::new(default_watcher_cfg(&cfg), bus.clone(), token_provider);
+    let inventory = watcher.inventory();
+    watcher.spawn();
 
     spawn_logs_ingestor(bus.clone(), hype.clone());
     spawn_pool_pipeline(
         bus.clone(),
         rpc.clone(),
+        inventory,
         tx.clone(),
         sink.clone(),
         hype.clone(),
@@ -67,10 +75,17 @@ async fn main() -> Result<()> {
 }
 
 fn default_watcher_cfg(cfg: &Config) -> PoolWatcherConfig {
-    let mut c = PoolWatcherConfig::default();
-    c.rpc_url = cfg.rpc_url.clone();
-    c.ws_url = cfg.ws_url.clone();
-    c
+    PoolWatcherConfig {
+        rpc_url: cfg.rpc_url.clone(),
+        ws_url: cfg.ws_url.clone(),
+        endpoints: cfg.endpoints.clone(),
+        periodic_resync_min: cfg.periodic_resync_min,
+        programs: cfg.programs.clone(),
+        reconnect: cfg.reconnect.clone(),
+        snapshot: cfg.snapshot.clone(),
+        health: cfg.health.clone(),
+        grpc: cfg.grpc.clone(),
+    }
 }
 
 fn current_ms() -> u64 {
@@ -84,12 +99,19 @@ fn current_ms() -> u64 {
 struct Config {
     rpc_url: String,
     ws_url: String,
+    endpoints: Vec<EndpointConfig>,
     out_dir: PathBuf,
     quote_mints: Vec<Pubkey>,
     probe_amount: u64,
     policy: Policy,
     hype_cfg: HypeConfig,
     broadcast_addr: String,
+    periodic_resync_min: u64,
+    programs: Vec<ProgramConfig>,
+    reconnect: ReconnectConfig,
+    snapshot: SnapshotConfig,
+    health: HealthConfig,
+    grpc: Option<GrpcConfig>,
 }
 
 impl Config {
@@ -98,12 +120,19 @@ impl Config {
         let RawConfig {
             rpc_url,
             ws_url,
+            endpoints,
             out_dir,
             quote_mints,
             probe_amount,
             policy,
             hype,
             broadcast_addr,
+            periodic_resync_min,
+            programs,
+            reconnect,
+            snapshot,
+            health,
+            grpc,
         } = toml::from_str(&data).expect("config parse failed");
         let quote_mints = quote_mints
             .into_iter()
@@ -112,12 +141,24 @@ impl Config {
         Self {
             rpc_url,
             ws_url,
+            endpoints,
             out_dir,
             quote_mints,
             probe_amount,
             policy,
             hype_cfg: hype,
             broadcast_addr,
+            periodic_resync_min,
+            // without [[programs]] entries the watcher's defaults apply
+            programs: if programs.is_empty() {
+                PoolWatcherConfig::default().programs
+            } else {
+                programs
+            },
+            reconnect,
+            snapshot,
+            health,
+            grpc,
         }
     }
 }
@@ -128,6 +169,8 @@ struct RawConfig {
     rpc_url: String,
     #[serde(default = "default_ws_url")]
     ws_url: String,
+    #[serde(default)]
+    endpoints: Vec<EndpointConfig>,
     #[serde(default = "default_out_dir")]
     out_dir: PathBuf,
     #[serde(default)]
@@ -140,6 +183,18 @@ struct RawConfig {
     hype: HypeConfig,
     #[serde(default = "default_broadcast_addr")]
     broadcast_addr: String,
+    #[serde(default = "default_resync_min")]
+    periodic_resync_min: u64,
+    #[serde(default)]
+    programs: Vec<ProgramConfig>,
+    #[serde(default)]
+    reconnect: ReconnectConfig,
+    #[serde(default)]
+    snapshot: SnapshotConfig,
+    #[serde(default)]
+    health: HealthConfig,
+    #[serde(default)]
+    grpc: Option<GrpcConfig>,
 }
 
 fn default_rpc_url() -> String {
@@ -162,10 +217,26 @@ fn default_broadcast_addr() -> String {
     "127.0.0.1:9001".into()
 }
 
+fn default_resync_min() -> u64 {
+    30
+}
+
+/// Next event of the bus, skipping over the events a lagging receiver
+/// missed. `None` once the bus is closed.
+async fn next_event(rx: &mut broadcast::Receiver<PoolEvent>) -> Option<PoolEvent> {
+    loop {
+        match rx.recv().await {
+            Ok(ev) => return Some(ev),
+            Err(broadcast::error::RecvError::Lagged(missed)) => warn!(missed, "pool events dropped, receiver lagging"),
+            Err(broadcast::error::RecvError::Closed) => return None,
+        }
+    }
+}
+
 fn spawn_logs_ingestor(bus: Arc<PoolBus>, hype: Arc<HypeAggregator>) {
     tokio::spawn(async move {
         let mut rx = bus.subscribe();
-        while let Ok(ev) = rx.recv().await {
+        while let Some(ev) = next_event(&mut rx).await {
             if let PoolEvent::ProgramLog {
                 program,
                 signature,
@@ -214,12 +285,15 @@ fn spawn_ws_server(addr: String, tx: broadcast::Sender<String>) {
 async fn spawn_pool_pipeline(
     bus: Arc<PoolBus>,
     rpc: Arc<RpcClient>,
+    inventory: Inventory,
     tx: broadcast::Sender<String>,
     sink: FileSink,
     hype: Arc<HypeAggregator>,
     cfg: Config,
 ) {
-    let seen = Arc::new(Mutex::new(LruCache::<Pubkey, u64>::new(
+    // keyed by pool and whether the event is a graduation, so that the
+    // graduation of a pool just announced as new is still alerted
+    let seen = Arc::new(Mutex::new(LruCache::<(Pubkey, bool), u64>::new(
         NonZeroUsize::new(10_000).unwrap(),
     )));
     let mint_cache = Arc::new(Mutex::new(LruCache::<Pubkey, TokenSafetyReport>::new(
@@ -227,71 +301,119 @@ async fn spawn_pool_pipeline(
     )));
     tokio::spawn(async move {
         let mut rx = bus.subscribe();
-        while let Ok(ev) = rx.recv().await {
-            match ev {
-                PoolEvent::AccountNew { info, .. } | PoolEvent::AccountChanged { info, .. } => {
-                    if let (Some(mint_a), Some(mint_b)) = (info.base_mint, info.quote_mint) {
-                        if sol_pair(mint_a, mint_b).is_none() {
-                            continue;
-                        }
-                        let pool = info.id.account;
-                        let program = info.id.program;
-                        let now = current_ms();
-                        let ttl = 5 * 60 * 1000;
-                        let mut seen_lock = seen.lock().await;
-                        let mut process = true;
-                        if let Some(ts) = seen_lock.get(&pool).copied() {
-                            if now - ts < ttl {
-                                process = false;
-                            }
-                        }
-                        if process {
-                            seen_lock.put(pool, now);
-                        }
-                        drop(seen_lock);
-                        if !process {
-                            continue;
-                        }
-                        let rpc = rpc.clone();
-                        let tx = tx.clone();
-                        let sink = sink.clone();
-                        let hype = hype.clone();
-                        let policy = cfg.policy.clone();
-                        let quote_mints = cfg.quote_mints.clone();
-                        let probe_amount = cfg.probe_amount;
-                        let mint_cache = mint_cache.clone();
-                        tokio::spawn(async move {
-                            if let Err(e) = handle_pool_event(
-                                rpc,
-                                tx,
-                                sink,
-                                hype,
-                                policy,
-                                quote_mints,
-                                probe_amount,
-                                mint_cache,
-                                pool,
-                                program,
-                                mint_a,
-                                mint_b,
-                                info.fee_bps,
-                                info.tick_spacing,
-                            )
-                            .await
-                            {
-                                warn!(?e, ?pool, "pipeline failed");
-                            }
-                        });
-                    }
+        while let Some(ev) = next_event(&mut rx).await {
+            let graduated_from = match &ev {
+                PoolEvent::CurveGraduated { curve, .. } => Some(curve.account),
+                _ => None,
+            };
+            let info = match ev {
+                // scheduled pools are alerted by `PoolOpened`, once swaps succeed
+                PoolEvent::AccountNew { info, slot, .. } | PoolEvent::AccountChanged { info, slot, .. }
+                    if !info.is_tradeable(current_ms() / 1000, slot) =>
+                {
+                    continue
                 }
-                _ => {}
+                PoolEvent::AccountNew { info, .. }
+                | PoolEvent::AccountChanged { info, .. }
+                | PoolEvent::PoolOpened { info, .. } => match (info.base_mint, info.quote_mint) {
+                    (Some(mint_a), Some(mint_b)) if sol_pair(mint_a, mint_b).is_some() => info,
+                    _ => continue,
+                },
+                // a completed curve is alerted before its mint is known; the
+                // pipeline resolves it from the curve's token account
+                PoolEvent::CurveGraduated {
+                    curve, mint, pool, ..
+                } => pool.unwrap_or_else(|| PoolInfo {
+                    dex: DexKind::PumpFun,
+                    id: curve,
+                    base_mint: mint,
+                    quote_mint: Some(SOL_MINT),
+                    ..Default::default()
+                }),
+                _ => continue,
+            };
+            let pool = info.id.account;
+            let mut seen_lock = seen.lock().await;
+            let key = (pool, graduated_from.is_some());
+            let process = should_process(&mut seen_lock, key, current_ms(), 5 * 60 * 1000);
+            drop(seen_lock);
+            if !process {
+                continue;
             }
+            let rpc = rpc.clone();
+            let inventory = inventory.clone();
+            let tx = tx.clone();
+            let sink = sink.clone();
+            let hype = hype.clone();
+            let policy = cfg.policy.clone();
+            let quote_mints = cfg.quote_mints.clone();
+            let probe_amount = cfg.probe_amount;
+            let mint_cache = mint_cache.clone();
+            tokio::spawn(async move {
+                if let Err(e) = handle_pool_event(
+                    rpc,
+                    inventory,
+                    tx,
+                    sink,
+                    hype,
+                    policy,
+                    quote_mints,
+                    probe_amount,
+                    mint_cache,
+                    info,
+                    graduated_from,
+                )
+                .await
+                {
+                    warn!(?e, ?pool, "pipeline failed");
+                }
+            });
         }
     });
 }
 
+/// Split of a CLMM pool's liquidity by LP wallet. Positions the watcher has
+/// not indexed yet are loaded from the program, and owners resolved once.
+fn lp_concentration(rpc: &RpcClient, inventory: &Inventory, info: &PoolInfo) -> Result<Option<LpConcentration>> {
+    let mut pool_positions = inventory.positions(&info.id.account);
+    if pool_positions.is_empty() {
+        pool_positions = positions::fetch_positions(rpc, info)?;
+    }
+    if pool_positions.iter().any(|p| p.owner.is_none()) {
+        positions::resolve_owners(rpc, &mut pool_positions)?;
+        for p in &pool_positions {
+            inventory.upsert_position(p.clone());
+        }
+    }
+    Ok(positions::concentration(&pool_positions, info.tick_current).map(|c| LpConcentration {
+        positions: c.positions as u32,
+        owners: c.owners as u32,
+        top_owner: c.top_owner,
+        top_share: c.top_share,
+        single_lp: c.single_lp(),
+    }))
+}
+
+/// Find the mint of a pump.fun bonding curve through the token account it owns.
+fn resolve_curve_mint(rpc: &RpcClient, curve: &Pubkey) -> Result<Option<Pubkey>> {
+    for program in [TOKEN_PROGRAM, TOKEN_2022_PROGRAM] {
+        let accounts = rpc.get_token_accounts_by_owner(curve, TokenAccountsFilter::ProgramId(program))?;
+        for keyed in accounts {
+            if let UiAccountData::Json(parsed) = keyed.account.data {
+                let mint = parsed.parsed["info"]["mint"].as_str().and_then(|m| m.parse().ok());
+                if mint.is_some() {
+                    return Ok(mint);
+                }
+            }
+        }
+    }
+    Ok(None)
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn handle_pool_event(
     rpc: Arc<RpcClient>,
+    inventory: Inventory,
     tx: broadcast::Sender<String>,
     sink: FileSink,
     hype: Arc<HypeAggregator>,
@@ -299,13 +421,17 @@ async fn handle_pool_event(
     quote_mints: Vec<Pubkey>,
     probe_amount: u64,
     mint_cache: Arc<Mutex<LruCache<Pubkey, TokenSafetyReport>>>,
-    pool: Pubkey,
-    program: Pubkey,
-    mint_a: Pubkey,
-    mint_b: Pubkey,
-    fee_bps: Option<u16>,
-    tick_spacing: Option<u16>,
+    mut info: PoolInfo,
+    graduated_from: Option<Pubkey>,
 ) -> Result<()> {
+    if info.dex == DexKind::PumpFun && info.base_mint.is_none() {
+        info.base_mint = resolve_curve_mint(&rpc, &info.id.account)?;
+    }
+    let (Some(mint_a), Some(mint_b)) = (info.base_mint, info.quote_mint) else {
+        return Ok(());
+    };
+    let pool = info.id.account;
+    let program = info.id.program;
     let epoch = rpc.get_epoch_info().map(|e| e.epoch).unwrap_or(0);
     let (non_sol_mint, non_sol_is_a) = match sol_pair(mint_a, mint_b) {
         Some(v) => v,
@@ -360,6 +486,36 @@ async fn handle_pool_event(
     } else {
         rep_non_sol.decimals
     };
+    let (vault_fees_a, vault_fees_b) = info
+        .state
+        .as_ref()
+        .map(|s| s.vault_fees())
+        .unwrap_or((0, 0));
+    let vault_shares = match info.state.as_ref() {
+        Some(DexState::MeteoraDammV1(s)) => Some(VaultShares {
+            vault_a: s.a_vault,
+            vault_b: s.b_vault,
+            vault_lp_a: s.a_vault_lp,
+            vault_lp_b: s.b_vault_lp,
+        }),
+        _ => None,
+    };
+    let active_bin = match info.state.as_ref() {
+        Some(DexState::MeteoraDlmm(s)) => Some((s.active_id, s.bin_step)),
+        _ => None,
+    };
+    // tick arrays the subscription delivered when they span the depth band,
+    // else three each side around the price, which cover well beyond it
+    let liquidity = match inventory.liquidity_distribution(&info, DEPTH_PRICE_MOVE) {
+        Some(dist) => Some(dist),
+        None => match liquidity::fetch_distribution(&rpc, &info, 3, inventory.tick_array_bitmap(&pool).as_ref()) {
+            Ok(v) => v,
+            Err(e) => {
+                warn!(?e, ?pool, "tick arrays failed");
+                None
+            }
+        },
+    };
     let input = PoolInput {
         program,
         pool,
@@ -367,13 +523,18 @@ async fn handle_pool_event(
         mint_b,
         decimals_a,
         decimals_b,
-        vault_a: None,
-        vault_b: None,
-        sqrt_price_x64: None,
-        is_clmm: false,
+        vault_a: info.base_vault,
+        vault_b: info.quote_vault,
+        vault_fees_a,
+        vault_fees_b,
+        vault_shares,
+        sqrt_price_x64: info.sqrt_price_x64,
+        is_clmm: info.sqrt_price_x64.is_some(),
+        active_bin,
         quote_mints,
+        liquidity,
     };
-    let liq = match compute_quick(&*rpc, &input) {
+    let liq = match compute_quick(&rpc, &input) {
         Ok(v) => {
             info!(?pool, "liq computed");
             Some(v)
@@ -383,20 +544,38 @@ async fn handle_pool_event(
             None
         }
     };
+    // position lookups use the blocking client, one request per NFT
+    let lp = {
+        let (rpc, inventory, info) = (rpc.clone(), inventory.clone(), info.clone());
+        tokio::task::spawn_blocking(move || lp_concentration(&rpc, &inventory, &info)).await
+    };
+    let lp = match lp {
+        Ok(Ok(v)) => v,
+        Ok(Err(e)) => {
+            warn!(?e, ?pool, "positions failed");
+            None
+        }
+        Err(e) => {
+            warn!(?e, ?pool, "positions task failed");
+            None
+        }
+    };
     let hype_snap = hype.snapshot(&pool).await;
     let bundle = PoolTokenBundle {
         pool,
         program,
         token_a: rep_a.clone(),
         token_b: rep_b.clone(),
-        fee_bps,
-        tick_spacing,
+        fee_ppm: info.fee.map(|f| f.ppm()),
+        tick_spacing: info.tick_spacing,
         ts_ms: current_ms(),
     };
     let alert = EnrichedPoolAlert {
         bundle,
         liq,
         hype: hype_snap,
+        lp,
+        graduated_from,
     };
     if let Err(e) = sink.write_json("alerts_enriched", &alert).await {
         warn!(?e, ?pool, "file sink error");
@@ -439,7 +618,7 @@ fn sol_pair(mint_a: Pubkey, mint_b: Pubkey) -> Option<(Pubkey, bool)> {
     }
 }
 
-fn should_process(cache: &mut LruCache<Pubkey, u64>, key: Pubkey, now: u64, ttl: u64) -> bool {
+fn should_process<K: Hash + Eq>(cache: &mut LruCache<K, u64>, key: K, now: u64, ttl: u64) -> bool {
     if let Some(ts) = cache.get(&key).copied() {
         if now - ts < ttl {
             return false;
@@ -462,6 +641,15 @@ mod tests {
         assert!(should_process(&mut cache, key, 2000, 1000));
     }
 
+    #[test]
+    fn test_dedup_keeps_graduation_of_new_pool() {
+        let mut cache = LruCache::new(NonZeroUsize::new(4).unwrap());
+        let pool = Pubkey::new_unique();
+        assert!(should_process(&mut cache, (pool, false), 0, 1000));
+        assert!(should_process(&mut cache, (pool, true), 10, 1000));
+        assert!(!should_process(&mut cache, (pool, true), 20, 1000));
+    }
+
     #[test]
     fn test_sol_pair_detect() {
         let other = Pubkey::new_unique();
@@ -2,5 +2,31 @@
 #[ignore]
 async fn integration_stub() {
     // Placeholder for integration test with mocked components.
-    assert!(true);
+}
+
+#[test]
+fn shipped_config_programs_parse() {
+    #[derive(serde::Deserialize)]
+    struct Programs {
+        programs: Vec<pool_watcher::ProgramConfig>,
+    }
+    let raw = std::fs::read_to_string("arb-config.toml").expect("arb-config.toml");
+    let Programs { programs } = toml::from_str(&raw).expect("programs");
+    assert!(programs.iter().any(|p| p.kind == pool_watcher::DexKind::PumpSwap));
+    assert!(programs.iter().any(|p| p.kind == pool_watcher::DexKind::PumpFun && !p.snapshot));
+}
+
+#[test]
+fn shipped_config_parses_as_watcher_config() {
+    let raw = std::fs::read_to_string("arb-config.toml").expect("arb-config.toml");
+    let cfg: pool_watcher::PoolWatcherConfig = toml::from_str(&raw).expect("watcher config");
+    let damm_v2 = "cpamdpZCGKUy5JxQXB4dcpGPiikHswburH8Y7u8MQmG";
+    assert!(cfg.programs.iter().any(|p| p.kind == pool_watcher::DexKind::MeteoraDamm && p.id.to_string() == damm_v2));
+}
+
+#[test]
+fn default_watcher_config_builds() {
+    let cfg = pool_watcher::PoolWatcherConfig::default();
+    let damm = cfg.programs.iter().filter(|p| p.kind == pool_watcher::DexKind::MeteoraDamm).count();
+    assert_eq!(damm, 2);
 }
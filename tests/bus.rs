@@ -1,4 +1,4 @@
-use pool_watcher::{PoolBus, PoolEvent};
+use pool_watcher::{FeeRate, PoolBus, PoolEvent, PoolField, PoolId, PoolInfo};
 use solana_sdk::pubkey::Pubkey;
 
 #[tokio::test]
@@ -12,3 +12,33 @@ async fn publish_and_receive() {
         other => panic!("unexpected event: {:?}", other),
     }
 }
+
+#[test]
+fn account_update_classifies_per_pool() {
+    let known = PoolInfo {
+        id: PoolId { program: Pubkey::new_unique(), account: Pubkey::new_unique() },
+        base_mint: Some(Pubkey::new_unique()),
+        fee: Some(FeeRate::from_bps(30)),
+        sqrt_price_x64: Some(1 << 64),
+        liquidity: Some(1_000),
+        ..Default::default()
+    };
+    let other = PoolInfo { id: PoolId { account: Pubkey::new_unique(), ..known.id.clone() }, ..known.clone() };
+    assert!(matches!(PoolEvent::account_update(None, other, 100, 7), PoolEvent::AccountNew { slot: 7, .. }));
+
+    let mut updated = known.clone();
+    updated.sqrt_price_x64 = Some(2 << 64);
+    updated.liquidity = Some(2_000);
+    match PoolEvent::account_update(Some(&known), updated, 100, 8) {
+        PoolEvent::AccountChanged { changed, slot: 8, .. } => {
+            assert_eq!(changed, vec![PoolField::Price, PoolField::Liquidity])
+        }
+        other => panic!("unexpected event: {:?}", other),
+    }
+
+    let mut updated = known.clone();
+    updated.fee = Some(FeeRate::from_bps(100));
+    updated.swap_enabled = Some(false);
+    assert_eq!(updated.changed_fields(&known), vec![PoolField::Fee, PoolField::Status]);
+    assert!(known.changed_fields(&known).is_empty());
+}
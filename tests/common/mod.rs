@@ -0,0 +1,184 @@
+//! Mock RPC servers and pool fixtures shared by the watcher tests.
+#![allow(dead_code)]
+
+use std::sync::{Arc, Mutex};
+
+use base64::{engine::general_purpose, Engine as _};
+use futures::{
+    future::{self, BoxFuture},
+    SinkExt, StreamExt,
+};
+use pool_watcher::decoders::{orca_whirl, TokenIntrospectionProvider};
+use pool_watcher::positions::Position;
+use pool_watcher::types::{PoolId, PoolInfo};
+use pool_watcher::{AccountSource, DexKind, PoolWatcherConfig, ProgramConfig};
+use serde_json::{json, Value};
+use solana_sdk::pubkey::Pubkey;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tokio_tungstenite::{accept_async, tungstenite::Message};
+
+pub struct NoToken2022;
+
+impl TokenIntrospectionProvider for NoToken2022 {
+    fn token2022_flags<'a>(
+        &'a self,
+        _source: &'a dyn AccountSource,
+        mints: &'a [Pubkey],
+    ) -> BoxFuture<'a, anyhow::Result<Vec<bool>>> {
+        Box::pin(future::ready(Ok(vec![false; mints.len()])))
+    }
+}
+
+/// Whirlpool account with mints `[1; 32]` and `[2; 32]`.
+pub fn whirlpool_data(liquidity: u128) -> Vec<u8> {
+    let mut data = whirlpool_account(Pubkey::new_from_array([1; 32]), Pubkey::new_from_array([2; 32]), 64, 3000);
+    data[49..65].copy_from_slice(&liquidity.to_le_bytes());
+    data[65..81].copy_from_slice(&(1u128 << 64).to_le_bytes());
+    data
+}
+
+/// Whirlpool account trading `token_a` for `token_b`, other fields zeroed.
+pub fn whirlpool_account(token_a: Pubkey, token_b: Pubkey, tick_spacing: u16, fee_rate: u16) -> Vec<u8> {
+    let mut data = vec![0u8; orca_whirl::WHIRLPOOL_LEN];
+    data[..8].copy_from_slice(&orca_whirl::WHIRLPOOL_DISCRIMINATOR);
+    data[41..43].copy_from_slice(&tick_spacing.to_le_bytes());
+    data[45..47].copy_from_slice(&fee_rate.to_le_bytes());
+    data[101..133].copy_from_slice(token_a.as_ref());
+    data[181..213].copy_from_slice(token_b.as_ref());
+    data
+}
+
+/// Orca Whirlpools pool of `program` at a new address.
+pub fn pool(program: Pubkey) -> PoolInfo {
+    PoolInfo {
+        dex: DexKind::OrcaWhirlpools,
+        id: PoolId { program, account: Pubkey::new_unique() },
+        ..Default::default()
+    }
+}
+
+/// Orca Whirlpools position in `pool` over the tick `range`.
+pub fn position(pool: Pubkey, owner: Option<Pubkey>, range: (i32, i32), liquidity: u128) -> Position {
+    Position {
+        dex: DexKind::OrcaWhirlpools,
+        address: Pubkey::new_unique(),
+        pool,
+        position_mint: Pubkey::new_unique(),
+        tick_lower: range.0,
+        tick_upper: range.1,
+        liquidity,
+        owner,
+    }
+}
+
+/// Config watching `programs` with the default settings. The endpoints are
+/// empty, for tests that set them or replace the source.
+pub fn watcher_config(programs: Vec<ProgramConfig>) -> PoolWatcherConfig {
+    PoolWatcherConfig { rpc_url: String::new(), ws_url: String::new(), programs, ..PoolWatcherConfig::default() }
+}
+
+/// Orca Whirlpools entry without filters.
+pub fn whirlpool_program(snapshot: bool) -> ProgramConfig {
+    ProgramConfig {
+        kind: DexKind::OrcaWhirlpools,
+        id: orca_whirl::PROGRAM_ID,
+        snapshot,
+        idl: None,
+        filters: None,
+        mints: Vec::new(),
+    }
+}
+
+pub fn ui_account(data: &[u8]) -> Value {
+    json!({
+        "data": [general_purpose::STANDARD.encode(data), "base64"],
+        "executable": false,
+        "lamports": 1_000_000,
+        "owner": orca_whirl::PROGRAM_ID.to_string(),
+        "rentEpoch": 0,
+        "space": data.len(),
+    })
+}
+
+/// Result of a JSON-RPC request, given the request.
+pub type RpcHandler = Arc<dyn Fn(&Value) -> Value + Send + Sync>;
+
+/// JSON-RPC server over HTTP answering every request with `handler`.
+pub async fn serve_rpc(listener: TcpListener, handler: RpcHandler) {
+    loop {
+        let Ok((stream, _)) = listener.accept().await else { return };
+        tokio::spawn(serve_http(stream, handler.clone()));
+    }
+}
+
+async fn serve_http(mut stream: TcpStream, handler: RpcHandler) {
+    let mut buf = Vec::new();
+    loop {
+        let Some(header_end) = buf.windows(4).position(|w| w == b"\r\n\r\n") else {
+            let mut chunk = [0u8; 4096];
+            match stream.read(&mut chunk).await {
+                Ok(0) | Err(_) => return,
+                Ok(n) => buf.extend_from_slice(&chunk[..n]),
+            }
+            continue;
+        };
+        let headers = String::from_utf8_lossy(&buf[..header_end]).to_lowercase();
+        let length: usize = headers
+            .lines()
+            .find_map(|line| line.strip_prefix("content-length:"))
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(0);
+        let body_start = header_end + 4;
+        while buf.len() < body_start + length {
+            let mut chunk = [0u8; 4096];
+            match stream.read(&mut chunk).await {
+                Ok(0) | Err(_) => return,
+                Ok(n) => buf.extend_from_slice(&chunk[..n]),
+            }
+        }
+        let request: Value = serde_json::from_slice(&buf[body_start..body_start + length]).expect("request");
+        buf.drain(..body_start + length);
+
+        let body = json!({ "jsonrpc": "2.0", "result": handler(&request), "id": request["id"] }).to_string();
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        if stream.write_all(response.as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Pubsub server that accepts every subscription without notifying, and
+/// records the methods it is sent.
+pub async fn serve_pubsub(listener: TcpListener, methods: Arc<Mutex<Vec<String>>>) {
+    loop {
+        let Ok((stream, _)) = listener.accept().await else { return };
+        let methods = methods.clone();
+        tokio::spawn(async move {
+            let Ok(mut ws) = accept_async(stream).await else { return };
+            let mut subscriptions = 0u64;
+            while let Some(Ok(Message::Text(text))) = ws.next().await {
+                let request: Value = serde_json::from_str(&text).expect("request");
+                let method = request["method"].as_str().unwrap_or_default().to_string();
+                let result = match method.ends_with("Unsubscribe") {
+                    true => json!(true),
+                    false => {
+                        subscriptions += 1;
+                        json!(subscriptions)
+                    }
+                };
+                methods.lock().unwrap().push(method);
+                let reply = json!({ "jsonrpc": "2.0", "result": result, "id": request["id"] });
+                if ws.send(Message::Text(reply.to_string())).await.is_err() {
+                    return;
+                }
+            }
+        });
+    }
+}
@@ -1,6 +1,12 @@
+mod common;
+
+use common::whirlpool_account;
 use std::collections::HashSet;
-use pool_watcher::decoders::{self, orca_whirl, raydium_clmm, TokenIntrospectionProvider};
-use pool_watcher::types::DexKind;
+use futures::future::{self, BoxFuture};
+use pool_watcher::decoders::{self, DecodeOutcome, meteora_damm, meteora_dlmm, orca_whirl, pump_fun, pump_swap, raydium_amm_v4, raydium_clmm, raydium_cpmm, TokenIntrospectionProvider};
+use std::sync::Arc;
+use pool_watcher::types::{DexKind, DexState, FeeRate, OpenAt, PoolId, PoolInfo};
+use pool_watcher::{AccountSource, ConfigAccount, ConfigRegistry, DecoderRegistry, PoolDecoder, PoolWatcherConfig, RpcSource};
 use solana_sdk::pubkey::Pubkey;
 
 #[test]
@@ -9,16 +15,103 @@ fn test_orca_decode() {
     let account = Pubkey::new_unique();
     let token_a = Pubkey::new_unique();
     let token_b = Pubkey::new_unique();
-    let mut data = vec![0u8; 200];
-    data[69..101].copy_from_slice(token_a.as_ref());
-    data[149..181].copy_from_slice(token_b.as_ref());
-    data[9..11].copy_from_slice(&3u16.to_le_bytes());
-    data[13..15].copy_from_slice(&5u16.to_le_bytes());
+    let data = whirlpool_account(token_a, token_b, 3, 5);
     let info = orca_whirl::try_decode(program, account, &data).expect("decode");
     assert_eq!(info.base_mint, Some(token_a));
     assert_eq!(info.quote_mint, Some(token_b));
     assert_eq!(info.tick_spacing, Some(3));
-    assert_eq!(info.fee_bps, Some(5));
+    assert_eq!(info.fee, Some(FeeRate::from_ppm(5)));
+}
+
+#[test]
+fn test_orca_decode_full_state() {
+    let program = Pubkey::new_unique();
+    let account = Pubkey::new_unique();
+    let config = Pubkey::new_unique();
+    let vault_a = Pubkey::new_unique();
+    let vault_b = Pubkey::new_unique();
+    let reward_mint = Pubkey::new_unique();
+    let mut data = whirlpool_account(Pubkey::new_unique(), Pubkey::new_unique(), 64, 3000);
+    data[8..40].copy_from_slice(config.as_ref());
+    data[47..49].copy_from_slice(&1300u16.to_le_bytes());
+    data[49..65].copy_from_slice(&5_000_000u128.to_le_bytes());
+    data[65..81].copy_from_slice(&(1u128 << 64).to_le_bytes());
+    data[81..85].copy_from_slice(&(-120i32).to_le_bytes());
+    data[133..165].copy_from_slice(vault_a.as_ref());
+    data[213..245].copy_from_slice(vault_b.as_ref());
+    data[269 + 128..269 + 160].copy_from_slice(reward_mint.as_ref());
+    let info = orca_whirl::try_decode(program, account, &data).expect("decode");
+    // fee_rate is in hundredths of a basis point: 3000 is a 0.3% pool
+    assert_eq!(info.fee.map(|f| f.bps()), Some(30.0));
+    assert_eq!(info.base_vault, Some(vault_a));
+    assert_eq!(info.quote_vault, Some(vault_b));
+    assert_eq!(info.sqrt_price_x64, Some(1u128 << 64));
+    assert_eq!(info.liquidity, Some(5_000_000));
+    assert_eq!(info.tick_current, Some(-120));
+    let Some(DexState::Whirlpool(state)) = info.state else { panic!("missing whirlpool state") };
+    assert_eq!(state.whirlpools_config, config);
+    assert_eq!(state.protocol_fee_rate, 1300);
+    assert!(!state.reward_infos[0].initialized());
+    assert_eq!(state.reward_infos[1].mint, reward_mint);
+}
+
+#[test]
+fn test_orca_decode_rejects_short_account() {
+    let data = vec![0u8; orca_whirl::WHIRLPOOL_LEN - 1];
+    assert!(orca_whirl::try_decode(Pubkey::new_unique(), Pubkey::new_unique(), &data).is_none());
+}
+
+#[test]
+fn test_orca_config_accounts() {
+    let program = orca_whirl::PROGRAM_ID;
+    let config_key = Pubkey::new_unique();
+    let mut config = vec![0u8; 108];
+    config[..8].copy_from_slice(&orca_whirl::ACCOUNT_TYPES[1].0);
+    config[104..106].copy_from_slice(&300u16.to_le_bytes());
+    let mut tier = vec![0u8; orca_whirl::FEE_TIER_LEN];
+    tier[..8].copy_from_slice(&orca_whirl::ACCOUNT_TYPES[3].0);
+    tier[8..40].copy_from_slice(config_key.as_ref());
+    tier[40..42].copy_from_slice(&64u16.to_le_bytes());
+    tier[42..44].copy_from_slice(&3000u16.to_le_bytes());
+
+    let configs = ConfigRegistry::default();
+    for (key, data) in [(config_key, &config), (orca_whirl::fee_tier_address(&config_key, 64), &tier)] {
+        let DecodeOutcome::Config(decoded) = orca_whirl::decode_account(program, key, data) else {
+            panic!("not a config")
+        };
+        // whirlpools carry their own fee, so neither account sets one
+        assert!(!configs.insert(key, *decoded));
+    }
+    let Some(ConfigAccount::WhirlpoolsConfig(stored)) = configs.get(&config_key) else { panic!("missing config") };
+    assert_eq!(stored.default_protocol_fee_rate, 300);
+    let fee_tier = configs.fee_tier(&config_key, 64).expect("fee tier");
+    assert_eq!((fee_tier.whirlpools_config, fee_tier.default_fee_rate), (config_key, 3000));
+
+    let mut data = whirlpool_account(Pubkey::new_unique(), Pubkey::new_unique(), 64, 500);
+    data[8..40].copy_from_slice(config_key.as_ref());
+    let mut info = orca_whirl::try_decode(program, Pubkey::new_unique(), &data).expect("decode");
+    assert_eq!(info.config, Some(config_key));
+    assert!(configs.missing(&info).is_empty());
+    configs.apply(&mut info);
+    assert_eq!(info.fee, Some(FeeRate::from_ppm(500)));
+    info.tick_spacing = Some(8);
+    assert_eq!(configs.missing(&info), vec![orca_whirl::fee_tier_address(&config_key, 8)]);
+}
+
+#[test]
+fn test_config_registry_reports_fee_changes() {
+    let key = Pubkey::new_unique();
+    let config = |fee: u32| {
+        let mut data = vec![0u8; raydium_clmm::AMM_CONFIG_LEN];
+        data[..8].copy_from_slice(&raydium_clmm::ACCOUNT_TYPES[1].0);
+        data[47..51].copy_from_slice(&fee.to_le_bytes());
+        ConfigAccount::parse(DexKind::RaydiumClmm, &data).expect("config")
+    };
+    let configs = ConfigRegistry::default();
+    assert!(configs.insert(key, config(2500)));
+    assert!(!configs.insert(key, config(2500)));
+    assert!(configs.insert(key, config(100)));
+    assert_eq!(configs.get(&key).and_then(|c| c.trade_fee()), Some(FeeRate::from_ppm(100)));
 }
 
 #[test]
@@ -28,54 +121,88 @@ fn test_raydium_decode() {
     let cfg_account = Pubkey::new_unique();
     let token_a = Pubkey::new_unique();
     let token_b = Pubkey::new_unique();
-    // feed config with trade fee
-    let mut cfg = vec![0u8; 117];
+    let mut cfg = vec![0u8; raydium_clmm::AMM_CONFIG_LEN];
+    cfg[..8].copy_from_slice(&raydium_clmm::ACCOUNT_TYPES[1].0);
     cfg[47..51].copy_from_slice(&300u32.to_le_bytes());
-    raydium_clmm::try_decode(program, cfg_account, &cfg);
+    cfg[51..53].copy_from_slice(&10u16.to_le_bytes());
+    let DecodeOutcome::Config(config) = raydium_clmm::decode_account(program, cfg_account, &cfg) else {
+        panic!("not a config")
+    };
+    let ConfigAccount::RaydiumClmm(amm_config) = config.as_ref() else { panic!("not a CLMM config") };
+    assert_eq!((amm_config.trade_fee_rate, amm_config.tick_spacing), (300, 10));
 
     let mut data = vec![0u8; 240];
+    data[..8].copy_from_slice(&raydium_clmm::ACCOUNT_TYPES[0].0);
     data[9..41].copy_from_slice(cfg_account.as_ref());
     data[73..105].copy_from_slice(token_a.as_ref());
     data[105..137].copy_from_slice(token_b.as_ref());
     data[235..237].copy_from_slice(&9u16.to_le_bytes());
-    let info = raydium_clmm::try_decode(program, account, &data).expect("decode");
+    let mut info = raydium_clmm::try_decode(program, account, &data).expect("decode");
     assert_eq!(info.base_mint, Some(token_a));
     assert_eq!(info.quote_mint, Some(token_b));
-    assert_eq!(info.fee_bps, Some(3));
+    assert_eq!(info.config, Some(cfg_account));
+    assert_eq!(info.fee, None);
+
+    // the fee comes from the AmmConfig once the registry has it
+    let configs = ConfigRegistry::default();
+    assert_eq!(configs.missing(&info), vec![cfg_account]);
+    assert!(configs.insert(cfg_account, *config));
+    assert!(configs.missing(&info).is_empty());
+    configs.apply(&mut info);
+    assert_eq!(info.fee, Some(FeeRate::from_ppm(300)));
+    assert_eq!(info.fee.unwrap().bps(), 3.0);
     assert_eq!(info.tick_spacing, Some(9));
+    // the truncated account stops before status and open_time
+    assert_eq!((info.swap_enabled, info.open_at), (None, None));
+}
+
+#[test]
+fn test_raydium_clmm_status_and_open_time() {
+    let mut data = vec![0u8; 1544];
+    data[..8].copy_from_slice(&raydium_clmm::ACCOUNT_TYPES[0].0);
+    data[235..237].copy_from_slice(&60u16.to_le_bytes());
+    data[389] = 1 << 4;
+    data[1080..1088].copy_from_slice(&1_700_000_000u64.to_le_bytes());
+    let info = raydium_clmm::try_decode(Pubkey::new_unique(), Pubkey::new_unique(), &data).expect("decode");
+    assert_eq!(info.swap_enabled, Some(false));
+    assert_eq!(info.open_at, Some(OpenAt::Timestamp(1_700_000_000)));
+
+    // other status bits do not affect swaps, and a zero open_time means no schedule
+    data[389] = 0b1111;
+    data[1080..1088].fill(0);
+    let info = raydium_clmm::try_decode(Pubkey::new_unique(), Pubkey::new_unique(), &data).expect("decode");
+    assert_eq!((info.swap_enabled, info.open_at), (Some(true), None));
+    assert!(info.is_tradeable(0, 0));
 }
 
 struct MockTokenProvider { tokens: HashSet<Pubkey> }
 
 impl TokenIntrospectionProvider for MockTokenProvider {
-    fn is_token2022(&self, mint: &Pubkey) -> anyhow::Result<bool> {
-        Ok(self.tokens.contains(mint))
+    fn token2022_flags<'a>(
+        &'a self,
+        _source: &'a dyn AccountSource,
+        mints: &'a [Pubkey],
+    ) -> BoxFuture<'a, anyhow::Result<Vec<bool>>> {
+        Box::pin(future::ready(Ok(mints.iter().map(|mint| self.tokens.contains(mint)).collect())))
     }
 }
 
-#[test]
-fn test_decode_pool_token2022() {
-    let program = Pubkey::new_unique();
-    let account = Pubkey::new_unique();
+#[tokio::test]
+async fn test_set_token2022_flags() {
     let token_a = Pubkey::new_unique();
     let token_b = Pubkey::new_unique();
-    let mut data = vec![0u8; 200];
-    data[69..101].copy_from_slice(token_a.as_ref());
-    data[149..181].copy_from_slice(token_b.as_ref());
-    data[9..11].copy_from_slice(&3u16.to_le_bytes());
-    data[13..15].copy_from_slice(&5u16.to_le_bytes());
-    let mut set = HashSet::new();
-    set.insert(token_a);
-    let provider = MockTokenProvider { tokens: set };
-    let info = decoders::decode_pool(
-        DexKind::OrcaWhirlpools,
-        program,
-        account,
-        &data,
-        &provider,
-    ).expect("decode");
-    assert!(info.is_token2022_base);
-    assert!(!info.is_token2022_quote);
+    let data = whirlpool_account(token_a, token_b, 3, 5);
+    let decode = || decoders::decode_pool(DexKind::OrcaWhirlpools, Pubkey::new_unique(), Pubkey::new_unique(), &data);
+    let (mut first, mut second) = (decode().expect("decode"), decode().expect("decode"));
+    assert!(!first.is_token2022_base);
+    let provider = MockTokenProvider { tokens: [token_a].into_iter().collect() };
+    // the provider is not asked to read any account
+    let source = RpcSource::new(&PoolWatcherConfig::default());
+    decoders::set_token2022_flags([&mut first, &mut second], &provider, &source).await.expect("flags");
+    for info in [first, second] {
+        assert!(info.is_token2022_base);
+        assert!(!info.is_token2022_quote);
+    }
 }
 
 #[test]
@@ -85,25 +212,356 @@ fn test_decode_pool_raydium_cpmm_kind() {
     let cfg_account = Pubkey::new_unique();
     let token_a = Pubkey::new_unique();
     let token_b = Pubkey::new_unique();
+    let vault_a = Pubkey::new_unique();
+    let lp_mint = Pubkey::new_unique();
 
-    let mut cfg = vec![0u8; 117];
-    cfg[47..51].copy_from_slice(&300u32.to_le_bytes());
-    raydium_clmm::try_decode(program, cfg_account, &cfg);
+    let mut cfg = vec![0u8; raydium_cpmm::AMM_CONFIG_LEN];
+    cfg[..8].copy_from_slice(&raydium_cpmm::AMM_CONFIG_DISCRIMINATOR);
+    cfg[12..20].copy_from_slice(&2500u64.to_le_bytes());
+    assert!(raydium_cpmm::try_decode(program, cfg_account, &cfg).is_none());
+    let configs = ConfigRegistry::default();
+    configs.insert(cfg_account, ConfigAccount::parse(DexKind::RaydiumCpmm, &cfg).expect("config"));
 
-    let mut data = vec![0u8; 240];
-    data[9..41].copy_from_slice(cfg_account.as_ref());
-    data[73..105].copy_from_slice(token_a.as_ref());
-    data[105..137].copy_from_slice(token_b.as_ref());
-    data[235..237].copy_from_slice(&9u16.to_le_bytes());
-    let provider = MockTokenProvider { tokens: HashSet::new() };
-    let info = decoders::decode_pool(
+    let mut data = vec![0u8; raydium_cpmm::POOL_STATE_LEN];
+    data[..8].copy_from_slice(&raydium_cpmm::POOL_STATE_DISCRIMINATOR);
+    data[8..40].copy_from_slice(cfg_account.as_ref());
+    data[72..104].copy_from_slice(vault_a.as_ref());
+    data[136..168].copy_from_slice(lp_mint.as_ref());
+    data[168..200].copy_from_slice(token_a.as_ref());
+    data[200..232].copy_from_slice(token_b.as_ref());
+    data[329] = 1 << 2;
+    data[331] = 6;
+    data[332] = 9;
+    data[373..381].copy_from_slice(&1_700_000_000u64.to_le_bytes());
+    let mut info = decoders::decode_pool(
         DexKind::RaydiumCpmm,
         program,
         account,
         &data,
-        &provider,
     ).expect("decode");
+    assert_eq!(info.fee, None);
+    configs.apply(&mut info);
     assert_eq!(info.dex, DexKind::RaydiumCpmm);
     assert_eq!(info.base_mint, Some(token_a));
     assert_eq!(info.quote_mint, Some(token_b));
+    assert_eq!(info.base_vault, Some(vault_a));
+    assert_eq!(info.lp_mint, Some(lp_mint));
+    assert_eq!(info.fee, Some(FeeRate::from_ppm(2500)));
+    assert_eq!(info.swap_enabled, Some(false));
+    assert_eq!(info.open_at, Some(OpenAt::Timestamp(1_700_000_000)));
+    assert!(!info.is_tradeable(1_800_000_000, 0));
+    let Some(DexState::RaydiumCpmm(state)) = info.state else { panic!("missing cpmm state") };
+    assert_eq!((state.mint_0_decimals, state.mint_1_decimals), (6, 9));
+    assert_eq!(state.open_time, 1_700_000_000);
+    assert!(!state.swap_enabled());
+}
+
+#[test]
+fn test_raydium_amm_v4_decode() {
+    let program = Pubkey::new_unique();
+    let account = Pubkey::new_unique();
+    let coin_mint = Pubkey::new_unique();
+    let pc_mint = Pubkey::new_unique();
+    let coin_vault = Pubkey::new_unique();
+    let pc_vault = Pubkey::new_unique();
+    let lp_mint = Pubkey::new_unique();
+    let market = Pubkey::new_unique();
+    let mut data = vec![0u8; raydium_amm_v4::AMM_INFO_LEN];
+    data[0..8].copy_from_slice(&6u64.to_le_bytes());
+    data[176..184].copy_from_slice(&25u64.to_le_bytes());
+    data[184..192].copy_from_slice(&10_000u64.to_le_bytes());
+    data[200..208].copy_from_slice(&1_000u64.to_le_bytes());
+    data[224..232].copy_from_slice(&1_700_000_000u64.to_le_bytes());
+    data[336..368].copy_from_slice(coin_vault.as_ref());
+    data[368..400].copy_from_slice(pc_vault.as_ref());
+    data[400..432].copy_from_slice(coin_mint.as_ref());
+    data[432..464].copy_from_slice(pc_mint.as_ref());
+    data[464..496].copy_from_slice(lp_mint.as_ref());
+    data[528..560].copy_from_slice(market.as_ref());
+    data[720..728].copy_from_slice(&42u64.to_le_bytes());
+    let info = raydium_amm_v4::try_decode(program, account, &data).expect("decode");
+    assert_eq!(info.dex, DexKind::RaydiumAmmV4);
+    assert_eq!(info.base_mint, Some(coin_mint));
+    assert_eq!(info.quote_mint, Some(pc_mint));
+    assert_eq!(info.base_vault, Some(coin_vault));
+    assert_eq!(info.quote_vault, Some(pc_vault));
+    assert_eq!(info.lp_mint, Some(lp_mint));
+    assert_eq!(info.fee, Some(FeeRate::from_bps(25)));
+    assert_eq!(info.swap_enabled, Some(true));
+    assert_eq!(info.open_at, Some(OpenAt::Timestamp(1_700_000_000)));
+    let state = info.state.expect("state");
+    assert_eq!(state.vault_fees(), (0, 1_000));
+    let DexState::RaydiumAmmV4(amm) = state else { panic!("missing amm v4 state") };
+    assert_eq!(amm.market, market);
+    assert_eq!(amm.lp_amount, 42);
+    assert_eq!(amm.pool_open_time, 1_700_000_000);
+    assert!(amm.swap_enabled());
+
+    // other program accounts (open orders, target orders) have different sizes
+    assert!(raydium_amm_v4::try_decode(program, account, &data[..700]).is_none());
+}
+
+#[test]
+fn test_meteora_dlmm_decode() {
+    let program = Pubkey::new_unique();
+    let account = Pubkey::new_unique();
+    let mint_x = Pubkey::new_unique();
+    let mint_y = Pubkey::new_unique();
+    let reserve_x = Pubkey::new_unique();
+    let reserve_y = Pubkey::new_unique();
+    let mut data = vec![0u8; meteora_dlmm::LB_PAIR_LEN];
+    data[..8].copy_from_slice(&meteora_dlmm::LB_PAIR_DISCRIMINATOR);
+    data[8..10].copy_from_slice(&10_000u16.to_le_bytes());
+    data[76..80].copy_from_slice(&(-1234i32).to_le_bytes());
+    data[80..82].copy_from_slice(&25u16.to_le_bytes());
+    data[86] = meteora_dlmm::LbPairState::ACTIVATION_TIMESTAMP;
+    data[88..120].copy_from_slice(mint_x.as_ref());
+    data[120..152].copy_from_slice(mint_y.as_ref());
+    data[152..184].copy_from_slice(reserve_x.as_ref());
+    data[184..216].copy_from_slice(reserve_y.as_ref());
+    data[816..824].copy_from_slice(&1_700_000_000u64.to_le_bytes());
+    let info = meteora_dlmm::try_decode(program, account, &data).expect("decode");
+    assert_eq!(info.dex, DexKind::MeteoraDlmm);
+    assert_eq!(info.base_mint, Some(mint_x));
+    assert_eq!(info.quote_mint, Some(mint_y));
+    assert_eq!(info.base_vault, Some(reserve_x));
+    assert_eq!(info.quote_vault, Some(reserve_y));
+    // base_factor 10_000 * bin_step 25 -> 0.25%
+    assert_eq!(info.fee, Some(FeeRate::from_bps(25)));
+    assert_eq!(info.open_at, Some(OpenAt::Timestamp(1_700_000_000)));
+    assert!(!info.is_tradeable(1_699_999_999, u64::MAX));
+    assert!(info.is_tradeable(1_700_000_000, 0));
+    let Some(DexState::MeteoraDlmm(pair)) = info.state else { panic!("missing lb pair state") };
+    assert_eq!(pair.active_id, -1234);
+    assert_eq!(pair.bin_step, 25);
+    assert_eq!(pair.activation_point, 1_700_000_000);
+    assert!(pair.swap_enabled());
+}
+
+#[test]
+fn test_meteora_damm_v2_decode() {
+    let program = Pubkey::new_unique();
+    let account = Pubkey::new_unique();
+    let mint_a = Pubkey::new_unique();
+    let mint_b = Pubkey::new_unique();
+    let vault_a = Pubkey::new_unique();
+    let mut data = vec![0u8; meteora_damm::V2_POOL_LEN];
+    data[..8].copy_from_slice(&meteora_damm::POOL_DISCRIMINATOR);
+    data[8..16].copy_from_slice(&2_500_000u64.to_le_bytes());
+    data[168..200].copy_from_slice(mint_a.as_ref());
+    data[200..232].copy_from_slice(mint_b.as_ref());
+    data[232..264].copy_from_slice(vault_a.as_ref());
+    data[456..472].copy_from_slice(&(1u128 << 64).to_le_bytes());
+    data[472..480].copy_from_slice(&300_000_000u64.to_le_bytes());
+    let info = meteora_damm::try_decode(program, account, &data).expect("decode");
+    assert_eq!(info.dex, DexKind::MeteoraDamm);
+    assert_eq!(info.base_mint, Some(mint_a));
+    assert_eq!(info.quote_mint, Some(mint_b));
+    assert_eq!(info.base_vault, Some(vault_a));
+    assert_eq!(info.sqrt_price_x64, Some(1u128 << 64));
+    assert_eq!(info.fee, Some(FeeRate::from_bps(25)));
+    // activation type 0 counts in slots
+    assert_eq!((info.swap_enabled, info.open_at), (Some(true), Some(OpenAt::Slot(300_000_000))));
+    let Some(DexState::MeteoraDammV2(pool)) = info.state else { panic!("missing damm v2 state") };
+    assert_eq!(pool.activation_point, 300_000_000);
+    assert!(pool.swap_enabled());
+
+    // the cp-amm program is routed to this decoder whatever kind it is configured with
+    let info = DecoderRegistry::builtin()
+        .decode(DexKind::Other, meteora_damm::V2_PROGRAM_ID, account, &data)
+        .expect("decode by program");
+    assert_eq!(info.dex, DexKind::MeteoraDamm);
+}
+
+#[test]
+fn test_meteora_damm_v1_decode() {
+    let program = Pubkey::new_unique();
+    let account = Pubkey::new_unique();
+    let lp_mint = Pubkey::new_unique();
+    let mint_a = Pubkey::new_unique();
+    let mint_b = Pubkey::new_unique();
+    let a_vault = Pubkey::new_unique();
+    let mut data = vec![0u8; 944];
+    data[..8].copy_from_slice(&meteora_damm::POOL_DISCRIMINATOR);
+    data[8..40].copy_from_slice(lp_mint.as_ref());
+    data[40..72].copy_from_slice(mint_a.as_ref());
+    data[72..104].copy_from_slice(mint_b.as_ref());
+    data[104..136].copy_from_slice(a_vault.as_ref());
+    data[233] = 1;
+    data[330..338].copy_from_slice(&25u64.to_le_bytes());
+    data[338..346].copy_from_slice(&10_000u64.to_le_bytes());
+    data[403..411].copy_from_slice(&1_700_000_000u64.to_le_bytes());
+    data[475] = 1;
+    let info = meteora_damm::try_decode(program, account, &data).expect("decode");
+    assert_eq!(info.base_mint, Some(mint_a));
+    assert_eq!(info.lp_mint, Some(lp_mint));
+    assert_eq!(info.base_vault, None);
+    assert_eq!(info.fee, Some(FeeRate::from_bps(25)));
+    assert_eq!((info.swap_enabled, info.open_at), (Some(true), Some(OpenAt::Timestamp(1_700_000_000))));
+    let Some(DexState::MeteoraDammV1(pool)) = info.state else { panic!("missing damm v1 state") };
+    assert_eq!(pool.a_vault, a_vault);
+    assert!(pool.enabled);
+    assert_eq!((pool.activation_type, pool.activation_point), (1, 1_700_000_000));
+
+    data[0] = 0;
+    assert!(meteora_damm::try_decode(program, account, &data).is_none());
+}
+
+#[test]
+fn test_pump_fun_bonding_curve_decode() {
+    let account = Pubkey::new_unique();
+    let creator = Pubkey::new_unique();
+    let mut data = vec![0u8; 81];
+    data[..8].copy_from_slice(&pump_fun::BONDING_CURVE_DISCRIMINATOR);
+    data[8..16].copy_from_slice(&1_073_000_000_000_000u64.to_le_bytes());
+    data[16..24].copy_from_slice(&30_000_000_000u64.to_le_bytes());
+    data[24..32].copy_from_slice(&793_100_000_000_000u64.to_le_bytes());
+    data[40..48].copy_from_slice(&1_000_000_000_000_000u64.to_le_bytes());
+    data[49..81].copy_from_slice(creator.as_ref());
+    let info = pump_fun::try_decode(pump_fun::PROGRAM_ID, account, &data).expect("decode");
+    assert_eq!(info.dex, DexKind::PumpFun);
+    assert_eq!(info.base_mint, None);
+    assert_eq!(info.quote_mint, Some(pump_fun::WSOL_MINT));
+    assert!(!pump_fun::is_complete(&info));
+    let Some(DexState::PumpFunCurve(curve)) = &info.state else { panic!("missing curve state") };
+    assert_eq!(curve.real_token_reserves, 793_100_000_000_000);
+    assert_eq!(curve.creator, Some(creator));
+
+    // legacy curves end right after the complete flag
+    data.truncate(pump_fun::BONDING_CURVE_MIN_LEN);
+    data[48] = 1;
+    let info = pump_fun::try_decode(pump_fun::PROGRAM_ID, account, &data).expect("decode legacy");
+    assert!(pump_fun::is_complete(&info));
+    let Some(DexState::PumpFunCurve(curve)) = info.state else { panic!("missing curve state") };
+    assert_eq!(curve.creator, None);
+}
+
+#[test]
+fn test_pump_fun_bonding_curve_address() {
+    let mint = Pubkey::new_unique();
+    let curve = pump_fun::bonding_curve_address(&mint);
+    assert_eq!(curve, pump_fun::bonding_curve_address(&mint));
+    assert_ne!(curve, pump_fun::bonding_curve_address(&Pubkey::new_unique()));
+    assert!(!curve.is_on_curve());
+}
+
+#[test]
+fn test_pump_swap_decode() {
+    let account = Pubkey::new_unique();
+    let creator = Pubkey::new_unique();
+    let base = Pubkey::new_unique();
+    let lp_mint = Pubkey::new_unique();
+    let base_vault = Pubkey::new_unique();
+    let quote_vault = Pubkey::new_unique();
+    let mut data = vec![0u8; 243];
+    data[..8].copy_from_slice(&pump_swap::POOL_DISCRIMINATOR);
+    data[9..11].copy_from_slice(&3u16.to_le_bytes());
+    data[11..43].copy_from_slice(creator.as_ref());
+    data[43..75].copy_from_slice(base.as_ref());
+    data[75..107].copy_from_slice(pump_fun::WSOL_MINT.as_ref());
+    data[107..139].copy_from_slice(lp_mint.as_ref());
+    data[139..171].copy_from_slice(base_vault.as_ref());
+    data[171..203].copy_from_slice(quote_vault.as_ref());
+    data[203..211].copy_from_slice(&4_193_388_198_815u64.to_le_bytes());
+    let info = decoders::decode_pool(
+        DexKind::PumpSwap,
+        pump_swap::PROGRAM_ID,
+        account,
+        &data,
+    )
+    .expect("decode");
+    assert_eq!(info.dex, DexKind::PumpSwap);
+    assert_eq!(info.base_mint, Some(base));
+    assert_eq!(info.quote_mint, Some(pump_fun::WSOL_MINT));
+    assert_eq!(info.lp_mint, Some(lp_mint));
+    assert_eq!((info.base_vault, info.quote_vault), (Some(base_vault), Some(quote_vault)));
+    let Some(DexState::PumpSwap(pool)) = &info.state else { panic!("missing pump swap state") };
+    assert_eq!((pool.index, pool.creator), (3, creator));
+    assert_eq!(pool.coin_creator, None);
+
+    assert!(pump_swap::try_decode(pump_swap::PROGRAM_ID, account, &data[..200]).is_none());
+}
+
+struct FixedDecoder { program: Pubkey, mint: Pubkey }
+
+impl PoolDecoder for FixedDecoder {
+    fn kind(&self) -> DexKind { DexKind::Other }
+    fn program_ids(&self) -> Vec<Pubkey> { vec![self.program] }
+    fn matches(&self, data: &[u8]) -> bool { data.starts_with(b"fixed") }
+    fn decode(&self, program: Pubkey, account: Pubkey, _data: &[u8]) -> DecodeOutcome {
+        DecodeOutcome::Pool(Box::new(PoolInfo {
+            dex: DexKind::Other,
+            id: PoolId { program, account },
+            base_mint: Some(self.mint),
+            state: Some(DexState::Other(Box::new(serde_json::json!({ "custom": true })))),
+            ..Default::default()
+        }))
+    }
+}
+
+#[test]
+fn test_registry_custom_decoder() {
+    let program = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let mut registry = DecoderRegistry::builtin();
+    registry.register(Arc::new(FixedDecoder { program, mint }));
+
+    let info = registry
+        .decode(DexKind::Other, program, Pubkey::new_unique(), b"fixed pool")
+        .expect("decode");
+    assert_eq!(info.dex, DexKind::Other);
+    assert_eq!(info.base_mint, Some(mint));
+    assert!(matches!(info.state, Some(DexState::Other(_))));
+    assert!(registry.decode(DexKind::Other, program, Pubkey::new_unique(), b"other").is_none());
+
+    // unknown programs fall back to the configured kind
+    let data = whirlpool_account(Pubkey::new_unique(), mint, 64, 3000);
+    let info = registry
+        .decode(DexKind::OrcaWhirlpools, Pubkey::new_unique(), Pubkey::new_unique(), &data)
+        .expect("decode by kind");
+    assert_eq!(info.dex, DexKind::OrcaWhirlpools);
+    assert!(DecoderRegistry::new()
+        .decode(DexKind::OrcaWhirlpools, Pubkey::new_unique(), Pubkey::new_unique(), &data)
+        .is_none());
+}
+
+#[test]
+fn test_decode_routes_on_discriminator() {
+    let program = Pubkey::new_unique();
+    let registry = DecoderRegistry::builtin();
+    let decode = |kind, data: &[u8]| registry.decode_account(kind, program, Pubkey::new_unique(), data);
+
+    // a Whirlpool Position is large enough to pass the old length check
+    let mut position = vec![0u8; 216];
+    position[..8].copy_from_slice(&orca_whirl::ACCOUNT_TYPES[6].0);
+    assert!(matches!(
+        decode(DexKind::OrcaWhirlpools, &position),
+        DecodeOutcome::Position(_)
+    ));
+    let mut bundle = vec![0u8; 216];
+    bundle[..8].copy_from_slice(&orca_whirl::ACCOUNT_TYPES[7].0);
+    assert!(matches!(
+        decode(DexKind::OrcaWhirlpools, &bundle),
+        DecodeOutcome::Other { account_type: "PositionBundle" }
+    ));
+
+    let mut truncated = whirlpool_account(Pubkey::new_unique(), Pubkey::new_unique(), 64, 3000);
+    truncated.truncate(300);
+    assert!(matches!(
+        decode(DexKind::OrcaWhirlpools, &truncated),
+        DecodeOutcome::Invalid { account_type: "Whirlpool" }
+    ));
+
+    let mut unknown = vec![0u8; orca_whirl::WHIRLPOOL_LEN];
+    unknown[..8].copy_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+    assert!(matches!(
+        decode(DexKind::OrcaWhirlpools, &unknown),
+        DecodeOutcome::Unknown { discriminator: Some([1, 2, 3, 4, 5, 6, 7, 8]) }
+    ));
+    assert!(orca_whirl::try_decode(program, Pubkey::new_unique(), &unknown).is_none());
+
+    // a CLMM tick array is not mistaken for a pool
+    let mut tick_array = vec![0u8; 10240];
+    tick_array[..8].copy_from_slice(&raydium_clmm::ACCOUNT_TYPES[2].0);
+    assert!(matches!(decode(DexKind::RaydiumClmm, &tick_array), DecodeOutcome::TickArray(_)));
 }
@@ -0,0 +1,213 @@
+mod common;
+
+use std::sync::{
+    atomic::{AtomicU64, AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+
+use common::{
+    serve_pubsub, serve_rpc, ui_account, watcher_config, whirlpool_data, whirlpool_program, NoToken2022, RpcHandler,
+};
+use pool_watcher::{
+    EndpointConfig, HealthConfig, PoolBus, PoolEvent, PoolWatcher, PoolWatcherConfig, PoolWatcherHandle,
+    ReconnectConfig, SnapshotConfig, SubscriptionState,
+};
+use serde_json::{json, Value};
+use solana_sdk::pubkey::Pubkey;
+use tokio::{
+    net::TcpListener,
+    sync::broadcast,
+    time::{sleep, timeout, Duration},
+};
+
+/// RPC server for an endpoint whose slot is fine but whose
+/// `getProgramAccounts` answers are garbage.
+fn broken_snapshots() -> RpcHandler {
+    Arc::new(|request| match request["method"].as_str() {
+        Some("getProgramAccounts") => json!("not accounts"),
+        _ => json!(100),
+    })
+}
+
+/// RPC server whose snapshot holds `pool`.
+fn snapshot_of(pool: Pubkey) -> RpcHandler {
+    Arc::new(move |request| match request["method"].as_str() {
+        Some("getProgramAccounts") => json!({
+            "context": { "slot": 100 },
+            "value": [{ "pubkey": pool.to_string(), "account": ui_account(&whirlpool_data(1_000)) }],
+        }),
+        Some("getMultipleAccounts") => {
+            let keys = request["params"][0].as_array().map_or(0, Vec::len);
+            json!({ "context": { "slot": 100 }, "value": vec![Value::Null; keys] })
+        }
+        _ => json!(100),
+    })
+}
+
+/// RPC server whose snapshot holds `pool`, at the slot held in `slot`.
+/// Account reads are counted in `reads`.
+fn at_slot(pool: Pubkey, slot: Arc<AtomicU64>, reads: Arc<AtomicUsize>) -> RpcHandler {
+    let snapshot = snapshot_of(pool);
+    Arc::new(move |request| match request["method"].as_str() {
+        Some("getSlot") => json!(slot.load(Ordering::Relaxed)),
+        Some("getMultipleAccounts") => {
+            reads.fetch_add(1, Ordering::Relaxed);
+            snapshot(request)
+        }
+        _ => snapshot(request),
+    })
+}
+
+/// Endpoint with its RPC served by `handler` and, with `ws`, a pubsub
+/// server that accepts subscriptions. Without it the websocket is refused.
+async fn endpoint(handler: RpcHandler, ws: bool, weight: u32) -> EndpointConfig {
+    let rpc = TcpListener::bind("127.0.0.1:0").await.expect("bind rpc");
+    let rpc_url = format!("http://{}", rpc.local_addr().expect("rpc addr"));
+    tokio::spawn(serve_rpc(rpc, handler));
+    let ws_url = match ws {
+        true => {
+            let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind ws");
+            let url = format!("ws://{}", listener.local_addr().expect("ws addr"));
+            tokio::spawn(serve_pubsub(listener, Arc::new(Mutex::new(Vec::new()))));
+            url
+        }
+        false => "ws://127.0.0.1:1".into(),
+    };
+    EndpointConfig { rpc_url, ws_url, weight }
+}
+
+fn failover_config(endpoints: Vec<EndpointConfig>, snapshot: bool) -> PoolWatcherConfig {
+    PoolWatcherConfig {
+        endpoints,
+        reconnect: ReconnectConfig { initial_ms: 50, max_ms: 200 },
+        snapshot: SnapshotConfig { shard_prefix_bytes: 0, concurrency: 1, ..SnapshotConfig::default() },
+        // only the initial probe runs during the test
+        health: HealthConfig { probe_interval_ms: 600_000, ..HealthConfig::default() },
+        ..watcher_config(vec![whirlpool_program(snapshot)])
+    }
+}
+
+async fn wait_connected(handle: &PoolWatcherHandle) -> String {
+    timeout(Duration::from_secs(10), async {
+        loop {
+            if let SubscriptionState::Connected { endpoint, .. } = &handle.status().programs[0].subscription {
+                return endpoint.clone();
+            }
+            sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("subscription connected")
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_snapshot_fails_over_to_next_endpoint() {
+    let pool = Pubkey::new_unique();
+    let primary = endpoint(broken_snapshots(), true, 10).await;
+    let backup = endpoint(snapshot_of(pool), true, 1).await;
+    let bus = Arc::new(PoolBus::new(64));
+    let mut rx = bus.subscribe();
+    let cfg = failover_config(vec![primary.clone(), backup.clone()], true);
+    let handle = PoolWatcher::new(cfg, bus, Arc::new(NoToken2022)).spawn();
+
+    let count = timeout(Duration::from_secs(10), async {
+        loop {
+            match rx.recv().await {
+                Ok(PoolEvent::SnapshotFinished { count, .. }) => return count,
+                Err(broadcast::error::RecvError::Closed) => panic!("bus closed"),
+                _ => {}
+            }
+        }
+    })
+    .await
+    .expect("snapshot");
+    assert_eq!(count, 1);
+
+    // a bad answer is not a dead endpoint, so the subscription stays
+    let endpoints = handle.status().endpoints;
+    assert!(endpoints[0].healthy && endpoints[0].active);
+    assert!(endpoints[1].healthy);
+    assert_eq!(wait_connected(&handle).await, primary.ws_url);
+    handle.shutdown();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_refused_endpoint_is_marked_unhealthy() {
+    let pool = Pubkey::new_unique();
+    let backup = endpoint(snapshot_of(pool), true, 1).await;
+    // nothing listens on the primary's port once the listener is dropped
+    let closed = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+    let primary = EndpointConfig {
+        rpc_url: format!("http://{}", closed.local_addr().expect("addr")),
+        ws_url: "ws://127.0.0.1:1".into(),
+        weight: 10,
+    };
+    drop(closed);
+    let bus = Arc::new(PoolBus::new(64));
+    let mut rx = bus.subscribe();
+    let cfg = failover_config(vec![primary, backup.clone()], true);
+    let handle = PoolWatcher::new(cfg, bus, Arc::new(NoToken2022)).spawn();
+
+    timeout(Duration::from_secs(10), async {
+        while !matches!(rx.recv().await, Ok(PoolEvent::SnapshotFinished { .. })) {}
+    })
+    .await
+    .expect("snapshot");
+    let endpoints = handle.status().endpoints;
+    assert!(!endpoints[0].healthy && endpoints[0].last_error.is_some());
+    assert!(endpoints[1].healthy && endpoints[1].active);
+    assert_eq!(wait_connected(&handle).await, backup.ws_url);
+    handle.shutdown();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_subscription_fails_over_when_websocket_is_down() {
+    let primary = endpoint(snapshot_of(Pubkey::new_unique()), false, 10).await;
+    let backup = endpoint(snapshot_of(Pubkey::new_unique()), true, 1).await;
+    let cfg = failover_config(vec![primary, backup.clone()], false);
+    let handle = PoolWatcher::new(cfg, Arc::new(PoolBus::new(64)), Arc::new(NoToken2022)).spawn();
+
+    assert_eq!(wait_connected(&handle).await, backup.ws_url);
+    let status = handle.status();
+    // its RPC answered the probe, the websocket failed afterwards
+    assert!(status.endpoints[0].latency_ms.is_some() && !status.endpoints[0].healthy);
+    assert!(status.endpoints[1].active);
+    handle.shutdown();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_subscription_moves_off_lagging_endpoint() {
+    let pool = Pubkey::new_unique();
+    let (backup_slot, backup_reads) = (Arc::new(AtomicU64::new(100)), Arc::new(AtomicUsize::new(0)));
+    let primary = endpoint(snapshot_of(pool), true, 10).await;
+    let backup = endpoint(at_slot(pool, backup_slot.clone(), backup_reads.clone()), true, 1).await;
+    let cfg = PoolWatcherConfig {
+        health: HealthConfig { probe_interval_ms: 100, ..HealthConfig::default() },
+        ..failover_config(vec![primary.clone(), backup.clone()], true)
+    };
+    let handle = PoolWatcher::new(cfg, Arc::new(PoolBus::new(64)), Arc::new(NoToken2022)).spawn();
+    assert_eq!(wait_connected(&handle).await, primary.ws_url);
+
+    // the primary stays at slot 100 while the backup moves past `max_slot_lag`
+    backup_slot.store(1_000, Ordering::Relaxed);
+    timeout(Duration::from_secs(10), async {
+        while wait_connected(&handle).await != backup.ws_url {
+            sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("resubscribed to the backup");
+    let status = handle.status();
+    assert!(!status.endpoints[0].healthy && status.endpoints[1].active);
+    assert_eq!(status.endpoints[0].last_error.as_deref(), Some("900 slots behind"));
+
+    // the pools are refetched from the backup once resubscribed
+    timeout(Duration::from_secs(10), async {
+        while backup_reads.load(Ordering::Relaxed) == 0 {
+            sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("backfilled");
+    handle.shutdown();
+}
@@ -0,0 +1,109 @@
+use pool_watcher::decoders::{meteora_damm, orca_whirl, PoolLayout};
+use pool_watcher::filters::{program_layouts, snapshot_filters, snapshot_shards, subscription_filters, AccountFilter};
+use pool_watcher::{DecoderRegistry, DexKind, ProgramConfig};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_sdk::pubkey::Pubkey;
+
+fn program(kind: DexKind, id: Pubkey) -> ProgramConfig {
+    ProgramConfig { kind, id, snapshot: true, idl: None, filters: None, mints: Vec::new() }
+}
+
+fn memcmp(offset: usize, bytes: &[u8]) -> RpcFilterType {
+    RpcFilterType::Memcmp(Memcmp::new_base58_encoded(offset, bytes))
+}
+
+#[test]
+fn test_snapshot_filters_default_to_pool_layout() {
+    let whirlpools = program(DexKind::OrcaWhirlpools, orca_whirl::PROGRAM_ID);
+    let layouts = program_layouts(&DecoderRegistry::builtin(), &whirlpools);
+    assert_eq!(
+        snapshot_filters(&whirlpools, &layouts),
+        vec![vec![
+            RpcFilterType::DataSize(orca_whirl::WHIRLPOOL_LEN as u64),
+            memcmp(0, &orca_whirl::WHIRLPOOL_DISCRIMINATOR),
+        ]]
+    );
+    // the subscription keeps carrying configs, tick arrays and positions
+    assert_eq!(subscription_filters(&whirlpools, &layouts), vec![Vec::<RpcFilterType>::new()]);
+
+    // a program without layouts is fetched whole
+    assert_eq!(snapshot_filters(&whirlpools, &[]), vec![Vec::<RpcFilterType>::new()]);
+}
+
+#[test]
+fn test_mint_filters_cover_both_sides() {
+    let mint = Pubkey::new_unique();
+    let mut whirlpools = program(DexKind::OrcaWhirlpools, orca_whirl::PROGRAM_ID);
+    whirlpools.mints = vec![mint];
+    let layouts = program_layouts(&DecoderRegistry::builtin(), &whirlpools);
+    let sets = snapshot_filters(&whirlpools, &layouts);
+    assert_eq!(sets.len(), 2);
+    assert!(sets[0].contains(&memcmp(101, mint.as_ref())));
+    assert!(sets[1].contains(&memcmp(181, mint.as_ref())));
+    assert!(sets.iter().all(|set| set.contains(&memcmp(0, &orca_whirl::WHIRLPOOL_DISCRIMINATOR))));
+    assert_eq!(subscription_filters(&whirlpools, &layouts), sets);
+
+    // layouts without mints are only filtered on their discriminator
+    let curve = PoolLayout { discriminator: Some([1; 8]), data_size: None, mint_offsets: None };
+    assert_eq!(snapshot_filters(&whirlpools, &[curve]), vec![vec![memcmp(0, &[1; 8])]]);
+}
+
+#[test]
+fn test_overlapping_layouts_fetch_once() {
+    let damm = program(DexKind::MeteoraDamm, meteora_damm::V1_PROGRAM_ID);
+    let layouts = program_layouts(&DecoderRegistry::builtin(), &damm);
+    assert_eq!(layouts.len(), 2);
+    // v2 pools already match the discriminator-only v1 request
+    assert_eq!(snapshot_filters(&damm, &layouts), vec![vec![memcmp(0, &meteora_damm::POOL_DISCRIMINATOR)]]);
+}
+
+#[test]
+fn test_program_config_filters() {
+    let mint = Pubkey::new_unique();
+    let program: ProgramConfig = toml::from_str(&format!(
+        r#"
+        kind = "RaydiumAmmV4"
+        id = "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8"
+        filters = [{{ data_size = 752 }}, {{ memcmp = {{ offset = 8, bytes = "{mint}" }} }}]
+        mints = ["{mint}"]
+        "#
+    ))
+    .expect("program config");
+    assert_eq!(program.mints, vec![mint]);
+    let filters = program.filters.clone().expect("filters");
+    assert_eq!(filters[0], AccountFilter::DataSize(752));
+    assert_eq!(filters[1], AccountFilter::Memcmp { offset: 8, bytes: mint.to_string() });
+
+    // configured filters replace the layout ones and still take the mints
+    let layouts = program_layouts(&DecoderRegistry::builtin(), &program);
+    let sets = snapshot_filters(&program, &layouts);
+    assert_eq!(sets.len(), 2);
+    assert_eq!(sets[0][..2], [RpcFilterType::DataSize(752), memcmp(8, mint.as_ref())]);
+    assert_eq!(sets[1][2], memcmp(432, mint.as_ref()));
+}
+
+#[test]
+fn test_snapshot_shards_partition_by_mint_prefix() {
+    let whirlpools = program(DexKind::OrcaWhirlpools, orca_whirl::PROGRAM_ID);
+    let layouts = program_layouts(&DecoderRegistry::builtin(), &whirlpools);
+    let base = snapshot_filters(&whirlpools, &layouts).remove(0);
+    assert_eq!(snapshot_shards(&whirlpools, &layouts, 0), vec![base.clone()]);
+
+    let shards = snapshot_shards(&whirlpools, &layouts, 1);
+    assert_eq!(shards.len(), 256);
+    for (byte, shard) in shards.iter().enumerate() {
+        assert_eq!(shard[..2], base[..]);
+        assert_eq!(shard[2], memcmp(101, &[byte as u8]));
+    }
+    // wider prefixes are capped
+    assert_eq!(snapshot_shards(&whirlpools, &layouts, 9).len(), 1 << 16);
+    assert_eq!(snapshot_shards(&whirlpools, &layouts, 2)[258][2], memcmp(101, &[1, 2]));
+
+    // mints already narrow the requests, and configured filters stay as given
+    let mut narrowed = whirlpools.clone();
+    narrowed.mints = vec![Pubkey::new_unique()];
+    assert_eq!(snapshot_shards(&narrowed, &layouts, 1), snapshot_filters(&narrowed, &layouts));
+    let mut filtered = whirlpools.clone();
+    filtered.filters = Some(vec![AccountFilter::DataSize(653)]);
+    assert_eq!(snapshot_shards(&filtered, &layouts, 1), vec![vec![RpcFilterType::DataSize(653)]]);
+}
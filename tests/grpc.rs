@@ -0,0 +1,383 @@
+mod common;
+
+use std::{
+    convert::Infallible,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+};
+
+use common::{serve_rpc, watcher_config, whirlpool_data, whirlpool_program, NoToken2022};
+use futures::{future::BoxFuture, stream, StreamExt};
+use pool_watcher::decoders::{orca_whirl, pump_fun};
+use pool_watcher::filters::AccountFilter;
+use pool_watcher::source::geyser::{
+    subscribe_request_filter_accounts_filter::Filter, subscribe_request_filter_accounts_filter_memcmp::Data,
+    subscribe_update::UpdateOneof, GetSlotRequest, GetSlotResponse, SubscribeRequest, SubscribeUpdate,
+    SubscribeUpdateAccount, SubscribeUpdateAccountInfo, SubscribeUpdatePing, SubscribeUpdateTransaction,
+    SubscribeUpdateTransactionInfo, GET_SLOT_PATH, SUBSCRIBE_PATH,
+};
+use pool_watcher::{
+    AccountSource, DexKind, GrpcConfig, GrpcSource, PoolBus, PoolEvent, PoolWatcher, PoolWatcherConfig, ProgramConfig, SubscriptionState,
+};
+use serde_json::json;
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use tokio::{
+    net::TcpListener,
+    sync::oneshot,
+    time::{sleep, timeout, Duration},
+};
+use tonic::{
+    body::BoxBody,
+    codec::{ProstCodec, Streaming},
+    codegen::{http, Service},
+    server::{Grpc, NamedService},
+    transport::{server::TcpIncoming, Server},
+    Request, Response, Status,
+};
+
+type Updates = stream::BoxStream<'static, Result<SubscribeUpdate, Status>>;
+
+/// Geyser server recording the Subscribe streams it serves and the requests
+/// sent on them. Once a request holds the account and transaction filters of
+/// the Whirlpool program, it sends `transactions` and `accounts` as matching
+/// them, then keeps the stream open.
+#[derive(Clone)]
+struct MockGeyser {
+    streams: Arc<AtomicUsize>,
+    requests: Arc<Mutex<Vec<SubscribeRequest>>>,
+    accounts: Vec<UpdateOneof>,
+    transactions: Vec<UpdateOneof>,
+}
+
+impl NamedService for MockGeyser {
+    const NAME: &'static str = "geyser.Geyser";
+}
+
+impl Service<http::Request<BoxBody>> for MockGeyser {
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<'static, Result<Self::Response, Infallible>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<BoxBody>) -> Self::Future {
+        let geyser = self.clone();
+        Box::pin(async move {
+            Ok(match req.uri().path() {
+                SUBSCRIBE_PATH => Grpc::new(ProstCodec::default()).streaming(Subscribe(geyser), req).await,
+                GET_SLOT_PATH => Grpc::new(ProstCodec::default()).unary(GetSlot, req).await,
+                _ => Status::unimplemented("").into_http(),
+            })
+        })
+    }
+}
+
+struct Subscribe(MockGeyser);
+
+impl Service<Request<Streaming<SubscribeRequest>>> for Subscribe {
+    type Response = Response<Updates>;
+    type Error = Status;
+    type Future = BoxFuture<'static, Result<Self::Response, Status>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Status>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request<Streaming<SubscribeRequest>>) -> Self::Future {
+        let geyser = self.0.clone();
+        Box::pin(async move {
+            geyser.streams.fetch_add(1, Ordering::Relaxed);
+            let mut requests = req.into_inner();
+            let (names_tx, names_rx) = oneshot::channel();
+            let recorded = geyser.requests.clone();
+            tokio::spawn(async move {
+                let mut names_tx = Some(names_tx);
+                while let Ok(Some(request)) = requests.message().await {
+                    if let Some(names) = whirlpool_filters(&request) {
+                        if let Some(tx) = names_tx.take() {
+                            let _ = tx.send(names);
+                        }
+                    }
+                    recorded.lock().unwrap().push(request);
+                }
+            });
+            let ping = UpdateOneof::Ping(SubscribeUpdatePing {});
+            let ping = SubscribeUpdate { filters: Vec::new(), update_oneof: Some(ping) };
+            let scripted = stream::once(names_rx).flat_map(move |names| {
+                let (accounts, transactions) = names.unwrap_or_default();
+                let matched = |filters: Vec<String>, script: Vec<UpdateOneof>| {
+                    script
+                        .into_iter()
+                        .map(move |update| SubscribeUpdate { filters: filters.clone(), update_oneof: Some(update) })
+                };
+                let transactions = matched(transactions, geyser.transactions.clone());
+                stream::iter(transactions.chain(matched(accounts, geyser.accounts.clone())))
+            });
+            let updates = stream::once(async { ping }).chain(scripted).map(Ok).chain(stream::pending());
+            Ok(Response::new(updates.boxed()))
+        })
+    }
+}
+
+/// Names of the account and transaction filters `request` holds for the
+/// Whirlpool program, once it holds both.
+fn whirlpool_filters(request: &SubscribeRequest) -> Option<(Vec<String>, Vec<String>)> {
+    let program = orca_whirl::PROGRAM_ID.to_string();
+    let accounts: Vec<String> = request
+        .accounts
+        .iter()
+        .filter(|(_, filter)| filter.owner.contains(&program))
+        .map(|(name, _)| name.clone())
+        .collect();
+    let transactions: Vec<String> = request
+        .transactions
+        .iter()
+        .filter(|(_, filter)| filter.account_include.contains(&program))
+        .map(|(name, _)| name.clone())
+        .collect();
+    (!accounts.is_empty() && !transactions.is_empty()).then_some((accounts, transactions))
+}
+
+struct GetSlot;
+
+impl Service<Request<GetSlotRequest>> for GetSlot {
+    type Response = Response<GetSlotResponse>;
+    type Error = Status;
+    type Future = BoxFuture<'static, Result<Self::Response, Status>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Status>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _req: Request<GetSlotRequest>) -> Self::Future {
+        Box::pin(async { Ok(Response::new(GetSlotResponse { slot: 500 })) })
+    }
+}
+
+/// Serve `geyser` on a local port, returning its URL.
+async fn serve_geyser(geyser: MockGeyser) -> String {
+    let grpc = TcpListener::bind("127.0.0.1:0").await.expect("bind grpc");
+    let url = format!("http://{}", grpc.local_addr().expect("grpc addr"));
+    let incoming = TcpIncoming::from_listener(grpc, true, None).expect("incoming");
+    tokio::spawn(Server::builder().add_service(geyser).serve_with_incoming(incoming));
+    url
+}
+
+fn account(key: Pubkey, liquidity: Option<u128>, slot: u64, write_version: u64) -> UpdateOneof {
+    let (lamports, owner, data) = match liquidity {
+        Some(liquidity) => (1_000_000, orca_whirl::PROGRAM_ID, whirlpool_data(liquidity)),
+        None => (0, Pubkey::default(), Vec::new()),
+    };
+    UpdateOneof::Account(SubscribeUpdateAccount {
+        account: Some(SubscribeUpdateAccountInfo {
+            pubkey: key.to_bytes().to_vec(),
+            lamports,
+            owner: owner.to_bytes().to_vec(),
+            data,
+            write_version,
+            ..Default::default()
+        }),
+        slot,
+        is_startup: false,
+    })
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_grpc_source_orders_by_slot_and_write_version() {
+    let pool = Pubkey::new_unique();
+    let signature = Signature::from([7u8; 64]);
+    let (streams, requests) = (Arc::new(AtomicUsize::new(0)), Arc::new(Mutex::new(Vec::new())));
+    let geyser = MockGeyser {
+        streams: streams.clone(),
+        requests: requests.clone(),
+        accounts: vec![
+            account(pool, Some(1_000), 100, 1),
+            account(pool, Some(2_000), 120, 7),
+            // an earlier write of the same slot, dropped
+            account(pool, Some(3_000), 120, 6),
+            // an earlier slot with a later write version, dropped
+            account(pool, Some(4_000), 119, 9),
+            account(pool, Some(5_000), 120, 8),
+            account(pool, None, 121, 10),
+        ],
+        transactions: vec![UpdateOneof::Transaction(SubscribeUpdateTransaction {
+            transaction: Some(SubscribeUpdateTransactionInfo {
+                signature: signature.as_ref().to_vec(),
+                is_vote: false,
+                index: 0,
+            }),
+            slot: 130,
+        })],
+    };
+    let grpc_url = serve_geyser(geyser).await;
+    let rpc = TcpListener::bind("127.0.0.1:0").await.expect("bind rpc");
+    let rpc_url = format!("http://{}", rpc.local_addr().expect("rpc addr"));
+    tokio::spawn(serve_rpc(
+        rpc,
+        Arc::new(|request| match request["method"].as_str() {
+            Some("getMultipleAccounts") => json!({ "context": { "slot": 1 }, "value": [null] }),
+            _ => json!(1),
+        }),
+    ));
+
+    let discriminator = bs58::encode(orca_whirl::WHIRLPOOL_DISCRIMINATOR).into_string();
+    let program = ProgramConfig {
+        filters: Some(vec![
+            AccountFilter::DataSize(orca_whirl::WHIRLPOOL_LEN as u64),
+            AccountFilter::Memcmp { offset: 0, bytes: discriminator },
+        ]),
+        ..whirlpool_program(false)
+    };
+    // subscribed on the same stream, never matched by the scripted updates
+    let pump_fun_program = ProgramConfig {
+        kind: DexKind::PumpFun,
+        id: pump_fun::PROGRAM_ID,
+        snapshot: false,
+        idl: None,
+        filters: None,
+        mints: Vec::new(),
+    };
+    let cfg = PoolWatcherConfig {
+        rpc_url,
+        grpc: Some(GrpcConfig { url: grpc_url.clone(), x_token: None }),
+        ..watcher_config(vec![program, pump_fun_program])
+    };
+    let bus = Arc::new(PoolBus::new(64));
+    let mut rx = bus.subscribe();
+    let watcher = PoolWatcher::new(cfg, bus, Arc::new(NoToken2022));
+    let inventory = watcher.inventory();
+    let handle = watcher.spawn();
+
+    let mut seen = Vec::new();
+    timeout(Duration::from_secs(10), async {
+        while seen.len() < 5 {
+            let event = rx.recv().await.expect("bus open");
+            let summary = match event {
+                PoolEvent::AccountNew { info, slot, .. } => ("new", info.liquidity, slot),
+                PoolEvent::AccountChanged { info, slot, .. } => ("changed", info.liquidity, slot),
+                PoolEvent::AccountDeleted { slot, .. } => ("deleted", None, slot),
+                PoolEvent::ProgramLog { signature: logged, slot, .. } => {
+                    assert_eq!(logged, signature.to_string());
+                    ("log", None, slot)
+                }
+                _ => continue,
+            };
+            seen.push(summary);
+        }
+    })
+    .await
+    .expect("events published");
+
+    let (pools, log): (Vec<_>, Vec<_>) = seen.into_iter().partition(|(kind, ..)| *kind != "log");
+    assert_eq!(log, vec![("log", None, 130)]);
+    assert_eq!(
+        pools,
+        vec![
+            ("new", Some(1_000), 100),
+            ("changed", Some(2_000), 120),
+            ("changed", Some(5_000), 120),
+            ("deleted", None, 121),
+        ]
+    );
+    assert!(inventory.program_pools(&orca_whirl::PROGRAM_ID).is_empty());
+    assert_eq!(inventory.slot(&pool), Some(121));
+
+    let status = handle.status();
+    for state in [&status.programs[0].subscription, &status.programs[0].logs] {
+        assert!(matches!(state, SubscriptionState::Connected { endpoint, .. } if *endpoint == grpc_url));
+    }
+
+    // every subscription shares one stream, which answers the server's ping
+    // and ends up holding the filters of both programs
+    let owners = |request: &SubscribeRequest| {
+        let mut owners: Vec<String> = request.accounts.values().flat_map(|f| f.owner.clone()).collect();
+        owners.extend(request.transactions.values().flat_map(|f| f.account_include.clone()));
+        owners.sort();
+        owners
+    };
+    let mut expected: Vec<String> =
+        [orca_whirl::PROGRAM_ID, pump_fun::PROGRAM_ID].repeat(2).iter().map(Pubkey::to_string).collect();
+    expected.sort();
+    let request = timeout(Duration::from_secs(10), async {
+        loop {
+            let requests = requests.lock().unwrap().clone();
+            let full = requests.iter().rev().find(|r| owners(r) == expected).cloned();
+            if let Some(request) = full.filter(|_| requests.iter().any(|r| r.ping.is_some())) {
+                return request;
+            }
+            sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("filters of both programs");
+    assert_eq!(streams.load(Ordering::Relaxed), 1);
+
+    let whirlpool = orca_whirl::PROGRAM_ID.to_string();
+    let filter = request.accounts.values().find(|f| f.owner == [whirlpool.clone()]).expect("account filter");
+    let filters: Vec<_> = filter.filters.iter().filter_map(|f| f.filter.clone()).collect();
+    assert!(filters.contains(&Filter::Datasize(orca_whirl::WHIRLPOOL_LEN as u64)));
+    assert!(filters.iter().any(|f| matches!(
+        f,
+        Filter::Memcmp(memcmp)
+            if memcmp.offset == 0 && memcmp.data == Some(Data::Bytes(orca_whirl::WHIRLPOOL_DISCRIMINATOR.to_vec()))
+    )));
+    let filter = request.transactions.values().find(|f| f.account_include == [whirlpool.clone()]);
+    let filter = filter.expect("transaction filter");
+    assert_eq!(filter.vote, Some(false));
+
+    handle.shutdown();
+    timeout(Duration::from_secs(10), async {
+        while handle.is_running() {
+            sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("watcher stopped");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_grpc_lagging_subscription_is_ended() {
+    let pool = Pubkey::new_unique();
+    let transaction = UpdateOneof::Transaction(SubscribeUpdateTransaction {
+        transaction: Some(SubscribeUpdateTransactionInfo {
+            signature: Signature::from([7u8; 64]).as_ref().to_vec(),
+            is_vote: false,
+            index: 0,
+        }),
+        slot: 90,
+    });
+    let geyser = MockGeyser {
+        streams: Arc::new(AtomicUsize::new(0)),
+        requests: Arc::new(Mutex::new(Vec::new())),
+        // more transactions than the log subscription buffers, then the pool
+        accounts: vec![account(pool, Some(1_000), 100, 1)],
+        transactions: vec![transaction; 5_000],
+    };
+    let url = serve_geyser(geyser).await;
+    let source = GrpcSource::new(&watcher_config(Vec::new()), GrpcConfig { url, x_token: None });
+
+    // the log subscription is never read while the pool arrives
+    let mut logs = source.subscribe_logs(&orca_whirl::PROGRAM_ID).await.expect("subscribe logs");
+    let mut accounts = source.subscribe_accounts(&orca_whirl::PROGRAM_ID, vec![Vec::new()]).await.expect("subscribe");
+    let update = timeout(Duration::from_secs(10), accounts.next()).await.expect("not held up").expect("update");
+    assert_eq!((update.key, update.slot), (pool, 100));
+
+    // it ends after what it buffered, for its supervisor to resubscribe
+    let received = timeout(Duration::from_secs(10), async {
+        let mut received = 0;
+        while logs.next().await.is_some() {
+            received += 1;
+        }
+        received
+    })
+    .await
+    .expect("log subscription ended");
+    assert!(received < 5_000);
+    logs.close().await;
+    accounts.close().await;
+}
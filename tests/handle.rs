@@ -0,0 +1,62 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use common::{serve_pubsub, serve_rpc, watcher_config, whirlpool_program, NoToken2022};
+use pool_watcher::decoders::orca_whirl;
+use pool_watcher::{
+    PoolBus, PoolWatcher, PoolWatcherConfig, ReconnectConfig, SnapshotState, SubscriptionState,
+};
+use serde_json::json;
+use tokio::{
+    net::TcpListener,
+    time::{sleep, timeout, Duration},
+};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_shutdown_unsubscribes_and_joins() {
+    let methods = Arc::new(Mutex::new(Vec::new()));
+    let ws = TcpListener::bind("127.0.0.1:0").await.expect("bind ws");
+    let rpc = TcpListener::bind("127.0.0.1:0").await.expect("bind rpc");
+    let cfg = PoolWatcherConfig {
+        rpc_url: format!("http://{}", rpc.local_addr().expect("rpc addr")),
+        ws_url: format!("ws://{}", ws.local_addr().expect("ws addr")),
+        reconnect: ReconnectConfig { initial_ms: 50, max_ms: 200 },
+        ..watcher_config(vec![whirlpool_program(false)])
+    };
+    tokio::spawn(serve_pubsub(ws, methods.clone()));
+    tokio::spawn(serve_rpc(rpc, Arc::new(|_| json!(200))));
+
+    let handle = PoolWatcher::new(cfg, Arc::new(PoolBus::new(64)), Arc::new(NoToken2022)).spawn();
+    timeout(Duration::from_secs(10), async {
+        while !handle.status().healthy() {
+            sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("subscriptions connected");
+    let status = handle.status();
+    assert_eq!(status.programs.len(), 1);
+    assert_eq!(status.programs[0].program, orca_whirl::PROGRAM_ID);
+    assert_eq!(status.programs[0].snapshot, SnapshotState::Disabled);
+
+    handle.shutdown();
+    let handle = timeout(Duration::from_secs(10), async move {
+        while handle.is_running() {
+            sleep(Duration::from_millis(20)).await;
+        }
+        handle
+    })
+    .await
+    .expect("watcher stopped");
+    let status = handle.status();
+    assert!(!status.running && !status.healthy());
+    assert_eq!(status.programs[0].subscription, SubscriptionState::Stopped);
+    assert_eq!(status.programs[0].logs, SubscriptionState::Stopped);
+    tokio::task::spawn_blocking(move || handle.join()).await.expect("join task").expect("watcher thread");
+
+    let methods = methods.lock().unwrap().clone();
+    for method in ["programSubscribe", "logsSubscribe", "programUnsubscribe", "logsUnsubscribe"] {
+        assert!(methods.iter().any(|m| m == method), "{method} missing from {methods:?}");
+    }
+}
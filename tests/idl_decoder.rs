@@ -0,0 +1,160 @@
+use pool_watcher::decoders::idl::{anchor_discriminator, IdlDecoder, IdlDecoderConfig};
+use pool_watcher::decoders::DecodeOutcome;
+use pool_watcher::types::{DexKind, DexState, FeeRate};
+use pool_watcher::{PoolDecoder, ProgramConfig};
+use serde_json::json;
+use solana_sdk::pubkey::Pubkey;
+
+fn idl_config(account: &str) -> IdlDecoderConfig {
+    toml::from_str(&format!(
+        r#"
+        path = "unused.json"
+        account = "{account}"
+        mint_a = "token_a_mint"
+        mint_b = "token_b_mint"
+        fee = "fees.trade_fee_rate"
+        vault_a = "token_a_vault"
+        sqrt_price = "sqrt_price"
+        tick_current = "tick_current"
+        "#
+    ))
+    .expect("config")
+}
+
+// Anchor 0.30+ layout: explicit discriminators and account layouts under `types`.
+fn pool_idl() -> serde_json::Value {
+    json!({
+        "address": "11111111111111111111111111111111",
+        "accounts": [
+            { "name": "Pool", "discriminator": [1, 2, 3, 4, 5, 6, 7, 8] },
+            { "name": "Config", "discriminator": [8, 7, 6, 5, 4, 3, 2, 1] }
+        ],
+        "types": [
+            { "name": "Fees", "type": { "kind": "struct", "fields": [
+                { "name": "trade_fee_rate", "type": "u32" },
+                { "name": "protocol_fee_rate", "type": "u32" }
+            ] } },
+            { "name": "Status", "type": { "kind": "enum", "variants": [
+                { "name": "Enabled" }, { "name": "Disabled" }
+            ] } },
+            { "name": "Pool", "type": { "kind": "struct", "fields": [
+                { "name": "bump", "type": { "array": ["u8", 1] } },
+                { "name": "token_a_mint", "type": "pubkey" },
+                { "name": "token_b_mint", "type": "pubkey" },
+                { "name": "token_a_vault", "type": "pubkey" },
+                { "name": "fees", "type": { "defined": { "name": "Fees" } } },
+                { "name": "status", "type": { "defined": { "name": "Status" } } },
+                { "name": "sqrt_price", "type": "u128" },
+                { "name": "tick_current", "type": "i32" },
+                { "name": "label", "type": "string" },
+                { "name": "admin", "type": { "option": "pubkey" } }
+            ] } }
+        ]
+    })
+}
+
+fn pool_data(mint_a: Pubkey, mint_b: Pubkey, vault_a: Pubkey) -> Vec<u8> {
+    let mut data = vec![1, 2, 3, 4, 5, 6, 7, 8, 254];
+    data.extend_from_slice(mint_a.as_ref());
+    data.extend_from_slice(mint_b.as_ref());
+    data.extend_from_slice(vault_a.as_ref());
+    data.extend_from_slice(&2500u32.to_le_bytes());
+    data.extend_from_slice(&100u32.to_le_bytes());
+    data.push(1);
+    data.extend_from_slice(&(1u128 << 64).to_le_bytes());
+    data.extend_from_slice(&(-42i32).to_le_bytes());
+    data.extend_from_slice(&4u32.to_le_bytes());
+    data.extend_from_slice(b"pool");
+    data.push(0);
+    data
+}
+
+#[test]
+fn test_idl_decoder_maps_configured_fields() {
+    let program = Pubkey::new_unique();
+    let account = Pubkey::new_unique();
+    let (mint_a, mint_b, vault_a) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let decoder = IdlDecoder::from_idl(program, DexKind::Other, &pool_idl(), idl_config("Pool")).expect("idl");
+    let data = pool_data(mint_a, mint_b, vault_a);
+
+    assert!(decoder.matches(&data));
+    let DecodeOutcome::Pool(info) = decoder.decode(program, account, &data) else { panic!("not a pool") };
+    assert_eq!(info.dex, DexKind::Other);
+    assert_eq!((info.base_mint, info.quote_mint), (Some(mint_a), Some(mint_b)));
+    assert_eq!(info.base_vault, Some(vault_a));
+    assert_eq!(info.quote_vault, None);
+    assert_eq!(info.fee, Some(FeeRate::from_ppm(2500)));
+    assert_eq!(info.sqrt_price_x64, Some(1u128 << 64));
+    assert_eq!(info.tick_current, Some(-42));
+    let Some(DexState::Other(state)) = &info.state else { panic!("missing state") };
+    assert_eq!(state["status"], "Disabled");
+    assert_eq!(state["label"], "pool");
+    assert!(state["admin"].is_null());
+
+    let config = [8, 7, 6, 5, 4, 3, 2, 1, 0];
+    assert!(matches!(decoder.decode(program, account, &config), DecodeOutcome::Other { account_type: "Config" }));
+    assert!(matches!(decoder.decode(program, account, &data[..60]), DecodeOutcome::Invalid { account_type: "Pool" }));
+}
+
+#[test]
+fn test_idl_decoder_legacy_format() {
+    let idl = json!({
+        "version": "0.1.0",
+        "name": "legacy_dex",
+        "accounts": [{ "name": "LegacyPool", "type": { "kind": "struct", "fields": [
+            { "name": "tokenAMint", "type": "publicKey" },
+            { "name": "tokenBMint", "type": "publicKey" },
+            { "name": "feeNumerator", "type": "u64" }
+        ] } }]
+    });
+    let cfg: IdlDecoderConfig = toml::from_str(
+        r#"
+        path = "unused.json"
+        account = "LegacyPool"
+        mint_a = "tokenAMint"
+        mint_b = "tokenBMint"
+        fee = "feeNumerator"
+        fee_denominator = 10000
+        "#,
+    )
+    .expect("config");
+    let decoder = IdlDecoder::from_idl(Pubkey::new_unique(), DexKind::Other, &idl, cfg).expect("idl");
+    let (mint_a, mint_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut data = anchor_discriminator("LegacyPool").to_vec();
+    data.extend_from_slice(mint_a.as_ref());
+    data.extend_from_slice(mint_b.as_ref());
+    data.extend_from_slice(&25u64.to_le_bytes());
+
+    let DecodeOutcome::Pool(info) = decoder.decode(Pubkey::new_unique(), Pubkey::new_unique(), &data) else {
+        panic!("not a pool")
+    };
+    assert_eq!(info.base_mint, Some(mint_a));
+    assert_eq!(info.fee, Some(FeeRate::from_bps(25)));
+}
+
+#[test]
+fn test_idl_decoder_rejects_unknown_fields() {
+    let mut cfg = idl_config("Pool");
+    cfg.vault_b = Some("missing_vault".into());
+    let err = IdlDecoder::from_idl(Pubkey::new_unique(), DexKind::Other, &pool_idl(), cfg)
+        .err()
+        .expect("unknown field");
+    assert!(err.to_string().contains("missing_vault"));
+    assert!(IdlDecoder::from_idl(Pubkey::new_unique(), DexKind::Other, &pool_idl(), idl_config("Missing")).is_err());
+}
+
+#[test]
+fn test_program_config_with_idl() {
+    let program: ProgramConfig = toml::from_str(
+        r#"
+        kind = "Other"
+        id = "11111111111111111111111111111111"
+        idl = { path = "idl/dex.json", account = "Pool", mint_a = "a", mint_b = "b" }
+        "#,
+    )
+    .expect("program config");
+    assert!(program.snapshot);
+    let idl = program.idl.expect("idl");
+    assert_eq!(idl.fee_denominator, 1_000_000);
+    assert_eq!(idl.account, "Pool");
+}
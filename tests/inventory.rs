@@ -0,0 +1,136 @@
+mod common;
+
+use common::{pool, position};
+use pool_watcher::liquidity::TickArray;
+use pool_watcher::types::DexKind;
+use pool_watcher::Inventory;
+use solana_sdk::pubkey::Pubkey;
+
+#[test]
+fn test_record_slot_drops_stale_updates() {
+    let inventory = Inventory::default();
+    let account = Pubkey::new_unique();
+    assert_eq!(inventory.slot(&account), None);
+
+    assert!(inventory.record_slot(account, 100));
+    // several updates can land in one slot
+    assert!(inventory.record_slot(account, 100));
+    assert!(inventory.record_slot(account, 120));
+    // a snapshot read before the last websocket update
+    assert!(!inventory.record_slot(account, 110));
+    assert_eq!(inventory.slot(&account), Some(120));
+
+    assert!(inventory.record_slot(Pubkey::new_unique(), 1));
+}
+
+#[test]
+fn test_record_update_orders_writes_within_a_slot() {
+    let inventory = Inventory::default();
+    let account = Pubkey::new_unique();
+    assert!(inventory.record_update(account, 100, Some(7)));
+    assert!(!inventory.record_update(account, 100, Some(6)));
+    assert!(inventory.record_update(account, 100, Some(8)));
+    // an update without write version, e.g. from RPC, keeps the last one
+    assert!(inventory.record_update(account, 100, None));
+    assert!(!inventory.record_update(account, 100, Some(7)));
+    // a later slot wins whatever its write version
+    assert!(inventory.record_update(account, 101, Some(1)));
+    assert!(!inventory.record_update(account, 100, Some(9)));
+    assert_eq!(inventory.slot(&account), Some(101));
+}
+
+#[test]
+fn test_deleted_accounts_keep_their_slot_until_pruned() {
+    let inventory = Inventory::default();
+    let program = Pubkey::new_unique();
+    let (closed, open) = (pool(program), pool(program));
+    let closed_position = position(closed.id.account, None, (-10, 10), 100);
+    for (account, slot) in [(closed.id.account, 100), (open.id.account, 100), (closed_position.address, 90)] {
+        assert!(inventory.record_slot(account, slot));
+    }
+    inventory.upsert(closed.clone());
+    inventory.upsert(open.clone());
+    inventory.upsert_position(closed_position.clone());
+
+    assert!(inventory.record_slot(closed.id.account, 120));
+    assert!(inventory.remove(&closed.id).is_some());
+    // a read from before the deletion is still stale
+    assert!(!inventory.record_slot(closed.id.account, 110));
+    assert!(!inventory.record_slot(closed_position.address, 80));
+
+    // a resync read at slot 120 prunes what was deleted before it
+    inventory.prune_tombstones(120);
+    assert_eq!(inventory.slot(&closed.id.account), Some(120));
+    assert_eq!(inventory.slot(&closed_position.address), None);
+    inventory.prune_tombstones(121);
+    assert_eq!(inventory.slot(&closed.id.account), None);
+    // stored accounts keep their slot
+    assert_eq!(inventory.slot(&open.id.account), Some(100));
+}
+
+#[test]
+fn test_remove_prunes_pool_state() {
+    let inventory = Inventory::default();
+    let program = Pubkey::new_unique();
+    let (closed, open) = (pool(program), pool(program));
+    inventory.upsert(closed.clone());
+    inventory.upsert(open.clone());
+    let dropped = position(closed.id.account, None, (-10, 10), 100);
+    inventory.upsert_position(dropped.clone());
+    let kept = position(open.id.account, None, (-10, 10), 100);
+    inventory.upsert_position(kept.clone());
+    assert_eq!(inventory.program_pools(&program).len(), 2);
+
+    assert!(inventory.remove(&closed.id).is_some());
+    assert!(inventory.remove(&closed.id).is_none());
+    assert!(inventory.positions(&closed.id.account).is_empty());
+    assert!(!inventory.contains_account(&dropped.address));
+    assert!(inventory.remove_position(&dropped.address).is_none());
+    let pools = inventory.program_pools(&program);
+    assert_eq!(pools.len(), 1);
+    assert_eq!(pools[0].id.account, open.id.account);
+
+    assert_eq!(inventory.remove_position(&kept.address).map(|p| p.pool), Some(open.id.account));
+    assert!(inventory.positions(&open.id.account).is_empty());
+    assert!(inventory.program_pools(&Pubkey::new_unique()).is_empty());
+}
+
+#[test]
+fn test_contains_positions_and_tick_arrays() {
+    let inventory = Inventory::default();
+    let open = pool(Pubkey::new_unique());
+    let kept = position(open.id.account, None, (-10, 10), 100);
+    inventory.upsert_position(kept.clone());
+    let address = Pubkey::new_unique();
+    let array = TickArray { dex: DexKind::OrcaWhirlpools, pool: open.id.account, start_tick_index: -88, ticks: Vec::new() };
+    inventory.upsert_tick_array(address, array);
+
+    assert!(inventory.contains_account(&kept.address));
+    assert!(inventory.contains_account(&address));
+    assert!(!inventory.contains_account(&Pubkey::new_unique()));
+
+    assert_eq!(inventory.remove_tick_array(&address).map(|a| a.start_tick_index), Some(-88));
+    assert!(!inventory.contains_account(&address));
+    assert!(inventory.tick_arrays(&open.id.account).is_empty());
+    assert!(inventory.remove_tick_array(&address).is_none());
+}
+
+#[test]
+fn test_evict_idle_pools() {
+    let inventory = Inventory::default();
+    let program = Pubkey::new_unique();
+    let (idle, active) = (pool(program), pool(program));
+    for (info, slot) in [(&idle, 100), (&active, 10_000)] {
+        assert!(inventory.record_slot(info.id.account, slot));
+        inventory.upsert(info.clone());
+    }
+
+    assert_eq!(inventory.evict_idle(&program, 10_000), 0);
+    assert_eq!(inventory.evict_idle(&program, 9_000), 1);
+    let pools = inventory.program_pools(&program);
+    assert_eq!(pools.len(), 1);
+    assert_eq!(pools[0].id.account, active.id.account);
+    // evicted pools leave no tombstone, so any later update brings them back
+    assert_eq!(inventory.slot(&idle.id.account), None);
+    assert!(inventory.record_slot(idle.id.account, 50));
+}
@@ -0,0 +1,195 @@
+use pool_watcher::decoders::{orca_whirl, raydium_clmm, DecodeOutcome};
+use pool_watcher::liquidity::{self, tick_array_start, LiquidityRange};
+use pool_watcher::types::{DexKind, PoolId, PoolInfo};
+use pool_watcher::Inventory;
+use solana_sdk::pubkey::Pubkey;
+
+const LIQUIDITY: u128 = 1_000_000_000;
+
+fn whirlpool(pool: Pubkey) -> PoolInfo {
+    PoolInfo {
+        dex: DexKind::OrcaWhirlpools,
+        id: PoolId { program: orca_whirl::PROGRAM_ID, account: pool },
+        tick_spacing: Some(1),
+        tick_current: Some(0),
+        sqrt_price_x64: Some(1u128 << 64),
+        liquidity: Some(LIQUIDITY),
+        ..Default::default()
+    }
+}
+
+// one position over [-10, 10] in the array starting at -88
+fn whirlpool_tick_array(pool: Pubkey) -> Vec<u8> {
+    let mut data = vec![0u8; orca_whirl::TICK_ARRAY_LEN];
+    data[..8].copy_from_slice(&orca_whirl::ACCOUNT_TYPES[8].0);
+    data[8..12].copy_from_slice(&(-88i32).to_le_bytes());
+    let at = 12 + 78 * 113;
+    data[at] = 1;
+    data[at + 1..at + 17].copy_from_slice(&(LIQUIDITY as i128).to_le_bytes());
+    data[9956..9988].copy_from_slice(pool.as_ref());
+    data
+}
+
+fn raydium_tick_array(pool: Pubkey, start: i32, ticks: &[(usize, i128)]) -> Vec<u8> {
+    let mut data = vec![0u8; raydium_clmm::TICK_ARRAY_LEN];
+    data[..8].copy_from_slice(&raydium_clmm::ACCOUNT_TYPES[2].0);
+    data[8..40].copy_from_slice(pool.as_ref());
+    data[40..44].copy_from_slice(&start.to_le_bytes());
+    for &(offset, net) in ticks {
+        let at = 44 + offset * 168;
+        data[at..at + 4].copy_from_slice(&(start + offset as i32).to_le_bytes());
+        data[at + 4..at + 20].copy_from_slice(&net.to_le_bytes());
+        data[at + 20..at + 36].copy_from_slice(&net.unsigned_abs().to_le_bytes());
+    }
+    data
+}
+
+#[test]
+fn test_whirlpool_tick_array_decode() {
+    let pool = Pubkey::new_unique();
+    let data = whirlpool_tick_array(pool);
+    let array = orca_whirl::parse_tick_array(&data).expect("tick array");
+    assert_eq!(array.pool, pool);
+    assert_eq!(array.start_tick_index, -88);
+    assert_eq!(array.ticks.len(), 1);
+    assert_eq!((array.ticks[0].offset, array.ticks[0].liquidity_net), (78, LIQUIDITY as i128));
+
+    let outcome = orca_whirl::decode_account(orca_whirl::PROGRAM_ID, Pubkey::new_unique(), &data);
+    assert!(matches!(outcome, DecodeOutcome::TickArray(a) if a.start_tick_index == -88));
+    assert!(orca_whirl::parse_tick_array(&data[..9000]).is_none());
+}
+
+// the dynamic layout of the same array
+fn whirlpool_dynamic_tick_array(pool: Pubkey) -> Vec<u8> {
+    let mut data = vec![0u8; orca_whirl::DYNAMIC_TICK_ARRAY_MIN_LEN + 112];
+    data[..8].copy_from_slice(&orca_whirl::ACCOUNT_TYPES[9].0);
+    data[8..12].copy_from_slice(&(-88i32).to_le_bytes());
+    data[12..44].copy_from_slice(pool.as_ref());
+    data[44 + 78 / 8] = 1 << (78 % 8);
+    let at = 60 + 78;
+    data[at] = 1;
+    data[at + 1..at + 17].copy_from_slice(&(LIQUIDITY as i128).to_le_bytes());
+    data
+}
+
+#[test]
+fn test_whirlpool_dynamic_tick_array_decode() {
+    let pool = Pubkey::new_unique();
+    let data = whirlpool_dynamic_tick_array(pool);
+    let array = orca_whirl::parse_tick_array(&data).expect("dynamic tick array");
+    assert_eq!((array.pool, array.start_tick_index), (pool, -88));
+    assert_eq!(array.ticks.len(), 1);
+    assert_eq!((array.ticks[0].offset, array.ticks[0].liquidity_net), (78, LIQUIDITY as i128));
+
+    let outcome = orca_whirl::decode_account(orca_whirl::PROGRAM_ID, Pubkey::new_unique(), &data);
+    assert!(matches!(outcome, DecodeOutcome::TickArray(a) if a.ticks.len() == 1));
+    // the initialized tick's data is cut off
+    assert!(orca_whirl::parse_tick_array(&data[..data.len() - 1]).is_none());
+}
+
+#[test]
+fn test_raydium_tick_array_decode() {
+    let pool = Pubkey::new_unique();
+    let data = raydium_tick_array(pool, -60, &[(50, LIQUIDITY as i128)]);
+    let array = raydium_clmm::parse_tick_array(&data).expect("tick array");
+    assert_eq!(array.dex, DexKind::RaydiumClmm);
+    assert_eq!(array.pool, pool);
+    assert_eq!(array.start_tick_index, -60);
+    assert_eq!(array.ticks.len(), 1);
+    assert_eq!((array.ticks[0].offset, array.ticks[0].liquidity_net), (50, LIQUIDITY as i128));
+}
+
+#[test]
+fn test_raydium_tick_array_bitmap_extension() {
+    let mut data = vec![0u8; raydium_clmm::TICK_ARRAY_BITMAP_EXTENSION_LEN];
+    data[..8].copy_from_slice(&raydium_clmm::ACCOUNT_TYPES[3].0);
+    // first positive array past the pool bitmap, and the array just below -30720
+    data[40..48].copy_from_slice(&1u64.to_le_bytes());
+    let negative = 40 + 14 * 64 + 7 * 8;
+    data[negative..negative + 8].copy_from_slice(&(1u64 << 63).to_le_bytes());
+    let ext = raydium_clmm::parse_tick_array_bitmap_extension(&data).expect("extension");
+
+    assert_eq!(ext.is_initialized(0, 1), None);
+    assert_eq!(ext.is_initialized(30_720, 1), Some(true));
+    assert_eq!(ext.is_initialized(30_780, 1), Some(false));
+    assert_eq!(ext.is_initialized(-30_780, 1), Some(true));
+    assert_eq!(ext.is_initialized(-30_840, 1), Some(false));
+
+    let outcome = raydium_clmm::decode_account(raydium_clmm::PROGRAM_ID, Pubkey::new_unique(), &data);
+    let DecodeOutcome::TickArrayBitmap(ext) = outcome else { panic!("bitmap extension not routed") };
+    let inventory = Inventory::default();
+    inventory.upsert_tick_array_bitmap(*ext);
+    let stored = inventory.tick_array_bitmap(&Pubkey::default()).expect("stored per pool");
+    assert_eq!(stored.is_initialized(30_720, 1), Some(true));
+}
+
+#[test]
+fn test_tick_array_start() {
+    assert_eq!(tick_array_start(DexKind::OrcaWhirlpools, -1, 64), Some(-5632));
+    assert_eq!(tick_array_start(DexKind::OrcaWhirlpools, 5632, 64), Some(5632));
+    assert_eq!(tick_array_start(DexKind::RaydiumClmm, 100, 10), Some(0));
+    assert_eq!(tick_array_start(DexKind::RaydiumClmm, -601, 10), Some(-1200));
+    assert_eq!(tick_array_start(DexKind::RaydiumCpmm, 0, 1), None);
+}
+
+#[test]
+fn test_liquidity_distribution_ranges_and_depth() {
+    let pool = Pubkey::new_unique();
+    let info = whirlpool(pool);
+    let lower = orca_whirl::parse_tick_array(&whirlpool_tick_array(pool)).expect("tick array");
+    let mut upper = lower.clone();
+    upper.start_tick_index = 0;
+    upper.ticks[0].offset = 10;
+    upper.ticks[0].liquidity_net = -(LIQUIDITY as i128);
+
+    let dist = liquidity::distribution(&info, [&lower, &upper]).expect("distribution");
+    assert_eq!(dist.covered, (-88, 88));
+    assert_eq!(
+        dist.ranges(),
+        vec![
+            LiquidityRange { tick_lower: -10, tick_upper: 0, liquidity: LIQUIDITY },
+            LiquidityRange { tick_lower: 0, tick_upper: 10, liquidity: LIQUIDITY },
+        ]
+    );
+
+    // the position ends well inside a 2% move, so depth stops at its bounds
+    let depth = dist.depth(0.02);
+    let l = LIQUIDITY as f64;
+    assert!((depth.base_up - l * (1.0 - 1.0001f64.powf(-5.0))).abs() < 1.0);
+    assert!((depth.quote_down - l * (1.0 - 1.0001f64.powf(-5.0))).abs() < 1.0);
+
+    // a narrow move stays inside the position
+    let depth = dist.depth(0.0002);
+    assert!((depth.base_up - l * (1.0 - 1.0 / 1.0002f64.sqrt())).abs() < 1.0);
+}
+
+#[test]
+fn test_inventory_builds_distribution_from_tick_arrays() {
+    let pool = Pubkey::new_unique();
+    let mut info = whirlpool(pool);
+    info.dex = DexKind::RaydiumClmm;
+    info.id.program = raydium_clmm::PROGRAM_ID;
+    let inventory = Inventory::default();
+    assert!(inventory.liquidity_distribution(&info, 0.0002).is_none());
+
+    let array = raydium_clmm::parse_tick_array(&raydium_tick_array(pool, -60, &[(50, LIQUIDITY as i128)]))
+        .expect("tick array");
+    inventory.upsert_tick_array(Pubkey::new_unique(), array);
+    let other = raydium_clmm::parse_tick_array(&raydium_tick_array(Pubkey::new_unique(), 0, &[])).expect("tick array");
+    inventory.upsert_tick_array(Pubkey::new_unique(), other);
+    // a 0.02% move reaches the array above the price, which is missing
+    assert!(inventory.liquidity_distribution(&info, 0.0002).is_none());
+
+    let upper = raydium_clmm::parse_tick_array(&raydium_tick_array(pool, 0, &[])).expect("tick array");
+    inventory.upsert_tick_array(Pubkey::new_unique(), upper);
+    let dist = inventory.liquidity_distribution(&info, 0.0002).expect("distribution");
+    assert_eq!(dist.covered, (-60, 60));
+    assert_eq!(
+        dist.ranges(),
+        vec![
+            LiquidityRange { tick_lower: -10, tick_upper: 0, liquidity: LIQUIDITY },
+            LiquidityRange { tick_lower: 0, tick_upper: 60, liquidity: LIQUIDITY },
+        ]
+    );
+    assert!(inventory.liquidity_distribution(&info, 0.02).is_none());
+}
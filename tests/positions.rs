@@ -0,0 +1,167 @@
+mod common;
+
+use std::collections::HashMap;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use common::{position, serve_rpc, ui_account};
+use pool_watcher::decoders::{orca_whirl, raydium_clmm, DecodeOutcome};
+use pool_watcher::positions::{concentration, resolve_owners, Position, MAX_OWNER_LOOKUPS};
+use pool_watcher::types::DexKind;
+use pool_watcher::Inventory;
+use serde_json::{json, Value};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use tokio::net::TcpListener;
+
+fn whirlpool_position(pool: Pubkey, mint: Pubkey, liquidity: u128) -> Vec<u8> {
+    let mut data = vec![0u8; orca_whirl::POSITION_LEN];
+    data[..8].copy_from_slice(&orca_whirl::ACCOUNT_TYPES[6].0);
+    data[8..40].copy_from_slice(pool.as_ref());
+    data[40..72].copy_from_slice(mint.as_ref());
+    data[72..88].copy_from_slice(&liquidity.to_le_bytes());
+    data[88..92].copy_from_slice(&(-128i32).to_le_bytes());
+    data[92..96].copy_from_slice(&256i32.to_le_bytes());
+    data
+}
+
+#[test]
+fn test_whirlpool_position_decode() {
+    let (pool, mint, address) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let data = whirlpool_position(pool, mint, 5_000);
+    let p = orca_whirl::parse_position(address, &data).expect("position");
+    assert_eq!((p.address, p.pool, p.position_mint), (address, pool, mint));
+    assert_eq!((p.tick_lower, p.tick_upper, p.liquidity), (-128, 256, 5_000));
+    assert_eq!(p.owner, None);
+
+    let outcome = orca_whirl::decode_account(orca_whirl::PROGRAM_ID, address, &data);
+    assert!(matches!(outcome, DecodeOutcome::Position(p) if p.pool == pool));
+    assert!(orca_whirl::parse_position(address, &data[..200]).is_none());
+}
+
+#[test]
+fn test_raydium_personal_position_decode() {
+    let (pool, mint, address) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let mut data = vec![0u8; raydium_clmm::PERSONAL_POSITION_LEN];
+    data[..8].copy_from_slice(&raydium_clmm::ACCOUNT_TYPES[4].0);
+    data[9..41].copy_from_slice(mint.as_ref());
+    data[41..73].copy_from_slice(pool.as_ref());
+    data[73..77].copy_from_slice(&(-60i32).to_le_bytes());
+    data[77..81].copy_from_slice(&60i32.to_le_bytes());
+    data[81..97].copy_from_slice(&7_000u128.to_le_bytes());
+
+    let p = raydium_clmm::parse_personal_position(address, &data).expect("position");
+    assert_eq!(p.dex, DexKind::RaydiumClmm);
+    assert_eq!((p.pool, p.position_mint), (pool, mint));
+    assert_eq!((p.tick_lower, p.tick_upper, p.liquidity), (-60, 60, 7_000));
+
+    let outcome = raydium_clmm::decode_account(raydium_clmm::PROGRAM_ID, address, &data);
+    assert!(matches!(outcome, DecodeOutcome::Position(p) if p.liquidity == 7_000));
+    assert!(matches!(
+        raydium_clmm::decode_account(raydium_clmm::PROGRAM_ID, address, &data[..100]),
+        DecodeOutcome::Invalid { account_type: "PersonalPositionState" }
+    ));
+}
+
+#[test]
+fn test_concentration_flags_single_lp() {
+    let pool = Pubkey::new_unique();
+    let (whale, small) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let positions = vec![
+        position(pool, Some(whale), (-100, 100), 600),
+        position(pool, Some(whale), (-10, 10), 390),
+        position(pool, Some(small), (-100, 100), 10),
+        // out of range at tick 0, and ignored while in-range liquidity exists
+        position(pool, Some(small), (200, 300), 10_000),
+        position(pool, None, (-100, 100), 0),
+    ];
+    let c = concentration(&positions, Some(0)).expect("concentration");
+    assert_eq!((c.positions, c.owners), (3, 2));
+    assert_eq!(c.top_owner, Some(whale));
+    assert!((c.top_share - 0.99).abs() < 1e-9);
+    assert!(c.single_lp());
+
+    // without a current tick every funded position counts
+    let c = concentration(&positions, None).expect("concentration");
+    assert_eq!(c.top_owner, Some(small));
+    assert!(!c.single_lp());
+
+    // unresolved owners count as separate holders
+    let unresolved = vec![position(pool, None, (-10, 10), 50), position(pool, None, (-10, 10), 50)];
+    let c = concentration(&unresolved, Some(0)).expect("concentration");
+    assert_eq!((c.owners, c.top_owner), (2, None));
+    assert!(concentration(&[], Some(0)).is_none());
+}
+
+#[test]
+fn test_inventory_keeps_resolved_owner() {
+    let pool = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let inventory = Inventory::default();
+    let mut p = position(pool, Some(owner), (-10, 10), 100);
+    inventory.upsert_position(p.clone());
+
+    // a liquidity change arrives without the owner
+    p.owner = None;
+    p.liquidity = 50;
+    inventory.upsert_position(p.clone());
+    let stored = inventory.positions(&pool);
+    assert_eq!(stored.len(), 1);
+    assert_eq!((stored[0].liquidity, stored[0].owner), (50, Some(owner)));
+    assert!(inventory.positions(&Pubkey::new_unique()).is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_resolve_owners_of_largest_positions() {
+    let pool = Pubkey::new_unique();
+    // one position more than are looked up, the largest last
+    let mut positions: Vec<Position> =
+        (1..=MAX_OWNER_LOOKUPS as u128 + 1).map(|l| position(pool, None, (-10, 10), l)).collect();
+    let largest = positions[MAX_OWNER_LOOKUPS].clone();
+    let broken = positions[MAX_OWNER_LOOKUPS - 1].clone();
+    let smallest = positions[0].clone();
+    let (holder, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut token_account = vec![0u8; 165];
+    token_account[32..64].copy_from_slice(owner.as_ref());
+    let token_account = ui_account(&token_account);
+
+    let lookups: Arc<HashMap<String, Value>> = Arc::new(HashMap::from([
+        (
+            largest.position_mint.to_string(),
+            json!([{ "address": holder.to_string(), "amount": "1", "decimals": 0, "uiAmount": 1.0, "uiAmountString": "1" }]),
+        ),
+        // not a list of token accounts, so the lookup fails
+        (broken.position_mint.to_string(), json!("garbage")),
+    ]));
+    let requests = Arc::new(AtomicUsize::new(0));
+    let rpc = TcpListener::bind("127.0.0.1:0").await.expect("bind rpc");
+    let url = format!("http://{}", rpc.local_addr().expect("rpc addr"));
+    let counted = requests.clone();
+    tokio::spawn(serve_rpc(
+        rpc,
+        Arc::new(move |request| match request["method"].as_str() {
+            Some("getTokenLargestAccounts") => {
+                counted.fetch_add(1, Ordering::SeqCst);
+                let mint = request["params"][0].as_str().unwrap_or_default();
+                let value = lookups.get(mint).cloned().unwrap_or_else(|| json!([]));
+                json!({ "context": { "slot": 1 }, "value": value })
+            }
+            Some("getMultipleAccounts") => json!({ "context": { "slot": 1 }, "value": [token_account.clone()] }),
+            _ => Value::Null,
+        }),
+    ));
+
+    let positions = tokio::task::spawn_blocking(move || {
+        resolve_owners(&RpcClient::new(url), &mut positions).expect("resolve owners");
+        positions
+    })
+    .await
+    .expect("blocking task");
+    assert_eq!(requests.load(Ordering::SeqCst), MAX_OWNER_LOOKUPS);
+    let owner_of = |p: &Position| positions.iter().find(|q| q.address == p.address).and_then(|q| q.owner);
+    assert_eq!(owner_of(&largest), Some(owner));
+    assert_eq!(owner_of(&broken), None);
+    assert_eq!(owner_of(&smallest), None);
+}
@@ -0,0 +1,118 @@
+mod common;
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use common::{serve_rpc, ui_account, watcher_config, whirlpool_data, whirlpool_program, NoToken2022, RpcHandler};
+use futures::{SinkExt, StreamExt};
+use pool_watcher::{
+    PoolBus, PoolEvent, PoolField, PoolWatcher, PoolWatcherConfig, ReconnectConfig,
+};
+use serde_json::{json, Value};
+use solana_sdk::pubkey::Pubkey;
+use tokio::{
+    net::TcpListener,
+    sync::broadcast,
+    time::{timeout, Duration},
+};
+use tokio_tungstenite::{accept_async, tungstenite::Message};
+
+/// Pubsub server that delivers `pool` on the first program subscription, then
+/// drops the connection. Later subscriptions stay open without notifications.
+async fn serve_ws(listener: TcpListener, pool: Pubkey, program_subscriptions: Arc<AtomicUsize>) {
+    loop {
+        let Ok((stream, _)) = listener.accept().await else { return };
+        let subscriptions = program_subscriptions.clone();
+        tokio::spawn(async move {
+            let Ok(mut ws) = accept_async(stream).await else { return };
+            while let Some(Ok(Message::Text(text))) = ws.next().await {
+                let request: Value = serde_json::from_str(&text).expect("request");
+                let reply = json!({ "jsonrpc": "2.0", "result": 7, "id": request["id"] });
+                ws.send(Message::Text(reply.to_string())).await.expect("reply");
+                if request["method"] != "programSubscribe" || subscriptions.fetch_add(1, Ordering::SeqCst) > 0 {
+                    continue;
+                }
+                let notification = json!({
+                    "jsonrpc": "2.0",
+                    "method": "programNotification",
+                    "params": {
+                        "result": {
+                            "context": { "slot": 100 },
+                            "value": { "pubkey": pool.to_string(), "account": ui_account(&whirlpool_data(1_000)) },
+                        },
+                        "subscription": 7,
+                    },
+                });
+                ws.send(Message::Text(notification.to_string())).await.expect("notification");
+                let _ = ws.close(None).await;
+                return;
+            }
+        });
+    }
+}
+
+/// `getMultipleAccounts` returns the pool as it is after the outage, every
+/// other request a slot.
+fn after_outage(pool: Pubkey) -> RpcHandler {
+    Arc::new(move |request| match request["method"].as_str() {
+        Some("getMultipleAccounts") => {
+            let accounts: Vec<Value> = request["params"][0]
+                .as_array()
+                .expect("keys")
+                .iter()
+                .map(|key| match key.as_str() == Some(&pool.to_string()) {
+                    true => ui_account(&whirlpool_data(2_000)),
+                    false => Value::Null,
+                })
+                .collect();
+            json!({ "context": { "slot": 200 }, "value": accounts })
+        }
+        _ => json!(200),
+    })
+}
+
+async fn next_pool_event(rx: &mut broadcast::Receiver<PoolEvent>) -> PoolEvent {
+    loop {
+        match rx.recv().await.expect("bus") {
+            ev @ (PoolEvent::AccountNew { .. } | PoolEvent::AccountChanged { .. }) => return ev,
+            _ => continue,
+        }
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_program_subscription_reconnects_and_backfills() {
+    let pool = Pubkey::new_unique();
+    let subscriptions = Arc::new(AtomicUsize::new(0));
+    let ws = TcpListener::bind("127.0.0.1:0").await.expect("bind ws");
+    let rpc = TcpListener::bind("127.0.0.1:0").await.expect("bind rpc");
+    let cfg = PoolWatcherConfig {
+        rpc_url: format!("http://{}", rpc.local_addr().expect("rpc addr")),
+        ws_url: format!("ws://{}", ws.local_addr().expect("ws addr")),
+        reconnect: ReconnectConfig { initial_ms: 50, max_ms: 200 },
+        ..watcher_config(vec![whirlpool_program(false)])
+    };
+    tokio::spawn(serve_ws(ws, pool, subscriptions.clone()));
+    tokio::spawn(serve_rpc(rpc, after_outage(pool)));
+
+    let bus = Arc::new(PoolBus::new(64));
+    let mut rx = bus.subscribe();
+    PoolWatcher::new(cfg, bus, Arc::new(NoToken2022)).spawn();
+
+    let first = timeout(Duration::from_secs(10), next_pool_event(&mut rx)).await.expect("live update");
+    assert!(matches!(first, PoolEvent::AccountNew { ref info, slot: 100, .. } if info.id.account == pool));
+
+    // the change made while disconnected arrives through the backfill
+    let second = timeout(Duration::from_secs(10), next_pool_event(&mut rx)).await.expect("backfill");
+    match second {
+        PoolEvent::AccountChanged { info, slot, changed, .. } => {
+            assert_eq!(info.liquidity, Some(2_000));
+            assert_eq!(slot, 200);
+            assert_eq!(changed, vec![PoolField::Liquidity]);
+        }
+        other => panic!("unexpected event: {:?}", other),
+    }
+    assert!(subscriptions.load(Ordering::SeqCst) >= 2);
+}
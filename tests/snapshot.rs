@@ -0,0 +1,190 @@
+mod common;
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use common::{serve_rpc, ui_account, watcher_config, whirlpool_data, whirlpool_program, NoToken2022};
+use pool_watcher::decoders::{orca_whirl, DecodeOutcome};
+use pool_watcher::{
+    PoolBus, PoolEvent, PoolWatcher, PoolWatcherConfig, ReconnectConfig, SnapshotConfig,
+};
+use serde_json::{json, Value};
+use solana_sdk::pubkey::Pubkey;
+use tokio::{
+    net::TcpListener,
+    time::{timeout, Duration},
+};
+
+/// Base58 of the first byte of the mint at offset 101 of [`whirlpool_data`].
+const POOL_SHARD: &str = "2";
+
+fn in_pool_shard(request: &Value) -> bool {
+    request["params"][1]["filters"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .any(|filter| filter["memcmp"]["offset"] == 101 && filter["memcmp"]["bytes"] == POOL_SHARD)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_sharded_snapshot_publishes_and_prunes() {
+    let (pool, closed) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let shards = Arc::new(AtomicUsize::new(0));
+    let rpc = TcpListener::bind("127.0.0.1:0").await.expect("bind rpc");
+    let cfg = PoolWatcherConfig {
+        rpc_url: format!("http://{}", rpc.local_addr().expect("rpc addr")),
+        // subscriptions keep failing; only the snapshot is under test
+        ws_url: "ws://127.0.0.1:1".into(),
+        reconnect: ReconnectConfig { initial_ms: 1_000, max_ms: 1_000 },
+        snapshot: SnapshotConfig { shard_prefix_bytes: 1, concurrency: 16, ..SnapshotConfig::default() },
+        ..watcher_config(vec![whirlpool_program(true)])
+    };
+    let requests = shards.clone();
+    tokio::spawn(serve_rpc(
+        rpc,
+        Arc::new(move |request| match request["method"].as_str() {
+            Some("getProgramAccounts") => {
+                requests.fetch_add(1, Ordering::SeqCst);
+                let accounts = match in_pool_shard(request) {
+                    true => vec![json!({ "pubkey": pool.to_string(), "account": ui_account(&whirlpool_data(1_000)) })],
+                    false => Vec::new(),
+                };
+                json!({ "context": { "slot": 150 }, "value": accounts })
+            }
+            Some("getMultipleAccounts") => {
+                let keys = request["params"][0].as_array().map_or(0, Vec::len);
+                json!({ "context": { "slot": 150 }, "value": vec![Value::Null; keys] })
+            }
+            _ => json!(140),
+        }),
+    ));
+
+    let bus = Arc::new(PoolBus::new(64));
+    let mut rx = bus.subscribe();
+    let watcher = PoolWatcher::new(cfg, bus, Arc::new(NoToken2022));
+    // a pool seen before and closed since
+    let DecodeOutcome::Pool(info) = orca_whirl::decode_account(orca_whirl::PROGRAM_ID, closed, &whirlpool_data(5))
+    else {
+        panic!("whirlpool");
+    };
+    let inventory = watcher.inventory();
+    inventory.upsert(*info);
+    watcher.spawn();
+
+    let (mut new, mut deleted) = (None, None);
+    let count = timeout(Duration::from_secs(10), async {
+        loop {
+            match rx.recv().await.expect("bus") {
+                PoolEvent::AccountNew { info, slot, .. } => new = Some((info.id.account, slot)),
+                PoolEvent::AccountDeleted { id, slot } => deleted = Some((id.account, slot)),
+                PoolEvent::SnapshotFinished { count, .. } => return count,
+                _ => {}
+            }
+        }
+    })
+    .await
+    .expect("snapshot");
+    assert_eq!(count, 1);
+    assert_eq!(new, Some((pool, 150)));
+    assert_eq!(deleted, Some((closed, 150)));
+    assert_eq!(shards.load(Ordering::SeqCst), 256);
+    assert!(inventory.slot(&closed).is_some_and(|slot| slot >= 150));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_failed_shard_is_read_again() {
+    let pool = Pubkey::new_unique();
+    let failures = Arc::new(AtomicUsize::new(0));
+    let rpc = TcpListener::bind("127.0.0.1:0").await.expect("bind rpc");
+    let cfg = PoolWatcherConfig {
+        rpc_url: format!("http://{}", rpc.local_addr().expect("rpc addr")),
+        ws_url: "ws://127.0.0.1:1".into(),
+        reconnect: ReconnectConfig { initial_ms: 50, max_ms: 50 },
+        snapshot: SnapshotConfig { shard_prefix_bytes: 1, concurrency: 16, shard_attempts: 2 },
+        ..watcher_config(vec![whirlpool_program(true)])
+    };
+    let failed = failures.clone();
+    tokio::spawn(serve_rpc(
+        rpc,
+        Arc::new(move |request| match request["method"].as_str() {
+            // the pool's shard answers garbage to every retry of its first read
+            Some("getProgramAccounts") if in_pool_shard(request) && failed.fetch_add(1, Ordering::SeqCst) < 3 => {
+                json!("not accounts")
+            }
+            Some("getProgramAccounts") => {
+                let accounts = match in_pool_shard(request) {
+                    true => vec![json!({ "pubkey": pool.to_string(), "account": ui_account(&whirlpool_data(1_000)) })],
+                    false => Vec::new(),
+                };
+                json!({ "context": { "slot": 150 }, "value": accounts })
+            }
+            _ => json!(140),
+        }),
+    ));
+
+    let bus = Arc::new(PoolBus::new(64));
+    let mut rx = bus.subscribe();
+    let handle = PoolWatcher::new(cfg, bus, Arc::new(NoToken2022)).spawn();
+    let count = timeout(Duration::from_secs(10), async {
+        loop {
+            if let PoolEvent::SnapshotFinished { count, .. } = rx.recv().await.expect("bus") {
+                return count;
+            }
+        }
+    })
+    .await
+    .expect("snapshot");
+    assert_eq!(count, 1);
+    assert_eq!(failures.load(Ordering::SeqCst), 4);
+    handle.shutdown();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_snapshot_without_context_uses_probed_slot() {
+    let pool = Pubkey::new_unique();
+    let slot_requests = Arc::new(AtomicUsize::new(0));
+    let rpc = TcpListener::bind("127.0.0.1:0").await.expect("bind rpc");
+    let cfg = PoolWatcherConfig {
+        rpc_url: format!("http://{}", rpc.local_addr().expect("rpc addr")),
+        ws_url: "ws://127.0.0.1:1".into(),
+        reconnect: ReconnectConfig { initial_ms: 1_000, max_ms: 1_000 },
+        ..watcher_config(vec![whirlpool_program(true)])
+    };
+    let requests = slot_requests.clone();
+    tokio::spawn(serve_rpc(
+        rpc,
+        Arc::new(move |request| match request["method"].as_str() {
+            // a node ignoring `withContext`
+            Some("getProgramAccounts") => {
+                json!([{ "pubkey": pool.to_string(), "account": ui_account(&whirlpool_data(1_000)) }])
+            }
+            Some("getSlot") => {
+                requests.fetch_add(1, Ordering::SeqCst);
+                json!(140)
+            }
+            _ => json!(null),
+        }),
+    ));
+
+    let bus = Arc::new(PoolBus::new(64));
+    let mut rx = bus.subscribe();
+    PoolWatcher::new(cfg, bus, Arc::new(NoToken2022)).spawn();
+
+    let mut new = None;
+    timeout(Duration::from_secs(10), async {
+        loop {
+            match rx.recv().await.expect("bus") {
+                PoolEvent::AccountNew { info, slot, .. } => new = Some((info.id.account, slot)),
+                PoolEvent::SnapshotFinished { .. } => return,
+                _ => {}
+            }
+        }
+    })
+    .await
+    .expect("snapshot");
+    assert_eq!(new, Some((pool, 140)));
+    // only the startup probe asked for the slot
+    assert_eq!(slot_requests.load(Ordering::SeqCst), 1);
+}
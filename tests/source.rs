@@ -0,0 +1,387 @@
+mod common;
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use common::{watcher_config, whirlpool_data, whirlpool_program, NoToken2022};
+use futures::{
+    channel::mpsc,
+    future::{self, BoxFuture},
+    stream, StreamExt,
+};
+use pool_watcher::decoders::{meteora_damm, orca_whirl, pump_fun};
+use pool_watcher::filters::FilterSet;
+use pool_watcher::source::{AccountSnapshot, AccountUpdate, LogNotification, Subscription};
+use pool_watcher::{AccountSource, DexKind, PoolBus, PoolEvent, PoolWatcher, ProgramConfig, SubscriptionState};
+use solana_sdk::pubkey::Pubkey;
+use tokio::{
+    sync::broadcast,
+    time::{sleep, timeout, Duration},
+};
+
+/// Source replaying a fixed snapshot and whatever is sent on the channels of
+/// each program. Account reads find nothing and are recorded in `fetched`.
+struct MemorySource {
+    snapshot: AccountSnapshot,
+    slot: AtomicU64,
+    accounts: Mutex<HashMap<Pubkey, mpsc::UnboundedReceiver<AccountUpdate>>>,
+    logs: Mutex<HashMap<Pubkey, mpsc::UnboundedReceiver<LogNotification>>>,
+    fetched: Mutex<Vec<Pubkey>>,
+}
+
+impl MemorySource {
+    fn new(snapshot: AccountSnapshot) -> Arc<Self> {
+        Arc::new(Self {
+            snapshot,
+            slot: AtomicU64::new(0),
+            accounts: Mutex::default(),
+            logs: Mutex::default(),
+            fetched: Mutex::default(),
+        })
+    }
+
+    /// Sender feeding the account subscription of `program`. Programs
+    /// without one are subscribed to a silent stream.
+    fn accounts(&self, program: Pubkey) -> mpsc::UnboundedSender<AccountUpdate> {
+        let (tx, rx) = mpsc::unbounded();
+        self.accounts.lock().unwrap().insert(program, rx);
+        tx
+    }
+
+    /// Sender feeding the log subscription of `program`.
+    fn logs(&self, program: Pubkey) -> mpsc::UnboundedSender<LogNotification> {
+        let (tx, rx) = mpsc::unbounded();
+        self.logs.lock().unwrap().insert(program, rx);
+        tx
+    }
+}
+
+impl AccountSource for MemorySource {
+    fn snapshot<'a>(&'a self, _program: &'a Pubkey, _filters: FilterSet) -> BoxFuture<'a, anyhow::Result<AccountSnapshot>> {
+        Box::pin(future::ready(Ok(self.snapshot.clone())))
+    }
+
+    fn subscribe_accounts<'a>(
+        &'a self,
+        program: &'a Pubkey,
+        _filters: Vec<FilterSet>,
+    ) -> BoxFuture<'a, anyhow::Result<Subscription<AccountUpdate>>> {
+        let updates = match self.accounts.lock().unwrap().remove(program) {
+            Some(rx) => rx.boxed(),
+            None => stream::pending().boxed(),
+        };
+        Box::pin(future::ready(Ok(Subscription::new("memory", updates))))
+    }
+
+    fn subscribe_logs<'a>(&'a self, program: &'a Pubkey) -> BoxFuture<'a, anyhow::Result<Subscription<LogNotification>>> {
+        let notifications = match self.logs.lock().unwrap().remove(program) {
+            Some(rx) => rx.boxed(),
+            None => stream::pending().boxed(),
+        };
+        Box::pin(future::ready(Ok(Subscription::new("memory", notifications))))
+    }
+
+    fn get_accounts<'a>(&'a self, keys: &'a [Pubkey]) -> BoxFuture<'a, anyhow::Result<Vec<AccountUpdate>>> {
+        self.fetched.lock().unwrap().extend_from_slice(keys);
+        Box::pin(future::ready(Ok(keys.iter().map(|key| AccountUpdate::closed(*key, 0)).collect())))
+    }
+
+    fn slot(&self) -> BoxFuture<'_, anyhow::Result<u64>> {
+        Box::pin(future::ready(Ok(self.slot.load(Ordering::Relaxed))))
+    }
+}
+
+fn update(key: Pubkey, liquidity: u128, slot: u64) -> AccountUpdate {
+    AccountUpdate {
+        key,
+        lamports: 1_000_000,
+        owner: Some(orca_whirl::PROGRAM_ID),
+        data: whirlpool_data(liquidity),
+        slot,
+        write_version: None,
+    }
+}
+
+/// DAMM v2 pool activated at `point`, a slot for `activation_type` 0 and a
+/// unix timestamp for 1. A non-zero `pool_status` disables swaps.
+fn damm_v2_update(key: Pubkey, activation_type: u8, point: u64, pool_status: u8, slot: u64) -> AccountUpdate {
+    let mut data = vec![0u8; meteora_damm::V2_POOL_LEN];
+    data[..8].copy_from_slice(&meteora_damm::POOL_DISCRIMINATOR);
+    data[472..480].copy_from_slice(&point.to_le_bytes());
+    data[480] = activation_type;
+    data[481] = pool_status;
+    AccountUpdate { key, lamports: 1_000_000, owner: Some(meteora_damm::V2_PROGRAM_ID), data, slot, write_version: None }
+}
+
+/// pump.fun bonding curve, sold out when `complete`.
+fn curve_update(key: Pubkey, complete: bool, slot: u64) -> AccountUpdate {
+    let mut data = vec![0u8; pump_fun::BONDING_CURVE_MIN_LEN];
+    data[..8].copy_from_slice(&pump_fun::BONDING_CURVE_DISCRIMINATOR);
+    data[48] = complete as u8;
+    AccountUpdate { key, lamports: 1_000_000, owner: Some(pump_fun::PROGRAM_ID), data, slot, write_version: None }
+}
+
+fn program(kind: DexKind, id: Pubkey) -> ProgramConfig {
+    ProgramConfig { kind, id, snapshot: false, idl: None, filters: None, mints: Vec::new() }
+}
+
+/// Curve, mint, pool and slot of the next `CurveGraduated` event.
+async fn graduation(rx: &mut broadcast::Receiver<PoolEvent>) -> (Pubkey, Option<Pubkey>, Option<Pubkey>, u64) {
+    timeout(Duration::from_secs(10), async {
+        loop {
+            if let PoolEvent::CurveGraduated { curve, mint, pool, slot } = rx.recv().await.expect("bus open") {
+                assert_eq!(curve.program, pump_fun::PROGRAM_ID);
+                return (curve.account, mint, pool.map(|info| info.id.account), slot);
+            }
+        }
+    })
+    .await
+    .expect("curve graduated")
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).expect("clock").as_secs()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_subscribe_program_from_memory_source() {
+    let (snapshotted, created, unknown) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let source = MemorySource::new(AccountSnapshot { slot: 100, accounts: vec![(snapshotted, whirlpool_data(1_000))] });
+    let (accounts, logs) = (source.accounts(orca_whirl::PROGRAM_ID), source.logs(orca_whirl::PROGRAM_ID));
+    let cfg = watcher_config(vec![whirlpool_program(true)]);
+    for notification in [
+        update(created, 2_000, 110),
+        update(snapshotted, 3_000, 120),
+        // older than the change above, dropped
+        update(snapshotted, 9_000, 115),
+        AccountUpdate { data: vec![9; 16], ..update(unknown, 0, 125) },
+        AccountUpdate::closed(created, 130),
+    ] {
+        accounts.unbounded_send(notification).expect("queue update");
+    }
+    logs.unbounded_send(LogNotification { signature: "sig".into(), slot: 140 }).expect("queue log");
+
+    let bus = Arc::new(PoolBus::new(64));
+    let mut rx = bus.subscribe();
+    let watcher = PoolWatcher::new(cfg, bus, Arc::new(NoToken2022)).with_source(source);
+    let inventory = watcher.inventory();
+    let handle = watcher.spawn();
+
+    let mut seen = Vec::new();
+    timeout(Duration::from_secs(10), async {
+        while seen.len() < 5 {
+            let event = rx.recv().await.expect("bus open");
+            let summary = match event {
+                PoolEvent::AccountNew { info, slot, .. } => ("new", info.id.account, slot),
+                PoolEvent::AccountChanged { info, slot, .. } => ("changed", info.id.account, slot),
+                PoolEvent::AccountDeleted { id, slot } => ("deleted", id.account, slot),
+                PoolEvent::ProgramLog { program, slot, .. } => ("log", program, slot),
+                _ => continue,
+            };
+            seen.push(summary);
+        }
+    })
+    .await
+    .expect("events published");
+
+    let (mut pools, log): (Vec<_>, Vec<_>) = seen.into_iter().partition(|(kind, ..)| *kind != "log");
+    assert_eq!(log, vec![("log", orca_whirl::PROGRAM_ID, 140)]);
+    // the snapshot is published before the subscription starts
+    assert_eq!(pools.remove(0), ("new", snapshotted, 100));
+    assert_eq!(
+        pools,
+        vec![("new", created, 110), ("changed", snapshotted, 120), ("deleted", created, 130)]
+    );
+    let pools = inventory.program_pools(&orca_whirl::PROGRAM_ID);
+    assert_eq!(pools.len(), 1);
+    assert_eq!(pools[0].id.account, snapshotted);
+    assert_eq!(inventory.slot(&snapshotted), Some(120));
+    // the closed pool keeps its slot until the next resync; accounts that are
+    // not stored are not tracked
+    assert_eq!(inventory.slot(&created), Some(130));
+    assert_eq!(inventory.slot(&unknown), None);
+
+    let status = handle.status();
+    assert!(status.endpoints.is_empty());
+    for state in [&status.programs[0].subscription, &status.programs[0].logs] {
+        assert!(matches!(state, SubscriptionState::Connected { endpoint, .. } if endpoint == "memory"));
+    }
+
+    handle.shutdown();
+    timeout(Duration::from_secs(10), async {
+        while handle.is_running() {
+            sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("watcher stopped");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_missing_config_fetched_once() {
+    let pool = Pubkey::new_unique();
+    let source = MemorySource::new(AccountSnapshot { slot: 0, accounts: Vec::new() });
+    let accounts = source.accounts(orca_whirl::PROGRAM_ID);
+    let bus = Arc::new(PoolBus::new(64));
+    let mut rx = bus.subscribe();
+    let watcher = PoolWatcher::new(watcher_config(vec![whirlpool_program(false)]), bus, Arc::new(NoToken2022))
+        .with_source(source.clone());
+    let handle = watcher.spawn();
+
+    // every update references the same config and fee tier, which do not exist
+    let fetched = || source.fetched.lock().unwrap().len();
+    for (liquidity, slot) in [(1_000, 110), (2_000, 120), (3_000, 130)] {
+        accounts.unbounded_send(update(pool, liquidity, slot)).expect("queue update");
+        timeout(Duration::from_secs(10), async {
+            while !matches!(
+                rx.recv().await.expect("bus open"),
+                PoolEvent::AccountNew { slot: seen, .. } | PoolEvent::AccountChanged { slot: seen, .. } if seen == slot
+            ) {}
+            while fetched() == 0 {
+                sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("update published");
+    }
+    sleep(Duration::from_millis(200)).await;
+
+    let config = Pubkey::default();
+    let fee_tier = orca_whirl::fee_tier_address(&config, 64);
+    assert_eq!(*source.fetched.lock().unwrap(), vec![config, fee_tier]);
+    handle.shutdown();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_scheduled_pools_open() {
+    let (by_slot, by_time, by_status) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let source = MemorySource::new(AccountSnapshot { slot: 0, accounts: Vec::new() });
+    let accounts = source.accounts(meteora_damm::V2_PROGRAM_ID);
+    let opens_at = unix_now() + 2;
+    for notification in [
+        damm_v2_update(by_slot, 0, 500, 0, 100),
+        damm_v2_update(by_time, 1, opens_at, 0, 100),
+        damm_v2_update(by_status, 0, 0, 1, 100),
+    ] {
+        accounts.unbounded_send(notification).expect("queue update");
+    }
+    let cfg = watcher_config(vec![program(DexKind::MeteoraDamm, meteora_damm::V2_PROGRAM_ID)]);
+    let bus = Arc::new(PoolBus::new(64));
+    let mut rx = bus.subscribe();
+    let watcher = PoolWatcher::new(cfg, bus, Arc::new(NoToken2022)).with_source(source.clone());
+    let handle = watcher.spawn();
+
+    // none of the pools can be traded yet
+    let mut created = Vec::new();
+    timeout(Duration::from_secs(10), async {
+        while created.len() < 3 {
+            match rx.recv().await.expect("bus open") {
+                PoolEvent::AccountNew { info, .. } => created.push(info.id.account),
+                PoolEvent::PoolOpened { info, .. } => panic!("{} opened early", info.id.account),
+                _ => {}
+            }
+        }
+    })
+    .await
+    .expect("pools published");
+
+    source.slot.store(600, Ordering::Relaxed);
+    accounts.unbounded_send(damm_v2_update(by_status, 0, 0, 0, 120)).expect("queue update");
+    let mut opened = HashMap::new();
+    timeout(Duration::from_secs(10), async {
+        while opened.len() < 3 {
+            if let PoolEvent::PoolOpened { info, slot } = rx.recv().await.expect("bus open") {
+                assert!(info.is_tradeable(unix_now(), 600));
+                opened.insert(info.id.account, slot);
+            }
+        }
+    })
+    .await
+    .expect("pools opened");
+
+    assert!(unix_now() >= opens_at);
+    // the slot is read once a pool scheduled by slot is due; the status flip
+    // opens its pool with the update
+    assert_eq!(opened[&by_slot], 600);
+    assert_eq!(opened[&by_status], 120);
+    assert!(opened.contains_key(&by_time));
+    handle.shutdown();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_curve_graduates_on_complete() {
+    let curve = Pubkey::new_unique();
+    let source = MemorySource::new(AccountSnapshot { slot: 0, accounts: Vec::new() });
+    let curves = source.accounts(pump_fun::PROGRAM_ID);
+    // a curve first seen complete graduated before the watcher started
+    let done = Pubkey::new_unique();
+    for notification in [curve_update(done, true, 90), curve_update(curve, false, 100), curve_update(curve, true, 110)] {
+        curves.unbounded_send(notification).expect("queue update");
+    }
+    let bus = Arc::new(PoolBus::new(64));
+    let mut rx = bus.subscribe();
+    let cfg = watcher_config(vec![program(DexKind::PumpFun, pump_fun::PROGRAM_ID)]);
+    let handle = PoolWatcher::new(cfg, bus, Arc::new(NoToken2022)).with_source(source).spawn();
+
+    assert_eq!(graduation(&mut rx).await, (curve, None, None, 110));
+    handle.shutdown();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_curve_graduates_on_new_pool() {
+    // whirlpool_data pools trade mint [1; 32]
+    let mint = Pubkey::new_from_array([1; 32]);
+    let curve = pump_fun::bonding_curve_address(&mint);
+    let pool = Pubkey::new_unique();
+    let source = MemorySource::new(AccountSnapshot { slot: 0, accounts: Vec::new() });
+    let (curves, pools) = (source.accounts(pump_fun::PROGRAM_ID), source.accounts(orca_whirl::PROGRAM_ID));
+    let bus = Arc::new(PoolBus::new(64));
+    let mut rx = bus.subscribe();
+    let cfg = watcher_config(vec![program(DexKind::PumpFun, pump_fun::PROGRAM_ID), whirlpool_program(false)]);
+    let watcher = PoolWatcher::new(cfg, bus, Arc::new(NoToken2022)).with_source(source);
+    let inventory = watcher.inventory();
+    let handle = watcher.spawn();
+
+    // the curve is only tracked once the subscription delivered it
+    curves.unbounded_send(curve_update(curve, false, 100)).expect("queue curve");
+    timeout(Duration::from_secs(10), async {
+        while !matches!(rx.recv().await.expect("bus open"), PoolEvent::AccountNew { info, .. } if info.id.account == curve) {}
+    })
+    .await
+    .expect("curve published");
+    // a trade only moves the curve's reserves
+    curves.unbounded_send(curve_update(curve, false, 110)).expect("queue trade");
+    timeout(Duration::from_secs(10), async {
+        while inventory.slot(&curve) != Some(110) {
+            sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("trade applied");
+    pools.unbounded_send(update(pool, 1_000, 120)).expect("queue pool");
+
+    let graduated = timeout(Duration::from_secs(10), async {
+        loop {
+            match rx.recv().await.expect("bus open") {
+                PoolEvent::AccountChanged { info, .. } if info.id.account == curve => panic!("curve trade published"),
+                PoolEvent::CurveGraduated { curve, mint, pool, slot } => {
+                    return (curve.account, mint, pool.map(|info| info.id.account), slot)
+                }
+                _ => {}
+            }
+        }
+    })
+    .await
+    .expect("curve graduated");
+    assert_eq!(graduated, (curve, Some(mint), Some(pool), 120));
+    // the curve is dropped once matched to its pool
+    assert!(inventory.program_pools(&pump_fun::PROGRAM_ID).is_empty());
+    assert_eq!(inventory.slot(&curve), None);
+    handle.shutdown();
+}
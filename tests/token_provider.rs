@@ -1,91 +1,74 @@
-#![cfg(feature = "token-tests")]
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::Mutex,
+};
 
-use pool_watcher::token::{MintFetcher, TokenSafetyProvider};
-use pool_watcher::TokenIntrospectionProvider;
-use solana_sdk::{account::Account, pubkey::Pubkey};
-use spl_token::solana_program::program_pack::Pack;
-use spl_token::state as spl_v1;
-use std::str::FromStr;
+use futures::future::{self, BoxFuture};
+use pool_watcher::filters::FilterSet;
+use pool_watcher::source::{AccountSnapshot, AccountUpdate, LogNotification, Subscription};
+use pool_watcher::{AccountSource, TokenIntrospectionProvider, TokenSafetyProvider};
+use solana_sdk::pubkey::Pubkey;
 
-#[derive(Clone)]
-struct MockRpc {
-    account: Account,
+/// Source knowing the owner of a few mints, recording every batch read.
+struct MintSource {
+    owners: HashMap<Pubkey, Pubkey>,
+    reads: Mutex<Vec<Vec<Pubkey>>>,
 }
 
-impl MintFetcher for MockRpc {
-    fn get_account(&self, _mint: &Pubkey) -> anyhow::Result<Account> {
-        Ok(self.account.clone())
+impl AccountSource for MintSource {
+    fn snapshot<'a>(&'a self, _program: &'a Pubkey, _filters: FilterSet) -> BoxFuture<'a, anyhow::Result<AccountSnapshot>> {
+        Box::pin(future::ready(Ok(AccountSnapshot::default())))
     }
 
-    fn get_epoch(&self) -> anyhow::Result<u64> {
-        Ok(0)
+    fn subscribe_accounts<'a>(
+        &'a self,
+        _program: &'a Pubkey,
+        _filters: Vec<FilterSet>,
+    ) -> BoxFuture<'a, anyhow::Result<Subscription<AccountUpdate>>> {
+        Box::pin(future::ready(Err(anyhow::anyhow!("no subscriptions"))))
     }
-}
 
-fn create_v1_mint() -> Account {
-    use spl_token::solana_program::program_option::COption;
-    let mint = spl_v1::Mint {
-        mint_authority: COption::None,
-        supply: 0,
-        decimals: 6,
-        is_initialized: true,
-        freeze_authority: COption::None,
-    };
-    let mut data = vec![0u8; spl_v1::Mint::LEN];
-    spl_v1::Mint::pack(mint, &mut data).unwrap();
-    let program_id = Pubkey::from_str("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA").unwrap();
-    Account {
-        lamports: 0,
-        data,
-        owner: program_id,
-        executable: false,
-        rent_epoch: 0,
+    fn subscribe_logs<'a>(&'a self, _program: &'a Pubkey) -> BoxFuture<'a, anyhow::Result<Subscription<LogNotification>>> {
+        Box::pin(future::ready(Err(anyhow::anyhow!("no subscriptions"))))
     }
-}
 
-fn create_2022_mint_non_transferable() -> Account {
-    let base_len = spl_v1::Mint::LEN;
-    let mut data = vec![0u8; base_len + 4];
-    {
-        use spl_token::solana_program::program_option::COption;
-        let mint = spl_v1::Mint {
-            mint_authority: COption::None,
-            supply: 0,
-            decimals: 6,
-            is_initialized: true,
-            freeze_authority: COption::None,
-        };
-        spl_v1::Mint::pack(mint, &mut data[0..base_len]).unwrap();
+    fn get_accounts<'a>(&'a self, keys: &'a [Pubkey]) -> BoxFuture<'a, anyhow::Result<Vec<AccountUpdate>>> {
+        self.reads.lock().unwrap().push(keys.to_vec());
+        let updates = keys
+            .iter()
+            .map(|key| match self.owners.get(key) {
+                Some(owner) => AccountUpdate {
+                    key: *key,
+                    lamports: 1_000_000,
+                    owner: Some(*owner),
+                    data: vec![0; 82],
+                    slot: 1,
+                    write_version: None,
+                },
+                None => AccountUpdate::closed(*key, 1),
+            })
+            .collect();
+        Box::pin(future::ready(Ok(updates)))
     }
-    // append TLV for NonTransferable
-    data[base_len..base_len + 2].copy_from_slice(&9u16.to_le_bytes());
-    data[base_len + 2..base_len + 4].copy_from_slice(&0u16.to_le_bytes());
-    let program_id = Pubkey::from_str("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb").unwrap();
-    Account {
-        lamports: 0,
-        data,
-        owner: program_id,
-        executable: false,
-        rent_epoch: 0,
+
+    fn slot(&self) -> BoxFuture<'_, anyhow::Result<u64>> {
+        Box::pin(future::ready(Ok(1)))
     }
 }
 
-#[test]
-fn detects_token2022_mints() {
-    let rpc = MockRpc {
-        account: create_2022_mint_non_transferable(),
-    };
-    let provider = TokenSafetyProvider::new(rpc);
-    let mint = Pubkey::new_unique();
-    assert!(provider.is_token2022(&mint).unwrap());
-}
+#[tokio::test]
+async fn reads_unknown_mints_in_one_batch() {
+    let token = Pubkey::from_str("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA").unwrap();
+    let token_2022 = Pubkey::from_str("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb").unwrap();
+    let (v1, v2, missing) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let source = MintSource { owners: HashMap::from([(v1, token), (v2, token_2022)]), reads: Mutex::default() };
+    let provider = TokenSafetyProvider::new();
 
-#[test]
-fn detects_token_v1_mints() {
-    let rpc = MockRpc {
-        account: create_v1_mint(),
-    };
-    let provider = TokenSafetyProvider::new(rpc);
-    let mint = Pubkey::new_unique();
-    assert!(!provider.is_token2022(&mint).unwrap());
+    let flags = provider.token2022_flags(&source, &[v1, v2, missing]).await.unwrap();
+    assert_eq!(flags, vec![false, true, false]);
+    // known mints are cached, missing ones are read again
+    let flags = provider.token2022_flags(&source, &[v2, missing, v1]).await.unwrap();
+    assert_eq!(flags, vec![true, false, false]);
+    assert_eq!(*source.reads.lock().unwrap(), vec![vec![v1, v2, missing], vec![missing]]);
 }